@@ -1,11 +1,18 @@
 use crate::enum_info::EnumVariant;
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a BenEnum, so BenSchema cannot store it",
+    note = "derive BenEnum on enums; a nested BenSchema struct needs #[bschema(nested = \"tuple\")] or #[bschema(nested = \"flatten\")] on the field"
+)]
 pub trait BenEnumDesc: Sized + 'static {
     const BITS: u8;
 
     const VARIANTS: &'static [EnumVariant];
 
-    fn to_i16(self) -> i16;
+    /// `Enum8('label' = value, ...)`, or `Enum16` when `BITS` is 16.
+    const CH_TYPE: &'static str;
+
+    fn to_i16(&self) -> i16;
 
     fn from_i16(v: i16) -> Option<Self>;
 }
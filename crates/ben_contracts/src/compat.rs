@@ -0,0 +1,327 @@
+use std::fmt;
+
+use ben_wire::{
+    error::SchemaError,
+    schema::{Field, FieldType},
+};
+
+use crate::registry::Manifest;
+
+/// Which readers survive a change. Backward: readers on the new version can
+/// read rows written under the old one. Forward: the reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    Full,
+    Backward,
+    Forward,
+    Breaking,
+}
+
+impl Compatibility {
+    /// The weaker of the two guarantees.
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Full, c) | (c, Self::Full) => c,
+            (a, b) if a == b => a,
+            _ => Self::Breaking,
+        }
+    }
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Full => "full",
+            Self::Backward => "backward",
+            Self::Forward => "forward",
+            Self::Breaking => "breaking",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    AddedColumn {
+        name: String,
+        ch_type: String,
+    },
+    DroppedColumn {
+        name: String,
+        ch_type: String,
+    },
+    TypeChanged {
+        name: String,
+        from: String,
+        to: String,
+    },
+    /// A column kept its type but moved; positional encodings misread it.
+    Moved {
+        name: String,
+        from: usize,
+        to: usize,
+    },
+    KeyChanged {
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    OrderByChanged {
+        from: String,
+        to: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::AddedColumn { name, ch_type } => write!(f, "added `{name}` {ch_type}"),
+            Change::DroppedColumn { name, ch_type } => write!(f, "dropped `{name}` {ch_type}"),
+            Change::TypeChanged { name, from, to } => write!(f, "`{name}` {from} -> {to}"),
+            Change::Moved { name, from, to } => write!(f, "`{name}` moved {from} -> {to}"),
+            Change::KeyChanged { from, to } => {
+                write!(f, "key ({}) -> ({})", from.join(", "), to.join(", "))
+            }
+            Change::OrderByChanged { from, to } => write!(f, "order_by ({from}) -> ({to})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassifiedChange {
+    pub change: Change,
+    pub compat: Compatibility,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompatReport {
+    pub table: String,
+    pub from_version: u32,
+    pub to_version: u32,
+    pub changes: Vec<ClassifiedChange>,
+}
+
+impl CompatReport {
+    /// Weakest guarantee over all changes; `Full` when nothing changed.
+    pub fn compat(&self) -> Compatibility {
+        self.changes
+            .iter()
+            .fold(Compatibility::Full, |acc, c| acc.and(c.compat))
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.compat() == Compatibility::Breaking
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompatError {
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+
+    #[error("manifests describe different tables: `{old}` and `{new}`")]
+    TableMismatch { old: String, new: String },
+}
+
+/// Diffs two `__BEN_SCHEMA_JSON` manifests of the same table.
+pub fn check_manifests(old: &str, new: &str) -> Result<CompatReport, CompatError> {
+    let old: Manifest = serde_json::from_str(old)?;
+    let new: Manifest = serde_json::from_str(new)?;
+    if old.table != new.table {
+        return Err(CompatError::TableMismatch {
+            old: old.table,
+            new: new.table,
+        });
+    }
+
+    let old_cols = columns(&old)?;
+    let new_cols = columns(&new)?;
+    let mut changes = Vec::new();
+
+    for (name, ch_type, field) in &old_cols {
+        match new_cols.iter().find(|(n, ..)| n == name) {
+            None => changes.push(ClassifiedChange {
+                compat: if field.nullable {
+                    Compatibility::Full
+                } else {
+                    Compatibility::Backward
+                },
+                change: Change::DroppedColumn {
+                    name: name.clone(),
+                    ch_type: ch_type.clone(),
+                },
+            }),
+            Some((_, new_type, new_field)) if new_field != field => {
+                changes.push(ClassifiedChange {
+                    compat: type_change(field, new_field),
+                    change: Change::TypeChanged {
+                        name: name.clone(),
+                        from: ch_type.clone(),
+                        to: new_type.clone(),
+                    },
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (name, ch_type, field) in &new_cols {
+        if !old_cols.iter().any(|(n, ..)| n == name) {
+            changes.push(ClassifiedChange {
+                compat: if field.nullable {
+                    Compatibility::Full
+                } else {
+                    Compatibility::Forward
+                },
+                change: Change::AddedColumn {
+                    name: name.clone(),
+                    ch_type: ch_type.clone(),
+                },
+            });
+        }
+    }
+
+    // Compare the relative order of the columns both versions share.
+    let kept = |cols: &[(String, String, Field)], other: &[(String, String, Field)]| {
+        cols.iter()
+            .map(|(n, ..)| n.clone())
+            .filter(|n| other.iter().any(|(o, ..)| o == n))
+            .collect::<Vec<_>>()
+    };
+    let old_order = kept(&old_cols, &new_cols);
+    let new_order = kept(&new_cols, &old_cols);
+    for (from, name) in old_order.iter().enumerate() {
+        let to = new_order.iter().position(|n| n == name).unwrap_or(from);
+        if to != from {
+            changes.push(ClassifiedChange {
+                change: Change::Moved {
+                    name: name.clone(),
+                    from,
+                    to,
+                },
+                compat: Compatibility::Breaking,
+            });
+        }
+    }
+
+    // ClickHouse cannot re-sort an existing MergeTree, so both need a rebuild.
+    let (old_key, new_key) = (key_columns(&old), key_columns(&new));
+    if old_key != new_key {
+        changes.push(ClassifiedChange {
+            change: Change::KeyChanged {
+                from: old_key,
+                to: new_key,
+            },
+            compat: Compatibility::Breaking,
+        });
+    }
+    let (old_order_by, new_order_by) = (order_by(&old), order_by(&new));
+    if old_order_by != new_order_by {
+        changes.push(ClassifiedChange {
+            change: Change::OrderByChanged {
+                from: old_order_by,
+                to: new_order_by,
+            },
+            compat: Compatibility::Breaking,
+        });
+    }
+
+    Ok(CompatReport {
+        table: new.table,
+        from_version: old.version,
+        to_version: new.version,
+        changes,
+    })
+}
+
+fn columns(m: &Manifest) -> Result<Vec<(String, String, Field)>, SchemaError> {
+    m.columns
+        .iter()
+        .filter(|c| !c.infra)
+        .map(|c| {
+            let field = Field::from_ch_type(c.name.as_str(), &c.ch_type)?;
+            Ok((c.name.clone(), c.ch_type.clone(), field))
+        })
+        .collect()
+}
+
+fn key_columns(m: &Manifest) -> Vec<String> {
+    m.columns
+        .iter()
+        .filter(|c| c.key && !c.infra)
+        .map(|c| c.name.clone())
+        .collect()
+}
+
+fn order_by(m: &Manifest) -> String {
+    m.order_by
+        .as_deref()
+        .map(|s| s.split_whitespace().collect::<String>())
+        .unwrap_or_else(|| key_columns(m).join(","))
+}
+
+fn type_change(old: &Field, new: &Field) -> Compatibility {
+    let (old_ty, new_ty) = (nullable_ty(old), nullable_ty(new));
+    if old_ty.storage() == new_ty.storage() {
+        Compatibility::Full
+    } else if widens(&old_ty, &new_ty) {
+        Compatibility::Backward
+    } else if widens(&new_ty, &old_ty) {
+        Compatibility::Forward
+    } else {
+        Compatibility::Breaking
+    }
+}
+
+fn nullable_ty(f: &Field) -> FieldType {
+    if f.nullable {
+        FieldType::Nullable(Box::new(f.ty.clone()))
+    } else {
+        f.ty.clone()
+    }
+}
+
+/// Every `from` value is representable as a `to` value.
+fn widens(from: &FieldType, to: &FieldType) -> bool {
+    use FieldType::*;
+
+    let (from, to) = (from.storage(), to.storage());
+    if from == to {
+        return true;
+    }
+    match (from, to) {
+        (UInt8, UInt16 | UInt32 | UInt64 | UInt128 | Int16 | Int32 | Int64 | Float32 | Float64) => {
+            true
+        }
+        (UInt16, UInt32 | UInt64 | UInt128 | Int32 | Int64 | Float32 | Float64) => true,
+        (UInt32, UInt64 | UInt128 | Int64 | Float64) => true,
+        (UInt64, UInt128) => true,
+        (Int8, Int16 | Int32 | Int64 | Float32 | Float64) => true,
+        (Int16, Int32 | Int64 | Float32 | Float64) => true,
+        (Int32, Int64 | Float64) => true,
+        (Float32, Float64) => true,
+        (Date, Date32) => true,
+        // Adding labels keeps every stored value readable.
+        (Enum8(a), Enum8(b)) => a.iter().all(|v| b.contains(v)),
+        (Enum8(a), Enum16(b)) => a
+            .iter()
+            .all(|(label, value)| b.contains(&(label.clone(), (*value).into()))),
+        (Enum16(a), Enum16(b)) => a.iter().all(|v| b.contains(v)),
+        (
+            Decimal {
+                precision: p1,
+                scale: s1,
+            },
+            Decimal {
+                precision: p2,
+                scale: s2,
+            },
+        ) => s2 >= s1 && p2.saturating_sub(*s2) >= p1.saturating_sub(*s1),
+        (Nullable(a), Nullable(b)) | (Array(a), Array(b)) => widens(a, b),
+        (Map(k1, v1), Map(k2, v2)) => widens(k1, k2) && widens(v1, v2),
+        (Nullable(_), _) => false,
+        (a, Nullable(b)) => widens(a, b),
+        _ => false,
+    }
+}
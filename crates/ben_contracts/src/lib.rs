@@ -1,14 +1,21 @@
 pub mod ben_enum_desc;
 pub mod blot;
+pub mod compat;
 pub mod enum_info;
 pub mod enums;
 pub mod lucius;
+pub mod registry;
 pub mod rules;
 pub mod schema;
 
 pub use ben_enum_desc::BenEnumDesc;
 pub use blot::{BlotFieldRule, BlotOp, BlotSpec};
+pub use compat::{CompatReport, Compatibility, check_manifests};
 pub use enum_info::{BEN_ENUM_REGISTRY, EnumInfo};
 pub use enums::BenEnum;
 pub use lucius::{LuciusFieldSpec, LuciusLevel, LuciusSpec};
+pub use registry::{BEN_SCHEMA_REGISTRY, RegistryError, SchemaInfo, SchemaRegistry};
 pub use schema::*;
+
+#[doc(hidden)]
+pub use linkme;
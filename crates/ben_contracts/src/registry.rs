@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use ben_wire::{
+    Encoding, Envelope, Schema,
+    error::{RowpackError, SchemaError},
+    rowpack::decode_rowpack,
+    schema::Field,
+    slot::SlotValue,
+};
+use linkme::distributed_slice;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Link-time entry emitted by `#[derive(BenSchema)]`.
+#[derive(Debug)]
+pub struct SchemaInfo {
+    pub table: &'static str,
+    pub version: u32,
+    pub evt_hash: [u8; 32],
+    /// `(name, ClickHouse type)` in wire order.
+    pub columns: &'static [(&'static str, &'static str)],
+}
+
+impl SchemaInfo {
+    pub fn schema(&self) -> Result<Schema, RegistryError> {
+        let fields = self
+            .columns
+            .iter()
+            .map(|(name, ch_type)| Field::from_ch_type(*name, ch_type))
+            .collect::<Result<_, _>>()?;
+        Ok(Schema {
+            event: self.table.to_string(),
+            version: self.version,
+            evt_hash: self.evt_hash,
+            fields,
+        })
+    }
+}
+
+#[distributed_slice]
+pub static BEN_SCHEMA_REGISTRY: [fn() -> &'static SchemaInfo] = [..];
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    #[error("evt_hash {hash} is already registered for `{existing}`, refusing `{incoming}`")]
+    Conflict {
+        hash: String,
+        existing: String,
+        incoming: String,
+    },
+
+    #[error("no schema registered for evt_hash {0}")]
+    UnknownHash(String),
+
+    #[error("`{event}` has {expected} fields, envelope carries {got}")]
+    FieldCount {
+        event: String,
+        expected: usize,
+        got: u16,
+    },
+
+    #[error("generic decode needs a rowpack payload, got {0:?}")]
+    Encoding(Encoding),
+
+    #[error(transparent)]
+    Rowpack(#[from] RowpackError),
+}
+
+/// Schemas by `evt_hash`, for receivers that have no Rust type for the event.
+#[derive(Debug, Default, Clone)]
+pub struct SchemaRegistry {
+    by_hash: HashMap<[u8; 32], Schema>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every `#[derive(BenSchema)]` type linked into the binary.
+    pub fn linked() -> Result<Self, RegistryError> {
+        let mut reg = Self::new();
+        for f in BEN_SCHEMA_REGISTRY {
+            reg.insert(f().schema()?)?;
+        }
+        Ok(reg)
+    }
+
+    /// Registers `schema`. Registering an identical schema again is a no-op.
+    pub fn insert(&mut self, schema: Schema) -> Result<(), RegistryError> {
+        match self.by_hash.get(&schema.evt_hash) {
+            Some(existing) if *existing == schema => Ok(()),
+            Some(existing) => Err(RegistryError::Conflict {
+                hash: hex(&schema.evt_hash),
+                existing: existing.event.clone(),
+                incoming: schema.event,
+            }),
+            None => {
+                self.by_hash.insert(schema.evt_hash, schema);
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers a `__BEN_SCHEMA_JSON` manifest. The evt_hash is the SHA-256 of
+    /// `json` exactly as given, so pass the manifest through unmodified.
+    pub fn load_manifest_json(&mut self, json: &str) -> Result<[u8; 32], RegistryError> {
+        let manifest: Manifest = serde_json::from_str(json)?;
+        let evt_hash: [u8; 32] = Sha256::digest(json.as_bytes()).into();
+
+        let fields = manifest
+            .columns
+            .iter()
+            .filter(|c| !c.infra)
+            .map(|c| Field::from_ch_type(c.name.as_str(), &c.ch_type))
+            .collect::<Result<_, _>>()?;
+
+        self.insert(Schema {
+            event: manifest.table,
+            version: manifest.version,
+            evt_hash,
+            fields,
+        })?;
+        Ok(evt_hash)
+    }
+
+    pub fn get(&self, evt_hash: &[u8; 32]) -> Option<&Schema> {
+        self.by_hash.get(evt_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Schema> {
+        self.by_hash.values()
+    }
+
+    /// Looks up the envelope's schema and decodes its rowpack payload.
+    pub fn decode<'a>(
+        &self,
+        env: &Envelope<'a>,
+    ) -> Result<(&Schema, Vec<SlotValue<'a>>), RegistryError> {
+        let schema = self
+            .get(&env.evt_hash)
+            .ok_or_else(|| RegistryError::UnknownHash(hex(&env.evt_hash)))?;
+        if env.encoding != Encoding::Rowpack {
+            return Err(RegistryError::Encoding(env.encoding));
+        }
+        if usize::from(env.field_count) != schema.fields_len() {
+            return Err(RegistryError::FieldCount {
+                event: schema.event.clone(),
+                expected: schema.fields_len(),
+                got: env.field_count,
+            });
+        }
+        let slots = decode_rowpack(env.payload, schema.fields_len())?;
+        Ok((schema, slots))
+    }
+}
+
+/// The parts of a `__BEN_SCHEMA_JSON` manifest read back at runtime.
+#[derive(Deserialize)]
+pub(crate) struct Manifest {
+    pub table: String,
+    pub version: u32,
+    #[serde(default)]
+    pub order_by: Option<String>,
+    pub columns: Vec<ManifestColumn>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ManifestColumn {
+    pub name: String,
+    pub ch_type: String,
+    #[serde(default)]
+    pub key: bool,
+    #[serde(default)]
+    pub infra: bool,
+}
+
+fn hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
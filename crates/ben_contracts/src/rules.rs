@@ -1,8 +1,13 @@
-pub type Bits = u128;
+use ben_wire::bits::{Bit, BitSet, DEFAULT_WORDS};
 
+/// The same bitset the bitspec engine fills, so rule masks and event masks
+/// always agree on width.
+pub type Bits<const WORDS: usize = DEFAULT_WORDS> = BitSet<WORDS>;
+
+/// `None` past the default width.
 #[inline]
-pub const fn bit(i: u8) -> Bits {
-    1u128 << i
+pub const fn bit(i: Bit) -> Option<Bits> {
+    Bits::bit(i)
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -16,21 +21,66 @@ pub enum Action {
 }
 
 #[derive(Debug, Clone, Copy)]
-pub struct Rule {
-    pub all: Bits,
-    pub any: Bits,
-    pub none: Bits,
+pub struct Rule<const WORDS: usize = DEFAULT_WORDS> {
+    pub all: Bits<WORDS>,
+    pub any: Bits<WORDS>,
+    pub none: Bits<WORDS>,
     pub action: Action,
     pub priority: u8,
     pub id: u32,
 }
 
 #[inline]
-pub fn eval(bits: Bits, rules: &[Rule]) -> Action {
+pub fn eval<const WORDS: usize>(bits: Bits<WORDS>, rules: &[Rule<WORDS>]) -> Action {
     for r in rules {
-        if (bits & r.all) == r.all && (r.any == 0 || (bits & r.any) != 0) && (bits & r.none) == 0 {
+        if bits.contains_all(&r.all)
+            && (r.any.is_empty() || bits.intersects(&r.any))
+            && !bits.intersects(&r.none)
+        {
             return r.action;
         }
     }
     Action::Pass
 }
+
+/// Builds a rule list from a Bitspec struct's named bit consts, sorted
+/// highest priority first. Clauses are `ALL`, `ANY` and `NONE`, joined by
+/// `&`; priority defaults to 100.
+///
+/// ```ignore
+/// let rules = specs! {
+///     ALL(Conn::BIG) & NONE(Conn::NOT_TLS) => Quarantine("bulk") @prio 200;
+///     ANY(Conn::LATENCY_SLOW, Conn::LATENCY_STALLED) => RouteC;
+///     => PASS @prio 0;
+/// };
+/// ```
+#[macro_export]
+macro_rules! specs {
+    (@clause $rule:ident ALL $($bit:path),*) => { $($rule.all |= $bit;)* };
+    (@clause $rule:ident ANY $($bit:path),*) => { $($rule.any |= $bit;)* };
+    (@clause $rule:ident NONE $($bit:path),*) => { $($rule.none |= $bit;)* };
+    (@action PASS) => { $crate::rules::Action::Pass };
+    (@action $act:ident $(($arg:expr))?) => { $crate::rules::Action::$act $(($arg))? };
+    (@prio) => { 100 };
+    (@prio $prio:literal) => { $prio };
+
+    ($(
+        $($clause:ident ( $($bit:path),* ))&* => $act:ident $(($arg:expr))? $(@prio $prio:literal)? ;
+    )*) => {{
+        let mut rules = ::std::vec![$({
+            #[allow(unused_mut)]
+            let mut rule = $crate::rules::Rule {
+                all: $crate::rules::Bits::EMPTY,
+                any: $crate::rules::Bits::EMPTY,
+                none: $crate::rules::Bits::EMPTY,
+                action: $crate::specs!(@action $act $(($arg))?),
+                priority: $crate::specs!(@prio $($prio)?),
+                id: line!(),
+            };
+            $( $crate::specs!(@clause rule $clause $($bit),*); )*
+            rule
+        }),*];
+        rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        rules
+    }};
+}
@@ -94,6 +94,8 @@ pub struct SchemaManifest {
 #[derive(Debug, Clone)]
 pub struct TableDef {
     pub name: String,
+    /// `__BEN_SCHEMA_FINGERPRINT`; recorded in the migration ledger.
+    pub fingerprint: String,
     pub ddl: String,
     pub columns: Vec<ColumnDef>,
 }
@@ -104,6 +106,50 @@ pub struct ColumnDef {
     pub ch_type: String,
     pub nullable: bool,
     pub default: Option<String>,
+    /// Full `CODEC(..)` clause from the table's `compression`.
+    pub codec: Option<String>,
+    pub ttl: Option<String>,
+    /// One of `ben_wire::schema::INFRA_COLUMNS` that the struct leaves out.
+    pub infra: bool,
+}
+
+impl ColumnDef {
+    /// The column as `CREATE TABLE` declares it; see [`column_spec`].
+    pub fn spec(&self) -> String {
+        column_spec(
+            &self.name,
+            &self.ch_type,
+            self.default.as_deref(),
+            self.codec.as_deref(),
+            self.ttl.as_deref(),
+        )
+    }
+}
+
+/// `` `name` Type [DEFAULT expr] [CODEC(..)] [TTL expr] ``. Shared by the
+/// generated DDL and `ALTER TABLE .. ADD/MODIFY COLUMN` so both agree.
+pub fn column_spec(
+    name: &str,
+    ch_type: &str,
+    default: Option<&str>,
+    codec: Option<&str>,
+    ttl: Option<&str>,
+) -> String {
+    let mut out = format!("{} {ch_type}", quote_ident(name));
+    if let Some(d) = default {
+        out.push_str(&format!(" DEFAULT {d}"));
+    }
+    if let Some(c) = codec {
+        out.push_str(&format!(" {c}"));
+    }
+    if let Some(t) = ttl {
+        out.push_str(&format!(" TTL {t}"));
+    }
+    out
+}
+
+pub fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
 }
 
 pub fn merge_manifests(list: &[SchemaManifest]) -> SchemaManifest {
@@ -0,0 +1,209 @@
+use ben_contracts::compat::{Change, CompatError, Compatibility, check_manifests};
+use serde_json::json;
+
+fn manifest(version: u32, order_by: Option<&str>, cols: &[(&str, &str, bool)]) -> String {
+    let mut columns = vec![json!({
+        "name": "tenant_id", "ch_type": "UUID", "key": true, "infra": true,
+    })];
+    columns.extend(
+        cols.iter()
+            .map(|(name, ch_type, key)| json!({ "name": name, "ch_type": ch_type, "key": key })),
+    );
+    json!({
+        "table": "flows",
+        "version": version,
+        "order_by": order_by,
+        "columns": columns,
+    })
+    .to_string()
+}
+
+fn base() -> String {
+    manifest(
+        1,
+        None,
+        &[("id", "UInt64", true), ("bytes", "UInt32", false)],
+    )
+}
+
+#[test]
+fn unchanged_manifest_is_fully_compatible() {
+    let report = check_manifests(&base(), &base()).unwrap();
+    assert!(report.changes.is_empty());
+    assert_eq!(report.compat(), Compatibility::Full);
+}
+
+#[test]
+fn added_columns_depend_on_nullability() {
+    let new = manifest(
+        2,
+        None,
+        &[
+            ("id", "UInt64", true),
+            ("bytes", "UInt32", false),
+            ("note", "Nullable(String)", false),
+        ],
+    );
+    let report = check_manifests(&base(), &new).unwrap();
+    assert_eq!(report.changes.len(), 1);
+    assert_eq!(report.compat(), Compatibility::Full);
+
+    let new = manifest(
+        2,
+        None,
+        &[
+            ("id", "UInt64", true),
+            ("bytes", "UInt32", false),
+            ("port", "UInt16", false),
+        ],
+    );
+    let report = check_manifests(&base(), &new).unwrap();
+    assert_eq!(report.compat(), Compatibility::Forward);
+    assert!(
+        matches!(&report.changes[0].change, Change::AddedColumn { name, .. } if name == "port")
+    );
+}
+
+#[test]
+fn widening_is_backward_and_narrowing_forward() {
+    let widened = manifest(
+        2,
+        None,
+        &[("id", "UInt64", true), ("bytes", "UInt64", false)],
+    );
+    let report = check_manifests(&base(), &widened).unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+    assert_eq!(
+        report.changes[0].change,
+        Change::TypeChanged {
+            name: "bytes".into(),
+            from: "UInt32".into(),
+            to: "UInt64".into(),
+        }
+    );
+
+    let report = check_manifests(&widened, &base()).unwrap();
+    assert_eq!(report.compat(), Compatibility::Forward);
+
+    // Mask columns grew from UInt64 to UInt128.
+    let wide_id = manifest(
+        2,
+        None,
+        &[("id", "UInt128", true), ("bytes", "UInt32", false)],
+    );
+    let report = check_manifests(&base(), &wide_id).unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+
+    let nullable = manifest(
+        2,
+        None,
+        &[("id", "UInt64", true), ("bytes", "Nullable(Int64)", false)],
+    );
+    let report = check_manifests(&base(), &nullable).unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+
+    let text = manifest(
+        2,
+        None,
+        &[("id", "UInt64", true), ("bytes", "String", false)],
+    );
+    let report = check_manifests(&base(), &text).unwrap();
+    assert!(report.is_breaking());
+}
+
+#[test]
+fn added_enum_labels_are_backward() {
+    let enum_col = |ty| manifest(2, None, &[("id", "UInt64", true), ("level", ty, false)]);
+    let old = enum_col("Enum8('low' = 1, 'high' = 2)");
+
+    let added = enum_col("Enum8('low' = 1, 'high' = 2, 'max' = 3)");
+    let report = check_manifests(&old, &added).unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+    let report = check_manifests(
+        &old,
+        &enum_col("Enum16('low' = 1, 'high' = 2, 'max' = 300)"),
+    )
+    .unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+    let report = check_manifests(&added, &old).unwrap();
+    assert_eq!(report.compat(), Compatibility::Forward);
+
+    let renumbered = enum_col("Enum8('low' = 1, 'high' = 3)");
+    assert!(check_manifests(&old, &renumbered).unwrap().is_breaking());
+}
+
+#[test]
+fn dropped_required_column_is_backward_only() {
+    let new = manifest(2, None, &[("id", "UInt64", true)]);
+    let report = check_manifests(&base(), &new).unwrap();
+    assert_eq!(report.compat(), Compatibility::Backward);
+    assert!(
+        matches!(&report.changes[0].change, Change::DroppedColumn { name, .. } if name == "bytes")
+    );
+}
+
+#[test]
+fn mixed_directions_are_breaking() {
+    let new = manifest(
+        2,
+        None,
+        &[("id", "UInt64", true), ("port", "UInt16", false)],
+    );
+    let report = check_manifests(&base(), &new).unwrap();
+    assert_eq!(report.changes.len(), 2);
+    assert!(report.is_breaking());
+}
+
+#[test]
+fn key_order_by_and_moves_are_breaking() {
+    let rekeyed = manifest(
+        2,
+        None,
+        &[("id", "UInt64", true), ("bytes", "UInt32", true)],
+    );
+    let report = check_manifests(&base(), &rekeyed).unwrap();
+    assert!(report.is_breaking());
+    assert!(
+        report
+            .changes
+            .iter()
+            .any(|c| matches!(c.change, Change::KeyChanged { .. }))
+    );
+
+    let reordered = manifest(
+        2,
+        Some("bytes, id"),
+        &[("id", "UInt64", true), ("bytes", "UInt32", false)],
+    );
+    let report = check_manifests(&base(), &reordered).unwrap();
+    assert_eq!(
+        report.changes[0].change,
+        Change::OrderByChanged {
+            from: "id".into(),
+            to: "bytes,id".into(),
+        }
+    );
+
+    let moved = manifest(
+        2,
+        None,
+        &[("bytes", "UInt32", false), ("id", "UInt64", true)],
+    );
+    let report = check_manifests(&base(), &moved).unwrap();
+    assert!(report.is_breaking());
+    assert!(
+        report
+            .changes
+            .iter()
+            .all(|c| matches!(c.change, Change::Moved { .. }))
+    );
+}
+
+#[test]
+fn different_tables_are_rejected() {
+    let other = base().replace("\"flows\"", "\"dns\"");
+    assert!(matches!(
+        check_manifests(&base(), &other),
+        Err(CompatError::TableMismatch { .. })
+    ));
+}
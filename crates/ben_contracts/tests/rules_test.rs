@@ -0,0 +1,44 @@
+use ben_contracts::rules::{Action, Bits, Rule, bit, eval};
+use ben_wire::bits::Bit;
+
+fn bits<const W: usize>(list: &[Bit]) -> Bits<W> {
+    Bits::from_bits(list.iter().copied()).unwrap()
+}
+
+fn rule<const W: usize>(all: Bits<W>, any: Bits<W>, none: Bits<W>, action: Action) -> Rule<W> {
+    Rule {
+        all,
+        any,
+        none,
+        action,
+        priority: 0,
+        id: 0,
+    }
+}
+
+#[test]
+fn first_matching_rule_wins() {
+    let rules: [Rule; 2] = [
+        rule(bits(&[3, 100]), Bits::EMPTY, bits(&[127]), Action::RouteC),
+        rule(Bits::EMPTY, bits(&[5, 90]), Bits::EMPTY, Action::Sample(10)),
+    ];
+
+    assert!(matches!(eval(bits(&[3, 100]), &rules), Action::RouteC));
+    assert!(matches!(eval(bits(&[3, 100, 127]), &rules), Action::Pass));
+    assert!(matches!(eval(bits(&[90]), &rules), Action::Sample(10)));
+    assert!(matches!(eval(bits(&[3]), &rules), Action::Pass));
+    assert_eq!(bit(128), None);
+}
+
+#[test]
+fn wider_masks_do_not_alias() {
+    let rules = [rule(
+        bits::<4>(&[200]),
+        Bits::EMPTY,
+        Bits::EMPTY,
+        Action::Pause,
+    )];
+    assert!(matches!(eval(bits(&[200]), &rules), Action::Pause));
+    assert!(matches!(eval(bits(&[8]), &rules), Action::Pass));
+    assert!(matches!(eval(bits(&[72]), &rules), Action::Pass));
+}
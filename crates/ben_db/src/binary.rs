@@ -1,5 +1,7 @@
 use crate::error::DbError;
-use ben_wire::rowbinary::RowBinaryEncode;
+use ben_wire::rowbinary::{
+    InfraValues, NamesAndTypes, RowBinCursor, RowBinaryColumns, RowBinaryEncode,
+};
 use reqwest::Client;
 use url::Url;
 
@@ -26,20 +28,115 @@ impl ChBinaryClient {
         })
     }
 
-    pub async fn insert_struct<T: RowBinaryEncode>(
+    /// Inserts one row as plain `RowBinary`, naming `T`'s columns so the
+    /// infra columns it leaves out take their defaults.
+    pub async fn insert_struct<T: RowBinaryEncode + RowBinaryColumns>(
         &self,
         table: &str,
         value: &T,
     ) -> Result<(), DbError> {
         let mut buf = Vec::with_capacity(128);
         value.encode_rowbinary(&mut buf)?;
-        self.insert_rowbinary(table, buf).await
+
+        let names = T::COLUMNS
+            .iter()
+            .map(|(n, _)| format!("`{n}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!("INSERT INTO {} ({}) FORMAT RowBinary", table, names);
+        self.post(&query, &[], buf).await?;
+        Ok(())
     }
 
+    /// Plain `RowBinary`: `body` must list every column in table order.
     pub async fn insert_rowbinary(&self, table: &str, body: Vec<u8>) -> Result<(), DbError> {
+        let query = format!("INSERT INTO {} FORMAT RowBinary", table);
+        self.post(&query, &[], body).await?;
+        Ok(())
+    }
+
+    /// Inserts `rows` as `RowBinaryWithNamesAndTypes`, so the server maps
+    /// columns by name and fills the rest with defaults. The header is
+    /// checked against the table first.
+    pub async fn insert_named<T: RowBinaryEncode + RowBinaryColumns>(
+        &self,
+        table: &str,
+        rows: &[T],
+    ) -> Result<(), DbError> {
+        self.insert_named_with(table, rows, &InfraValues::default())
+            .await
+    }
+
+    /// [`Self::insert_named`], appending the pipeline's infra values to every
+    /// row. Setting a column `T` already declares is a duplicate column.
+    pub async fn insert_named_with<T: RowBinaryEncode + RowBinaryColumns>(
+        &self,
+        table: &str,
+        rows: &[T],
+        infra: &InfraValues,
+    ) -> Result<(), DbError> {
+        let mut header = NamesAndTypes::of::<T>();
+        header.columns.extend(infra.columns());
+
+        let mut body = Vec::with_capacity(128 * rows.len().max(1));
+        for row in rows {
+            row.encode_rowbinary(&mut body)?;
+            infra.write(&mut body);
+        }
+        self.insert_names_and_types(table, &header, &body).await
+    }
+
+    /// Sends `header` followed by `rows`, already encoded in header order.
+    pub async fn insert_names_and_types(
+        &self,
+        table: &str,
+        header: &NamesAndTypes,
+        rows: &[u8],
+    ) -> Result<(), DbError> {
+        let columns = self.table_columns(table).await?;
+        check_columns(table, header, &columns)?;
+
+        let mut body = Vec::with_capacity(64 * header.len() + rows.len());
+        header.write(&mut body);
+        body.extend_from_slice(rows);
+
+        let query = format!("INSERT INTO {} FORMAT RowBinaryWithNamesAndTypes", table);
+        self.post(&query, &[], body).await?;
+        Ok(())
+    }
+
+    /// Names and types of `table` in the client's database, in table order.
+    pub async fn table_columns(&self, table: &str) -> Result<NamesAndTypes, DbError> {
+        let query = "SELECT name, type FROM system.columns \
+                     WHERE database = currentDatabase() AND table = {table:String} \
+                     ORDER BY position FORMAT RowBinaryWithNamesAndTypes";
+        let body = self
+            .post(query, &[("param_table", table)], Vec::new())
+            .await?;
+
+        let mut cur = RowBinCursor::new(&body);
+        NamesAndTypes::read(&mut cur)?;
+        let mut out = NamesAndTypes::default();
+        while cur.remaining() > 0 {
+            out.columns.push((cur.read_string()?, cur.read_string()?));
+        }
+
+        if out.is_empty() {
+            return Err(DbError::Server(format!("table `{}` not found", table)));
+        }
+        Ok(out)
+    }
+
+    async fn post(
+        &self,
+        query: &str,
+        params: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>, DbError> {
         let mut url = self.url.clone();
         url.query_pairs_mut()
-            .append_pair("query", &format!("INSERT INTO {} FORMAT RowBinary", table));
+            .append_pair("query", query)
+            .extend_pairs(params);
 
         let mut req = self
             .client
@@ -53,12 +150,48 @@ impl ChBinaryClient {
 
         let resp = req.send().await?;
         let status = resp.status();
-        let text = resp.text().await?;
 
         if !status.is_success() {
-            return Err(DbError::Server(text));
+            return Err(DbError::Server(resp.text().await?));
         }
 
-        Ok(())
+        Ok(resp.bytes().await?.to_vec())
+    }
+}
+
+/// Checks an insert header against the table's columns. Every header column
+/// must exist with the same type; table columns the header leaves out get
+/// their defaults.
+pub fn check_columns(
+    table: &str,
+    header: &NamesAndTypes,
+    columns: &NamesAndTypes,
+) -> Result<(), DbError> {
+    for (i, (name, ty)) in header.columns.iter().enumerate() {
+        if header.columns[..i].iter().any(|(n, _)| n == name) {
+            return Err(DbError::DuplicateColumn(name.clone()));
+        }
+
+        let Some((_, expected)) = columns.columns.iter().find(|(n, _)| n == name) else {
+            return Err(DbError::UnknownColumn {
+                table: table.to_string(),
+                column: name.clone(),
+            });
+        };
+
+        if normalize_type(expected) != normalize_type(ty) {
+            return Err(DbError::ColumnType {
+                table: table.to_string(),
+                column: name.clone(),
+                expected: expected.clone(),
+                got: ty.clone(),
+            });
+        }
     }
+    Ok(())
+}
+
+/// The server prints `Map(String, String)`; don't trip over spacing.
+pub(crate) fn normalize_type(ty: &str) -> String {
+    ty.chars().filter(|c| !c.is_whitespace()).collect()
 }
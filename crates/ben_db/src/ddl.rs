@@ -1,5 +1,10 @@
-use crate::{ChHttpClient, DbError};
-use ben_contracts::schema::SchemaManifest;
+use crate::{ChHttpClient, DbError, binary::normalize_type};
+use ben_contracts::schema::{SchemaManifest, TableDef, quote_ident};
+use ben_wire::rowbinary::NamesAndTypes;
+use serde::Deserialize;
+
+/// Ledger of applied table fingerprints.
+pub const MIGRATIONS_TABLE: &str = "ben_schema_migrations";
 
 /// Ensures the database exists.
 pub async fn ensure_database(client: &ChHttpClient, db: &str) -> Result<(), DbError> {
@@ -7,12 +12,239 @@ pub async fn ensure_database(client: &ChHttpClient, db: &str) -> Result<(), DbEr
     Ok(())
 }
 
+/// Creates missing tables and adds new columns; see [`migrate_manifest`].
+/// Type changes and drops need [`MigrateOptions`] opt-ins.
 pub async fn apply_manifest(
     client: &ChHttpClient,
     manifest: &SchemaManifest,
 ) -> Result<(), DbError> {
+    migrate_manifest(client, manifest, MigrateOptions::default()).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MigrateOptions {
+    /// Plan against the live tables but execute nothing and record nothing.
+    pub dry_run: bool,
+    /// Drop live columns the manifest no longer has. Off by default.
+    pub allow_drop: bool,
+    /// `MODIFY` live columns whose type differs from the manifest. Off by
+    /// default, since ClickHouse rewrites the column's data to do it.
+    pub allow_modify: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableMigration {
+    pub table: String,
+    pub fingerprint: String,
+    /// The `CREATE TABLE` for a new table, otherwise the `ALTER`s in order.
+    pub statements: Vec<String>,
+    /// Live columns the manifest no longer has, left alone without `allow_drop`.
+    pub kept_columns: Vec<String>,
+    /// Live columns whose type differs, left alone without `allow_modify`.
+    /// The fingerprint is not recorded while any remain, so a later run can
+    /// still apply them.
+    pub unmodified_columns: Vec<String>,
+    /// The ledger already has this fingerprint; nothing was planned.
+    pub already_applied: bool,
+}
+
+impl TableMigration {
+    /// Whether the ledger may record the fingerprint once the statements ran:
+    /// not while a type change was skipped, so a later run still applies it.
+    pub fn records_fingerprint(&self) -> bool {
+        !self.already_applied && self.unmodified_columns.is_empty()
+    }
+}
+
+/// Brings every table in `manifest` up to date and records each new
+/// fingerprint in [`MIGRATIONS_TABLE`].
+pub async fn migrate_manifest(
+    client: &ChHttpClient,
+    manifest: &SchemaManifest,
+    opts: MigrateOptions,
+) -> Result<Vec<TableMigration>, DbError> {
+    let ledger = if opts.dry_run {
+        table_exists(client, MIGRATIONS_TABLE).await?
+    } else {
+        client.exec(&ledger_ddl()).await?;
+        true
+    };
+
+    let mut out = Vec::with_capacity(manifest.tables.len());
     for table in &manifest.tables {
-        client.exec(&table.ddl).await?;
+        let mut m = TableMigration {
+            table: table.name.clone(),
+            fingerprint: table.fingerprint.clone(),
+            ..Default::default()
+        };
+
+        if ledger && is_applied(client, table).await? {
+            m.already_applied = true;
+            out.push(m);
+            continue;
+        }
+
+        let live = live_columns(client, &table.name).await?;
+        if live.is_empty() {
+            m.statements.push(table.ddl.clone());
+        } else {
+            let plan = plan_alters(table, &live, opts);
+            m.statements = plan.statements;
+            m.kept_columns = plan.kept_columns;
+            m.unmodified_columns = plan.unmodified_columns;
+        }
+
+        if !opts.dry_run {
+            for sql in &m.statements {
+                client.exec(sql).await?;
+            }
+            if m.records_fingerprint() {
+                record(client, &m).await?;
+            }
+        }
+        out.push(m);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlterPlan {
+    pub statements: Vec<String>,
+    pub kept_columns: Vec<String>,
+    pub unmodified_columns: Vec<String>,
+}
+
+/// Diffs `table` against the live columns. Statements come out as ADDs, then
+/// MODIFYs, then DROPs, so nothing is dropped before its replacement exists.
+/// Columns are spelled as in the table's DDL, with default, codec and TTL.
+pub fn plan_alters(table: &TableDef, live: &NamesAndTypes, opts: MigrateOptions) -> AlterPlan {
+    let name = quote_ident(&table.name);
+    let mut adds = Vec::new();
+    let mut modifies = Vec::new();
+    let mut plan = AlterPlan::default();
+
+    for (i, col) in table.columns.iter().enumerate() {
+        match live.columns.iter().find(|(n, _)| *n == col.name) {
+            None => {
+                let mut sql = format!("ALTER TABLE {name} ADD COLUMN {}", col.spec());
+                match i.checked_sub(1) {
+                    Some(prev) => {
+                        let prev = quote_ident(&table.columns[prev].name);
+                        sql.push_str(&format!(" AFTER {prev}"));
+                    }
+                    None => sql.push_str(" FIRST"),
+                }
+                adds.push(sql);
+            }
+            Some((_, ty)) if normalize_type(ty) != normalize_type(&col.ch_type) => {
+                if opts.allow_modify {
+                    modifies.push(format!("ALTER TABLE {name} MODIFY COLUMN {}", col.spec()));
+                } else {
+                    plan.unmodified_columns.push(col.name.clone());
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    plan.statements.extend(adds);
+    plan.statements.extend(modifies);
+
+    for (col, _) in &live.columns {
+        if table.columns.iter().any(|c| c.name == *col) {
+            continue;
+        }
+        if opts.allow_drop {
+            plan.statements.push(format!(
+                "ALTER TABLE {name} DROP COLUMN {}",
+                quote_ident(col)
+            ));
+        } else {
+            plan.kept_columns.push(col.clone());
+        }
+    }
+    plan
+}
+
+fn ledger_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE}\n\
+         (\n  \
+           `table_name` String,\n  \
+           `fingerprint` String,\n  \
+           `statements` Array(String),\n  \
+           `applied_at` DateTime64(3) DEFAULT now64(3)\n\
+         )\n\
+         ENGINE = MergeTree\n\
+         ORDER BY (table_name, applied_at)"
+    )
+}
+
+async fn table_exists(client: &ChHttpClient, table: &str) -> Result<bool, DbError> {
+    let body = client
+        .exec_with_params(
+            "SELECT count() FROM system.tables \
+             WHERE database = currentDatabase() AND name = {table:String}",
+            &[("param_table", table)],
+        )
+        .await?;
+    Ok(body.trim() != "0")
+}
+
+async fn is_applied(client: &ChHttpClient, table: &TableDef) -> Result<bool, DbError> {
+    let sql = format!(
+        "SELECT count() FROM {MIGRATIONS_TABLE} \
+         WHERE table_name = {{table:String}} AND fingerprint = {{fingerprint:String}}"
+    );
+    let body = client
+        .exec_with_params(
+            &sql,
+            &[
+                ("param_table", table.name.as_str()),
+                ("param_fingerprint", table.fingerprint.as_str()),
+            ],
+        )
+        .await?;
+    Ok(body.trim() != "0")
+}
+
+/// Empty when the table does not exist.
+async fn live_columns(client: &ChHttpClient, table: &str) -> Result<NamesAndTypes, DbError> {
+    #[derive(Deserialize)]
+    struct Row {
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
     }
+
+    let body = client
+        .exec_with_params(
+            "SELECT name, type FROM system.columns \
+             WHERE database = currentDatabase() AND table = {table:String} \
+             ORDER BY position FORMAT JSONEachRow",
+            &[("param_table", table)],
+        )
+        .await?;
+
+    let mut out = NamesAndTypes::default();
+    for line in body.lines().filter(|l| !l.trim().is_empty()) {
+        let row: Row = serde_json::from_str(line)?;
+        out.columns.push((row.name, row.ty));
+    }
+    Ok(out)
+}
+
+async fn record(client: &ChHttpClient, m: &TableMigration) -> Result<(), DbError> {
+    let row = serde_json::json!({
+        "table_name": m.table,
+        "fingerprint": m.fingerprint,
+        "statements": m.statements,
+    });
+    let sql = format!(
+        "INSERT INTO {MIGRATIONS_TABLE} (table_name, fingerprint, statements) \
+         FORMAT JSONEachRow\n{row}"
+    );
+    client.exec(&sql).await?;
     Ok(())
 }
@@ -8,9 +8,26 @@ pub enum DbError {
     #[error("URL parse error: {0}")]
     Url(#[from] url::ParseError),
 
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("ClickHouse returned non-success: {0}")]
     Server(String),
 
+    #[error("table `{table}` has no column `{column}`")]
+    UnknownColumn { table: String, column: String },
+
+    #[error("column `{table}.{column}` is {expected}, rows carry {got}")]
+    ColumnType {
+        table: String,
+        column: String,
+        expected: String,
+        got: String,
+    },
+
+    #[error("column `{0}` appears twice in the insert header")]
+    DuplicateColumn(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -23,7 +23,20 @@ impl ChHttpClient {
     }
 
     pub async fn exec(&self, sql: &str) -> Result<String, DbError> {
-        let mut req = self.client.post(self.base.clone()).body(sql.to_string());
+        self.exec_with_params(sql, &[]).await
+    }
+
+    /// Runs `sql` with `{name:Type}` query parameters, given as `("param_name", value)`.
+    pub async fn exec_with_params(
+        &self,
+        sql: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, DbError> {
+        let mut url = self.base.clone();
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+        let mut req = self.client.post(url).body(sql.to_string());
 
         if let Some((u, p)) = &self.auth {
             req = req.basic_auth(u, Some(p));
@@ -4,8 +4,12 @@ mod ddl;
 mod error;
 mod http;
 
-pub use binary::ChBinaryClient;
+pub use binary::{ChBinaryClient, check_columns};
 pub use config::ChConfig;
+pub use ddl::{
+    AlterPlan, MIGRATIONS_TABLE, MigrateOptions, TableMigration, apply_manifest, ensure_database,
+    migrate_manifest, plan_alters,
+};
 
 pub use error::DbError;
 pub use http::ChHttpClient;
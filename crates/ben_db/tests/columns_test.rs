@@ -0,0 +1,49 @@
+use ben_db::{DbError, check_columns};
+use ben_wire::rowbinary::NamesAndTypes;
+
+fn cols(list: &[(&str, &str)]) -> NamesAndTypes {
+    NamesAndTypes {
+        columns: list
+            .iter()
+            .map(|(n, t)| (n.to_string(), t.to_string()))
+            .collect(),
+    }
+}
+
+fn table() -> NamesAndTypes {
+    cols(&[
+        ("id", "UInt64"),
+        ("attrs", "Map(String, String)"),
+        ("system_mask", "UInt128"),
+    ])
+}
+
+#[test]
+fn header_may_reorder_and_skip_columns() {
+    let header = cols(&[("attrs", "Map(String,String)"), ("id", "UInt64")]);
+    check_columns("t", &header, &table()).unwrap();
+}
+
+#[test]
+fn unknown_column_is_rejected() {
+    let header = cols(&[("id", "UInt64"), ("nope", "String")]);
+    let err = check_columns("t", &header, &table()).unwrap_err();
+    assert!(matches!(err, DbError::UnknownColumn { column, .. } if column == "nope"));
+}
+
+#[test]
+fn type_mismatch_is_rejected() {
+    let header = cols(&[("id", "Int64")]);
+    let err = check_columns("t", &header, &table()).unwrap_err();
+    assert!(matches!(
+        err,
+        DbError::ColumnType { expected, got, .. } if expected == "UInt64" && got == "Int64"
+    ));
+}
+
+#[test]
+fn duplicate_column_is_rejected() {
+    let header = cols(&[("id", "UInt64"), ("id", "UInt64")]);
+    let err = check_columns("t", &header, &table()).unwrap_err();
+    assert!(matches!(err, DbError::DuplicateColumn(c) if c == "id"));
+}
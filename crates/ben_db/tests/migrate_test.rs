@@ -0,0 +1,232 @@
+use ben_contracts::schema::{ColumnDef, TableDef};
+use ben_db::{MigrateOptions, TableMigration, plan_alters};
+use ben_wire::rowbinary::NamesAndTypes;
+
+const ALLOW_ALL: MigrateOptions = MigrateOptions {
+    dry_run: false,
+    allow_drop: true,
+    allow_modify: true,
+};
+
+fn col(name: &str, ch_type: &str) -> ColumnDef {
+    ColumnDef {
+        name: name.into(),
+        ch_type: ch_type.into(),
+        ..Default::default()
+    }
+}
+
+fn infra(name: &str) -> ColumnDef {
+    ColumnDef {
+        default: Some("0".into()),
+        infra: true,
+        ..col(name, "UInt128")
+    }
+}
+
+fn table(columns: Vec<ColumnDef>) -> TableDef {
+    TableDef {
+        name: "flows".into(),
+        fingerprint: "sha256:00".into(),
+        ddl: String::new(),
+        columns,
+    }
+}
+
+fn live(list: &[(&str, &str)]) -> NamesAndTypes {
+    NamesAndTypes {
+        columns: list
+            .iter()
+            .map(|(n, t)| (n.to_string(), t.to_string()))
+            .collect(),
+    }
+}
+
+#[test]
+fn matching_table_needs_nothing() {
+    let t = table(vec![
+        col("id", "UInt64"),
+        col("attrs", "Map(String, String)"),
+        infra("system_mask"),
+        infra("struct_mask"),
+        infra("field_mask"),
+    ]);
+    let l = live(&[
+        ("id", "UInt64"),
+        ("attrs", "Map(String,String)"),
+        ("system_mask", "UInt128"),
+        ("struct_mask", "UInt128"),
+        ("field_mask", "UInt128"),
+    ]);
+    let plan = plan_alters(&t, &l, ALLOW_ALL);
+    assert!(plan.statements.is_empty());
+    assert!(plan.kept_columns.is_empty());
+}
+
+#[test]
+fn adds_then_modifies_then_drops() {
+    let mut note = col("note", "Nullable(String)");
+    note.default = Some("NULL".into());
+    let t = table(vec![
+        col("host", "String"),
+        col("id", "UInt64"),
+        note,
+        col("bytes", "UInt64"),
+    ]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt32"), ("legacy", "String")]);
+
+    let plan = plan_alters(&t, &l, ALLOW_ALL);
+    assert_eq!(
+        plan.statements,
+        [
+            "ALTER TABLE `flows` ADD COLUMN `host` String FIRST",
+            "ALTER TABLE `flows` ADD COLUMN `note` Nullable(String) DEFAULT NULL AFTER `id`",
+            "ALTER TABLE `flows` MODIFY COLUMN `bytes` UInt64",
+            "ALTER TABLE `flows` DROP COLUMN `legacy`",
+        ]
+    );
+}
+
+#[test]
+fn drops_need_opt_in() {
+    let t = table(vec![col("id", "UInt64")]);
+    let l = live(&[("id", "UInt64"), ("legacy", "String")]);
+
+    let plan = plan_alters(&t, &l, MigrateOptions::default());
+    assert!(plan.statements.is_empty());
+    assert_eq!(plan.kept_columns, ["legacy"]);
+}
+
+#[test]
+fn missing_infra_columns_are_added_with_defaults() {
+    let t = table(vec![col("id", "UInt64"), infra("system_mask")]);
+    let l = live(&[("id", "UInt64")]);
+
+    let plan = plan_alters(&t, &l, MigrateOptions::default());
+    assert_eq!(
+        plan.statements,
+        ["ALTER TABLE `flows` ADD COLUMN `system_mask` UInt128 DEFAULT 0 AFTER `id`"]
+    );
+}
+
+#[test]
+fn type_changes_need_opt_in() {
+    let t = table(vec![col("id", "UInt64"), col("bytes", "UInt64")]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt32")]);
+
+    let plan = plan_alters(&t, &l, MigrateOptions::default());
+    assert!(plan.statements.is_empty());
+    assert_eq!(plan.unmodified_columns, ["bytes"]);
+}
+
+#[test]
+fn alters_keep_default_codec_and_ttl() {
+    let seen = ColumnDef {
+        default: Some("now()".into()),
+        codec: Some("CODEC(ZSTD(3))".into()),
+        ttl: Some("seen + INTERVAL 1 DAY".into()),
+        ..col("seen", "DateTime")
+    };
+    let bytes = ColumnDef {
+        default: Some("0".into()),
+        codec: Some("CODEC(ZSTD(3))".into()),
+        ..col("bytes", "UInt64")
+    };
+    let t = table(vec![col("id", "UInt64"), seen, bytes]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt32")]);
+
+    let plan = plan_alters(&t, &l, ALLOW_ALL);
+    assert_eq!(
+        plan.statements,
+        [
+            "ALTER TABLE `flows` ADD COLUMN `seen` DateTime DEFAULT now() CODEC(ZSTD(3)) \
+             TTL seen + INTERVAL 1 DAY AFTER `id`",
+            "ALTER TABLE `flows` MODIFY COLUMN `bytes` UInt64 DEFAULT 0 CODEC(ZSTD(3))",
+        ]
+    );
+}
+
+#[test]
+fn adds_follow_the_manifest_order() {
+    let t = table(vec![
+        col("tenant", "String"),
+        col("id", "UInt64"),
+        col("src", "IPv6"),
+        col("dst", "IPv6"),
+        col("bytes", "UInt64"),
+    ]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt64")]);
+
+    // Each ADD is placed after the column before it, which may itself be new.
+    let plan = plan_alters(&t, &l, MigrateOptions::default());
+    assert_eq!(
+        plan.statements,
+        [
+            "ALTER TABLE `flows` ADD COLUMN `tenant` String FIRST",
+            "ALTER TABLE `flows` ADD COLUMN `src` IPv6 AFTER `id`",
+            "ALTER TABLE `flows` ADD COLUMN `dst` IPv6 AFTER `src`",
+        ]
+    );
+}
+
+#[test]
+fn modify_and_drop_are_opted_into_separately() {
+    let t = table(vec![col("id", "UInt64"), col("bytes", "UInt64")]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt32"), ("legacy", "String")]);
+
+    let modify_only = MigrateOptions {
+        allow_modify: true,
+        ..Default::default()
+    };
+    let plan = plan_alters(&t, &l, modify_only);
+    assert_eq!(
+        plan.statements,
+        ["ALTER TABLE `flows` MODIFY COLUMN `bytes` UInt64"]
+    );
+    assert_eq!(plan.kept_columns, ["legacy"]);
+    assert!(plan.unmodified_columns.is_empty());
+
+    let drop_only = MigrateOptions {
+        allow_drop: true,
+        ..Default::default()
+    };
+    let plan = plan_alters(&t, &l, drop_only);
+    assert_eq!(
+        plan.statements,
+        ["ALTER TABLE `flows` DROP COLUMN `legacy`"]
+    );
+    assert!(plan.kept_columns.is_empty());
+    assert_eq!(plan.unmodified_columns, ["bytes"]);
+}
+
+#[test]
+fn skipped_modifies_hold_back_the_fingerprint() {
+    let t = table(vec![col("id", "UInt64"), col("bytes", "UInt64")]);
+    let l = live(&[("id", "UInt64"), ("bytes", "UInt32"), ("legacy", "String")]);
+    let migration = |opts| {
+        let plan = plan_alters(&t, &l, opts);
+        TableMigration {
+            table: t.name.clone(),
+            fingerprint: t.fingerprint.clone(),
+            statements: plan.statements,
+            kept_columns: plan.kept_columns,
+            unmodified_columns: plan.unmodified_columns,
+            already_applied: false,
+        }
+    };
+
+    assert!(!migration(MigrateOptions::default()).records_fingerprint());
+    // A column kept without `allow_drop` does not hold it back.
+    let modify_only = MigrateOptions {
+        allow_modify: true,
+        ..Default::default()
+    };
+    assert!(migration(modify_only).records_fingerprint());
+    assert!(migration(ALLOW_ALL).records_fingerprint());
+
+    let applied = TableMigration {
+        already_applied: true,
+        ..Default::default()
+    };
+    assert!(!applied.records_fingerprint());
+}
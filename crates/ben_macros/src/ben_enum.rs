@@ -1,3 +1,4 @@
+use ben_wire::schema::FieldType;
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
 use serde::Serialize;
@@ -154,6 +155,14 @@ pub fn derive_ben_enum(input: DeriveInput) -> syn::Result<TokenStream2> {
     let needs_16 = collected.iter().any(|v| v.value < -128 || v.value > 127);
     let bits: u8 = if needs_16 { 16 } else { 8 };
 
+    let labeled = collected.iter().map(|v| (v.label.clone(), v.value));
+    let ch_type = if needs_16 {
+        FieldType::Enum16(labeled.collect())
+    } else {
+        FieldType::Enum8(labeled.map(|(label, value)| (label, value as i8)).collect())
+    }
+    .ch_type();
+
     let variant_idents: Vec<_> = collected.iter().map(|v| &v.ident).collect();
     let labels_lit: Vec<_> = collected
         .iter()
@@ -164,7 +173,7 @@ pub fn derive_ben_enum(input: DeriveInput) -> syn::Result<TokenStream2> {
         .map(|v| syn::LitInt::new(&v.value.to_string(), Span::call_site()))
         .collect();
 
-    let reg_ident = quote::format_ident!("{}_ENUM_META", ident);
+    let reg_ident = quote::format_ident!("{}_ENUM_META", ident.to_string().to_uppercase());
 
     Ok(quote! {
 
@@ -256,7 +265,9 @@ pub fn derive_ben_enum(input: DeriveInput) -> syn::Result<TokenStream2> {
                 )*
             ];
 
-            fn to_i16(self) -> i16 { self.__ben_enum_to_i16() }
+            const CH_TYPE: &'static str = #ch_type;
+
+            fn to_i16(&self) -> i16 { self.__ben_enum_to_i16() }
             fn from_i16(v: i16) -> Option<Self> { Self::__ben_enum_from_i16(v) }
         }
 
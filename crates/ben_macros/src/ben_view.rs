@@ -0,0 +1,100 @@
+use darling::{FromDeriveInput, FromField};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{DeriveInput, Ident, LitStr, Path, Type};
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(attributes(bview), supports(struct_named))]
+struct ViewSpec {
+    /// BenSchema type whose evt_hash this view matches. Defaults to `Self`.
+    #[darling(default)]
+    schema: Option<Path>,
+    /// Accept rows of any version of this event, matched by name.
+    #[darling(default)]
+    event: Option<String>,
+    /// Convert fields with `Coercion::Lenient` instead of `Strict`.
+    #[darling(default)]
+    lenient: bool,
+
+    ident: Ident,
+    generics: syn::Generics,
+    data: darling::ast::Data<(), ViewField>,
+}
+
+#[derive(Debug, FromField)]
+#[darling(attributes(bview))]
+struct ViewField {
+    ident: Option<Ident>,
+    ty: Type,
+
+    /// Column name, when it differs from the field name.
+    #[darling(default)]
+    rename: Option<String>,
+}
+
+pub fn derive_ben_view(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let spec = ViewSpec::from_derive_input(&input)?;
+    let ident = &spec.ident;
+    let (impl_generics, ty_generics, where_clause) = spec.generics.split_for_impl();
+
+    let evt_hash = match (&spec.schema, &spec.event) {
+        (Some(schema), _) => quote! { <#schema>::__BEN_SCHEMA_EVT_HASH },
+        (None, Some(_)) => quote! { [0u8; 32] },
+        (None, None) => quote! { Self::__BEN_SCHEMA_EVT_HASH },
+    };
+    let event = match &spec.event {
+        Some(name) => quote! { Some(#name) },
+        None => quote! { None },
+    };
+
+    let coercion = if spec.lenient {
+        quote! { ::ben_wire::slot::Coercion::Lenient }
+    } else {
+        quote! { ::ben_wire::slot::Coercion::Strict }
+    };
+
+    let fields = spec
+        .data
+        .take_struct()
+        .expect("supports(struct_named)")
+        .fields;
+
+    let mut names = Vec::with_capacity(fields.len());
+    let mut lets = Vec::with_capacity(fields.len());
+    let mut idents = Vec::with_capacity(fields.len());
+
+    for f in &fields {
+        let field_ident = f.ident.as_ref().expect("named field");
+        let ty = &f.ty;
+        let name = LitStr::new(
+            &f.rename.clone().unwrap_or_else(|| field_ident.to_string()),
+            field_ident.span(),
+        );
+
+        lets.push(quote! {
+            let #field_ident = ::ben_wire::view::field_with::<#ty>(schema, row, #name, Self::COERCION)?;
+        });
+        names.push(name);
+        idents.push(field_ident);
+    }
+
+    let field_count = fields.len();
+
+    Ok(quote! {
+        impl #impl_generics ::ben_wire::view::BenView for #ident #ty_generics #where_clause {
+            const EVT_HASH: [u8; 32] = #evt_hash;
+            const EVENT: ::core::option::Option<&'static str> = #event;
+            const FIELD_NAMES: &'static [&'static str] = &[#(#names),*];
+            const FIELD_COUNT: usize = #field_count;
+            const COERCION: ::ben_wire::slot::Coercion = #coercion;
+
+            fn from_row(
+                schema: &::ben_wire::schema::Schema,
+                row: &[::ben_wire::slot::SlotValue],
+            ) -> ::core::result::Result<Self, ::ben_wire::error::ViewError> {
+                #(#lets)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+    })
+}
@@ -2,13 +2,17 @@
 pub struct ParsedPredicate {
     pub field_id: String,
     pub rule_name: String,
+    pub rule_span: proc_macro2::Span,
     pub op_tokens: proc_macro2::TokenStream,
+    /// The op as written, for [`BitInfo`](bitspec_engine::pack::BitInfo).
+    pub op_text: String,
 }
 
 #[derive(Debug)]
 pub struct ParsedThreshold {
     pub field_id: String,
     pub rule_name: String,
+    pub rule_span: proc_macro2::Span,
     pub fact: Option<String>,
     pub flags: Vec<(String, Vec<String>)>,
     pub op: Option<String>,
@@ -1,9 +1,40 @@
+use std::net::IpAddr;
+
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
-use syn::{Expr, Lit, spanned::Spanned};
+use syn::{BinOp, Expr, Lit, spanned::Spanned};
 
-pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
+/// `fields` are the struct's field names, which cross-field ops may read.
+pub fn convert_expr_to_op(expr: &Expr, fields: &[String]) -> syn::Result<TokenStream2> {
     match expr {
+        Expr::Paren(p) => convert_expr_to_op(&p.expr, fields),
+
+        Expr::Binary(bin) => {
+            let op = match bin.op {
+                BinOp::Gt(_) => quote! { Gt },
+                BinOp::Ge(_) => quote! { Gte },
+                BinOp::Lt(_) => quote! { Lt },
+                BinOp::Le(_) => quote! { Lte },
+                BinOp::Eq(_) => quote! { Eq },
+                BinOp::Ne(_) => quote! { Ne },
+                _ => {
+                    return Err(syn::Error::new(
+                        bin.op.span(),
+                        "expected a comparison: >, >=, <, <=, == or !=",
+                    ));
+                }
+            };
+            let lhs = convert_num(&bin.left, fields)?;
+            let rhs = convert_num(&bin.right, fields)?;
+            Ok(quote! {
+                ::bitspec_engine::predicate::PredOp::Compare {
+                    lhs: #lhs,
+                    op: ::bitspec_engine::predicate::CmpOp::#op,
+                    rhs: #rhs,
+                }
+            })
+        }
+
         Expr::Call(call) => {
             let func = match &*call.func {
                 Expr::Path(p) => p.path.segments.last().unwrap().ident.to_string(),
@@ -33,12 +64,67 @@ pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
                         ::bitspec_engine::predicate::PredOp::StartsWith(#s)
                     })
                 }
+                "ends_with" => {
+                    let s = parse_arg_str(&call.args, 0)?;
+                    Ok(quote! {
+                        ::bitspec_engine::predicate::PredOp::EndsWith(#s)
+                    })
+                }
                 "contains" => {
                     let s = parse_arg_str(&call.args, 0)?;
                     Ok(quote! {
                         ::bitspec_engine::predicate::PredOp::Contains(#s)
                     })
                 }
+                "glob" => {
+                    let s = parse_arg_str(&call.args, 0)?;
+                    Ok(quote! {
+                        ::bitspec_engine::predicate::PredOp::Glob(#s)
+                    })
+                }
+                "matches" => {
+                    let s = parse_arg_str(&call.args, 0)?;
+                    if let Err(e) = regex::Regex::new(&s) {
+                        return Err(syn::Error::new(
+                            call.args[0].span(),
+                            format!("invalid regex: {e}"),
+                        ));
+                    }
+                    Ok(quote! {
+                        {
+                            static RE: ::bitspec_engine::predicate::Pattern =
+                                ::bitspec_engine::predicate::Pattern::new(#s);
+                            ::bitspec_engine::predicate::PredOp::Matches(&RE)
+                        }
+                    })
+                }
+                "in_set" => convert_in_set(call),
+                "in_cidr" => {
+                    if call.args.is_empty() {
+                        return Err(syn::Error::new(
+                            call.span(),
+                            "in_cidr needs at least one network",
+                        ));
+                    }
+                    let nets = call
+                        .args
+                        .iter()
+                        .map(|a| {
+                            let (net, prefix) = parse_cidr(a)?;
+                            Ok(quote! {
+                                ::bitspec_engine::predicate::Cidr { net: #net, prefix: #prefix }
+                            })
+                        })
+                        .collect::<syn::Result<Vec<_>>>()?;
+                    Ok(quote! {
+                        {
+                            static INNER: &[::bitspec_engine::predicate::Cidr] = &[
+                                #( #nets ),*
+                            ];
+                            ::bitspec_engine::predicate::PredOp::InCidr(INNER)
+                        }
+                    })
+                }
 
                 "eq" => {
                     let arg = &call.args[0];
@@ -75,11 +161,18 @@ pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
                     })
                 }
 
+                "is_null" | "is_missing" if !call.args.is_empty() => Err(syn::Error::new(
+                    call.args.span(),
+                    format!("{func}() takes no arguments"),
+                )),
+                "is_null" => Ok(quote! { ::bitspec_engine::predicate::PredOp::IsNull }),
+                "is_missing" => Ok(quote! { ::bitspec_engine::predicate::PredOp::IsMissing }),
+
                 "all" => {
                     let inner = call
                         .args
                         .iter()
-                        .map(convert_expr_to_op)
+                        .map(|a| convert_expr_to_op(a, fields))
                         .collect::<Result<Vec<_>, _>>()?;
 
                     Ok(quote! {
@@ -96,7 +189,7 @@ pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
                     let inner = call
                         .args
                         .iter()
-                        .map(convert_expr_to_op)
+                        .map(|a| convert_expr_to_op(a, fields))
                         .collect::<Result<Vec<_>, _>>()?;
 
                     Ok(quote! {
@@ -110,7 +203,7 @@ pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
                 }
 
                 "not" => {
-                    let inner = convert_expr_to_op(&call.args[0])?;
+                    let inner = convert_expr_to_op(&call.args[0], fields)?;
                     Ok(quote! {
                         {
                             static INNER: ::bitspec_engine::predicate::PredOp = #inner;
@@ -128,11 +221,209 @@ pub fn convert_expr_to_op(expr: &Expr) -> syn::Result<TokenStream2> {
 
         _ => Err(syn::Error::new(
             expr.span(),
-            "expected function-call predicate: gt(5), eq(\"x\"), all(...), etc.",
+            "expected function-call predicate: gt(5), eq(\"x\"), all(...), etc., \
+             or a comparison such as ratio(a, b) > 10",
         )),
     }
 }
 
+/// `in_set(["a", "b"])` or `in_set([22, 3389])`, sorted for binary search.
+fn convert_in_set(call: &syn::ExprCall) -> syn::Result<TokenStream2> {
+    let Some(Expr::Array(arr)) = call.args.first().filter(|_| call.args.len() == 1) else {
+        return Err(syn::Error::new(
+            call.span(),
+            "in_set takes one array: in_set([\"a\", \"b\"]) or in_set([1, 2])",
+        ));
+    };
+    let first = arr
+        .elems
+        .first()
+        .ok_or_else(|| syn::Error::new(arr.span(), "in_set needs at least one value"))?;
+
+    if get_lit_str(first).is_ok() {
+        let mut set = arr
+            .elems
+            .iter()
+            .map(|e| get_lit_str(e).map_err(|_| mixed_set(e)))
+            .collect::<syn::Result<Vec<_>>>()?;
+        set.sort();
+        set.dedup();
+        return Ok(quote! {
+            {
+                static INNER: &[&str] = &[ #( #set ),* ];
+                ::bitspec_engine::predicate::PredOp::InStrSet(INNER)
+            }
+        });
+    }
+
+    let mut set = arr
+        .elems
+        .iter()
+        .map(expr_to_i64)
+        .collect::<syn::Result<Vec<_>>>()?;
+    set.sort_unstable();
+    set.dedup();
+    Ok(quote! {
+        {
+            static INNER: &[i64] = &[ #( #set ),* ];
+            ::bitspec_engine::predicate::PredOp::InIntSet(INNER)
+        }
+    })
+}
+
+fn expr_to_i64(expr: &Expr) -> syn::Result<i64> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(i), ..
+        }) => i.base10_parse(),
+        Expr::Unary(u) if matches!(u.op, syn::UnOp::Neg(_)) => expr_to_i64(&u.expr).map(|v| -v),
+        _ => Err(mixed_set(expr)),
+    }
+}
+
+fn mixed_set(expr: &Expr) -> syn::Error {
+    syn::Error::new(
+        expr.span(),
+        "in_set values must all be string or all integer literals",
+    )
+}
+
+/// `"10.0.0.0/8"` as an IPv6 network; IPv4 is mapped into `::ffff:0:0/96`.
+fn parse_cidr(expr: &Expr) -> syn::Result<(u128, u8)> {
+    let src = get_lit_str(expr)?;
+    let err = |msg: String| syn::Error::new(expr.span(), format!("invalid CIDR `{src}`: {msg}"));
+
+    let (addr, prefix) = src
+        .split_once('/')
+        .ok_or_else(|| err("expected ADDRESS/PREFIX".into()))?;
+    let addr: IpAddr = addr.parse().map_err(|e| err(format!("{e}")))?;
+    let prefix: u8 = prefix
+        .parse()
+        .map_err(|_| err("prefix is not a number".into()))?;
+
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix > max {
+        return Err(err(format!("prefix is longer than {max}")));
+    }
+    let (net, prefix) = match addr {
+        IpAddr::V4(v4) => (u128::from(v4.to_ipv6_mapped()), prefix + 96),
+        IpAddr::V6(v6) => (u128::from(v6), prefix),
+    };
+    let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+    if net & !mask != 0 {
+        return Err(err("host bits are set".into()));
+    }
+    Ok((net, prefix))
+}
+
+/// A `&NumExpr`: a field name, a number, `abs(x)`, `ratio(a, b)` or
+/// `+ - * /` over those.
+fn convert_num(expr: &Expr, fields: &[String]) -> syn::Result<TokenStream2> {
+    let node = match expr {
+        Expr::Paren(p) => return convert_num(&p.expr, fields),
+
+        Expr::Path(p) => {
+            let name = p
+                .path
+                .get_ident()
+                .map(|i| i.to_string())
+                .ok_or_else(|| syn::Error::new(p.span(), "expected a field name"))?;
+            if !fields.contains(&name) {
+                return Err(syn::Error::new(
+                    p.span(),
+                    format!("no field `{name}` on this struct"),
+                ));
+            }
+            quote! { ::bitspec_engine::predicate::NumExpr::Field(#name) }
+        }
+
+        Expr::Lit(_) | Expr::Unary(_) => {
+            let v = expr_to_f64(expr)?;
+            quote! { ::bitspec_engine::predicate::NumExpr::Const(#v) }
+        }
+
+        Expr::Call(call) => {
+            let func = match &*call.func {
+                Expr::Path(p) => p.path.segments.last().unwrap().ident.to_string(),
+                _ => return Err(syn::Error::new(call.func.span(), "expected op name")),
+            };
+            let arg = |idx: usize| {
+                call.args
+                    .iter()
+                    .nth(idx)
+                    .ok_or_else(|| syn::Error::new(call.span(), "Missing argument"))
+                    .and_then(|a| convert_num(a, fields))
+            };
+            match func.as_str() {
+                "abs" if call.args.len() == 1 => {
+                    let x = arg(0)?;
+                    quote! { ::bitspec_engine::predicate::NumExpr::Abs(#x) }
+                }
+                "ratio" if call.args.len() == 2 => {
+                    let (a, b) = (arg(0)?, arg(1)?);
+                    quote! {
+                        ::bitspec_engine::predicate::NumExpr::Arith {
+                            op: ::bitspec_engine::predicate::ArithOp::Div,
+                            lhs: #a,
+                            rhs: #b,
+                        }
+                    }
+                }
+                "abs" | "ratio" => {
+                    return Err(syn::Error::new(
+                        call.span(),
+                        "expected abs(x) or ratio(a, b)",
+                    ));
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        call.func.span(),
+                        format!("Unknown arithmetic op '{other}'; expected abs or ratio"),
+                    ));
+                }
+            }
+        }
+
+        Expr::Binary(bin) => {
+            let op = match bin.op {
+                BinOp::Add(_) => quote! { Add },
+                BinOp::Sub(_) => quote! { Sub },
+                BinOp::Mul(_) => quote! { Mul },
+                BinOp::Div(_) => quote! { Div },
+                _ => {
+                    return Err(syn::Error::new(
+                        bin.op.span(),
+                        "expected arithmetic: +, -, * or /",
+                    ));
+                }
+            };
+            let lhs = convert_num(&bin.left, fields)?;
+            let rhs = convert_num(&bin.right, fields)?;
+            quote! {
+                ::bitspec_engine::predicate::NumExpr::Arith {
+                    op: ::bitspec_engine::predicate::ArithOp::#op,
+                    lhs: #lhs,
+                    rhs: #rhs,
+                }
+            }
+        }
+
+        _ => {
+            return Err(syn::Error::new(
+                expr.span(),
+                "expected a field, a number, abs(...), ratio(...) or arithmetic",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        {
+            static INNER: ::bitspec_engine::predicate::NumExpr = #node;
+            &INNER
+        }
+    })
+}
+
 fn parse_arg_f64(
     args: &syn::punctuated::Punctuated<Expr, syn::token::Comma>,
     idx: usize,
@@ -210,3 +501,35 @@ fn get_lit_bool(expr: &Expr) -> syn::Result<bool> {
     }
     Err(syn::Error::new(expr.span(), "Expected bool literal"))
 }
+
+/// The op as a user would write it: `not(eq("tls"))`, `between(-1, 2.5)`.
+pub fn describe_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(bin) => {
+            let op = &bin.op;
+            format!(
+                "{} {} {}",
+                describe_expr(&bin.left),
+                quote!(#op),
+                describe_expr(&bin.right)
+            )
+        }
+        Expr::Paren(p) => format!("({})", describe_expr(&p.expr)),
+        Expr::Array(arr) => {
+            let elems: Vec<_> = arr.elems.iter().map(describe_expr).collect();
+            format!("[{}]", elems.join(", "))
+        }
+        Expr::Call(call) => {
+            let func = match &*call.func {
+                Expr::Path(p) => quote!(#p).to_string().replace(' ', ""),
+                other => quote!(#other).to_string(),
+            };
+            let args: Vec<_> = call.args.iter().map(describe_expr).collect();
+            format!("{func}({})", args.join(", "))
+        }
+        Expr::Unary(u) if matches!(u.op, syn::UnOp::Neg(_)) => {
+            format!("-{}", describe_expr(&u.expr))
+        }
+        other => quote!(#other).to_string(),
+    }
+}
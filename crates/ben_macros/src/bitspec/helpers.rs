@@ -1,16 +1,48 @@
-use proc_macro2::TokenStream as TokenStream2;
+use heck::ToShoutySnakeCase;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
-use syn::{DeriveInput, Expr, LitStr, spanned::Spanned};
+use syn::{DeriveInput, Expr, Ident, LitInt, LitStr};
 
 use crate::bitspec::data_objs::{ParsedPredicate, ParsedThreshold};
 use crate::bitspec::dsl;
 
+/// Mask width in bits when the struct doesn't set `#[bspec(width = N)]`.
+const DEFAULT_WIDTH: usize = 128;
+/// Bit indices and `bit_count` are `u16`.
+const MAX_WIDTH: usize = u16::MAX as usize / 64 * 64;
+
 pub struct ParsedBitspec {
     pub preds: Vec<ParsedPredicate>,
     pub thresh: Vec<ParsedThreshold>,
+    /// In 64-bit words.
+    pub words: usize,
+}
+
+/// `#[bspec(width = N)]` on the struct, as a word count.
+fn parse_width(input: &DeriveInput) -> syn::Result<usize> {
+    let mut words = DEFAULT_WIDTH / 64;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("bspec")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("width") {
+                return Err(meta.error("unknown key in struct-level #[bspec(...)]"));
+            }
+            let lit: LitInt = meta.value()?.parse()?;
+            let width: usize = lit.base10_parse()?;
+            if width == 0 || !width.is_multiple_of(64) || width > MAX_WIDTH {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    format!("bspec width must be a multiple of 64 between 64 and {MAX_WIDTH}"),
+                ));
+            }
+            words = width / 64;
+            Ok(())
+        })?;
+    }
+    Ok(words)
 }
 
-pub fn parse_struct_fields(input: &DeriveInput) -> ParsedBitspec {
+pub fn parse_struct_fields(input: &DeriveInput) -> syn::Result<ParsedBitspec> {
+    let words = parse_width(input)?;
     let mut preds = Vec::new();
     let mut thresh = Vec::new();
 
@@ -19,6 +51,11 @@ pub fn parse_struct_fields(input: &DeriveInput) -> ParsedBitspec {
         _ => panic!("#[derive(Bitspec)] only supported on structs"),
     };
 
+    let field_names: Vec<String> = struct_fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+        .collect();
+
     for field in struct_fields {
         let field_ident = field.ident.as_ref().unwrap();
         let field_name = field_ident.to_string();
@@ -30,7 +67,7 @@ pub fn parse_struct_fields(input: &DeriveInput) -> ParsedBitspec {
 
             attr.parse_nested_meta(|meta| {
                 if meta.path.is_ident("brule") {
-                    preds.push(parse_brule(&field_name, meta)?);
+                    preds.push(parse_brule(&field_name, &field_names, meta)?);
                     return Ok(());
                 }
 
@@ -40,22 +77,28 @@ pub fn parse_struct_fields(input: &DeriveInput) -> ParsedBitspec {
                 }
 
                 Err(meta.error("Unknown key inside #[bspec(...)]"))
-            })
-            .expect("failed parsing #[bspec]");
+            })?;
         }
     }
 
-    ParsedBitspec { preds, thresh }
+    Ok(ParsedBitspec {
+        preds,
+        thresh,
+        words,
+    })
 }
 
-fn parse_brule(field: &str, meta: syn::meta::ParseNestedMeta) -> syn::Result<ParsedPredicate> {
-    let mut rule_name: Option<String> = None;
+fn parse_brule(
+    field: &str,
+    fields: &[String],
+    meta: syn::meta::ParseNestedMeta,
+) -> syn::Result<ParsedPredicate> {
+    let mut rule_name: Option<LitStr> = None;
     let mut op_expr: Option<Expr> = None;
 
     meta.parse_nested_meta(|nested| {
         if nested.path.is_ident("rule") {
-            let lit: LitStr = nested.value()?.parse()?;
-            rule_name = Some(lit.value());
+            rule_name = Some(nested.value()?.parse()?);
             return Ok(());
         }
 
@@ -71,12 +114,14 @@ fn parse_brule(field: &str, meta: syn::meta::ParseNestedMeta) -> syn::Result<Par
     let rule_name = rule_name.ok_or_else(|| meta.error("brule requires rule=\"...\""))?;
     let op_raw = op_expr.ok_or_else(|| meta.error("brule requires op=..."))?;
 
-    let op_tokens = dsl::convert_expr_to_op(&op_raw)?;
+    let op_tokens = dsl::convert_expr_to_op(&op_raw, fields)?;
 
     Ok(ParsedPredicate {
         field_id: field.to_string(),
-        rule_name,
+        rule_name: rule_name.value(),
+        rule_span: rule_name.span(),
         op_tokens,
+        op_text: dsl::describe_expr(&op_raw),
     })
 }
 
@@ -89,8 +134,7 @@ fn parse_thresholds(field: &str, meta: syn::meta::ParseNestedMeta) -> syn::Resul
 
     meta.parse_nested_meta(|nested| {
         if nested.path.is_ident("rule") {
-            let lit: LitStr = nested.value()?.parse()?;
-            rule = Some(lit.value());
+            rule = Some(nested.value()?.parse::<LitStr>()?);
             return Ok(());
         }
 
@@ -162,9 +206,12 @@ fn parse_thresholds(field: &str, meta: syn::meta::ParseNestedMeta) -> syn::Resul
         levels.push((name.trim().to_string(), value));
     }
 
+    let rule = rule.ok_or_else(|| meta.error("thresholds requires rule=\"...\""))?;
+
     Ok(ParsedThreshold {
         field_id: field.to_string(),
-        rule_name: rule.ok_or_else(|| meta.error("thresholds requires rule=\"...\""))?,
+        rule_name: rule.value(),
+        rule_span: rule.span(),
         fact,
         flags,
         op,
@@ -172,34 +219,110 @@ fn parse_thresholds(field: &str, meta: syn::meta::ParseNestedMeta) -> syn::Resul
     })
 }
 
-pub fn make_codegen_items(
-    parsed: &ParsedBitspec,
-) -> syn::Result<(Vec<TokenStream2>, Vec<TokenStream2>, u16)> {
-    let mut next_bit: u16 = 0;
+pub struct Codegen {
+    pub preds: Vec<TokenStream2>,
+    pub thresh: Vec<TokenStream2>,
+    /// `BitInfo` literals, in bit order.
+    pub bits: Vec<TokenStream2>,
+    /// Associated consts naming each rule's bits.
+    pub consts: Vec<TokenStream2>,
+    pub bit_count: u16,
+}
+
+/// One associated const per rule name (threshold levels are `RULE_LEVEL`);
+/// a name used on several fields covers all their bits.
+struct BitName {
+    ident: Ident,
+    bits: Vec<u16>,
+    docs: Vec<String>,
+}
+
+/// Bits are handed out in field order: every `brule` first, then every
+/// threshold level. Fails when they don't fit the struct's width.
+pub fn make_codegen_items(input: &DeriveInput, parsed: &ParsedBitspec) -> syn::Result<Codegen> {
+    let width = parsed.words * 64;
+    let allocated =
+        parsed.preds.len() + parsed.thresh.iter().map(|t| t.levels.len()).sum::<usize>();
+    if allocated > width {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            format!(
+                "`{}` allocates {allocated} bits but its mask is {width} bits wide; \
+                 raise #[bspec(width = ...)] to a multiple of 64 that fits",
+                input.ident
+            ),
+        ));
+    }
+
+    let mut next_bit: usize = 0;
+    let mut bits = Vec::<TokenStream2>::new();
+    let mut names = Vec::<BitName>::new();
+    let mut name_bit = |name: &str, span: Span, bit: u16, doc: String| -> syn::Result<()> {
+        let const_name = name.to_shouty_snake_case();
+        let ident = syn::parse_str::<Ident>(&const_name)
+            .ok()
+            .filter(|_| const_name != "BITSPEC_PACK")
+            .ok_or_else(|| {
+                syn::Error::new(
+                    span,
+                    format!("bitspec rule `{name}` cannot become the const `{const_name}`"),
+                )
+            })?;
+        match names.iter_mut().find(|n| n.ident == ident) {
+            Some(n) => {
+                n.bits.push(bit);
+                n.docs.push(doc);
+            }
+            None => names.push(BitName {
+                ident: Ident::new(&const_name, span),
+                bits: vec![bit],
+                docs: vec![doc],
+            }),
+        }
+        Ok(())
+    };
 
     let mut pred_items = Vec::<TokenStream2>::new();
     for ParsedPredicate {
         field_id,
-        rule_name: _,
+        rule_name,
+        rule_span,
         op_tokens,
+        op_text,
     } in &parsed.preds
     {
-        let bit = next_bit;
+        let bit = next_bit as u16;
         next_bit += 1;
 
         pred_items.push(quote! {
             ::bitspec_engine::predicate::PredicateSpec {
                 field_id: #field_id,
-                bit: (1u64 << #bit),
+                bit: #bit,
                 op: #op_tokens,
             }
         });
+        bits.push(quote! {
+            ::bitspec_engine::pack::BitInfo {
+                bit: #bit,
+                rule: #rule_name,
+                level: None,
+                field: #field_id,
+                op: #op_text,
+            }
+        });
+        name_bit(
+            rule_name,
+            *rule_span,
+            bit,
+            format!("`{field_id} {op_text}`"),
+        )?;
     }
 
     let mut thresh_items = Vec::<TokenStream2>::new();
     for ParsedThreshold {
         field_id,
-        rule_name: _,
+        rule_name,
+        rule_span,
         fact,
         flags,
         op,
@@ -209,7 +332,7 @@ pub fn make_codegen_items(
         let mut level_tokens = Vec::<TokenStream2>::new();
 
         for (lvl_name, lvl_val) in levels {
-            let bit = next_bit;
+            let bit = next_bit as u16;
             next_bit += 1;
 
             level_tokens.push(quote! {
@@ -219,6 +342,23 @@ pub fn make_codegen_items(
                     bit: #bit,
                 }
             });
+
+            let op_text = format!("{}({lvl_val})", op.as_deref().unwrap_or_default());
+            bits.push(quote! {
+                ::bitspec_engine::pack::BitInfo {
+                    bit: #bit,
+                    rule: #rule_name,
+                    level: Some(#lvl_name),
+                    field: #field_id,
+                    op: #op_text,
+                }
+            });
+            name_bit(
+                &format!("{rule_name}_{lvl_name}"),
+                *rule_span,
+                bit,
+                format!("`{field_id} {op_text}`"),
+            )?;
         }
 
         let op_ts = match op.as_deref() {
@@ -260,5 +400,24 @@ pub fn make_codegen_items(
         });
     }
 
-    Ok((pred_items, thresh_items, next_bit))
+    let words = parsed.words;
+    let consts = names
+        .iter()
+        .map(|BitName { ident, bits, docs }| {
+            let doc = docs.join("; ");
+            quote! {
+                #[doc = #doc]
+                pub const #ident: ::bitspec_engine::BitMask<#words> =
+                    ::bitspec_engine::BitMask::<#words>::EMPTY #( .with(#bits).unwrap() )*;
+            }
+        })
+        .collect();
+
+    Ok(Codegen {
+        preds: pred_items,
+        thresh: thresh_items,
+        bits,
+        consts,
+        bit_count: allocated as u16,
+    })
 }
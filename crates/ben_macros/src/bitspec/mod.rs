@@ -2,42 +2,60 @@ mod data_objs;
 mod dsl;
 mod helpers;
 
+use heck::ToSnakeCase;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::DeriveInput;
 
-use crate::bitspec::helpers::{make_codegen_items, parse_struct_fields};
+use crate::bitspec::helpers::{Codegen, make_codegen_items, parse_struct_fields};
 
 pub fn expand_bitspec(ast: &DeriveInput) -> syn::Result<TokenStream2> {
     let struct_name = &ast.ident;
 
-    let parsed = parse_struct_fields(ast);
+    let parsed = parse_struct_fields(ast)?;
+    let words = parsed.words;
 
-    let (pred_items, thresh_items, bit_count) = make_codegen_items(&parsed)?;
+    let Codegen {
+        preds,
+        thresh,
+        bits,
+        consts,
+        bit_count,
+    } = make_codegen_items(ast, &parsed)?;
 
     let mod_name = syn::Ident::new(
-        &format!("__bitspec_generated_{}", struct_name),
+        &format!(
+            "__bitspec_generated_{}",
+            struct_name.to_string().to_snake_case()
+        ),
         struct_name.span(),
     );
 
     let expanded = quote! {
         mod #mod_name {
             use super::*;
-            pub static PRED_LIST: &[PredicateSpec] = &[
-                #(#pred_items),*
+            pub static PRED_LIST: &[::bitspec_engine::predicate::PredicateSpec] = &[
+                #(#preds),*
             ];
 
-            pub static THRESH_LIST: &[ThresholdSpec] = &[
-                #(#thresh_items),*
+            pub static THRESH_LIST: &[::bitspec_engine::threshold::ThresholdSpec] = &[
+                #(#thresh),*
+            ];
+
+            pub static BIT_LIST: &[::bitspec_engine::pack::BitInfo] = &[
+                #(#bits),*
             ];
         }
 
         impl #struct_name {
-            pub const BITSPEC_PACK: BitspecPack = BitspecPack {
+            pub const BITSPEC_PACK: ::bitspec_engine::pack::BitspecPack<#words> = ::bitspec_engine::pack::BitspecPack {
                 predicates: #mod_name::PRED_LIST,
                 thresholds: #mod_name::THRESH_LIST,
                 bit_count: #bit_count,
+                bits: #mod_name::BIT_LIST,
             };
+
+            #(#consts)*
         }
     };
 
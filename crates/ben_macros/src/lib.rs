@@ -1,4 +1,5 @@
 mod ben_enum;
+mod ben_view;
 mod bitspec;
 mod blot;
 mod lucius;
@@ -29,6 +30,16 @@ pub fn ben_enum(input: TokenStream) -> TokenStream {
     }
 }
 
+#[proc_macro_derive(BenView, attributes(bview))]
+pub fn ben_view(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match ben_view::derive_ben_view(ast) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 #[proc_macro_derive(Bitspec, attributes(bspec))]
 pub fn bitspec(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -3,36 +3,88 @@ use quote::quote;
 use syn::{Type, spanned::Spanned};
 
 use crate::schema::{
-    helpers::{hashmap_key_value, is_ben_enum, option_inner_ty, vec_inner_ty},
+    helpers::{
+        Scalar, borrowed_ty, hashmap_key_value, is_byte_vec, is_named_type, is_string,
+        option_inner_ty, scalar_kind, vec_inner_ty,
+    },
     validate::SchemaField,
 };
 
-pub fn encode_inner_element(ty: &Type, var_name: &str) -> syn::Result<TokenStream2> {
+/// Writes the `BenEnum` behind the reference `value` as its `Enum8`/`Enum16`.
+fn encode_enum(ty: &Type, value: TokenStream2) -> TokenStream2 {
+    quote! {
+        ::ben_wire::ch_binary::write_enum(
+            ::ben_contracts::BenEnumDesc::to_i16(#value),
+            <#ty as ::ben_contracts::BenEnumDesc>::BITS,
+            out,
+        );
+    }
+}
+
+/// Writes the scalar behind the reference `value`.
+fn encode_scalar(kind: Scalar, value: TokenStream2, scale: u32) -> TokenStream2 {
+    match kind {
+        Scalar::Ipv4 => quote! { ::ben_wire::ch_binary::write_ipv4(u32::from(*#value), out); },
+        Scalar::Ipv6 => quote! { ::ben_wire::ch_binary::write_ipv6(u128::from(*#value), out); },
+        Scalar::Ip => quote! { ::ben_wire::ch_binary::write_ip(*#value, out); },
+        Scalar::Uuid => quote! {
+            ::ben_wire::ch_binary::write_uuid(
+                &::ben_wire::ch_value::UuidValue::uuid_bytes(#value),
+                out,
+            );
+        },
+        Scalar::DateTime => quote! {
+            ::ben_wire::ch_binary::write_datetime64(
+                ::ben_wire::ch_value::DateTime64Value::to_ticks(#value, #scale),
+                out,
+            );
+        },
+    }
+}
+
+fn decode_scalar(kind: Scalar, ty: &Type, scale: u32) -> TokenStream2 {
+    match kind {
+        Scalar::Ipv4 => quote! { ::std::net::Ipv4Addr::from(cur.read_ipv4()?) },
+        Scalar::Ipv6 => quote! { ::std::net::Ipv6Addr::from(cur.read_ipv6()?) },
+        Scalar::Ip => quote! { cur.read_ip()? },
+        Scalar::Uuid => quote! {
+            <#ty as ::ben_wire::ch_value::UuidValue>::from_uuid_bytes(cur.read_uuid()?)
+        },
+        Scalar::DateTime => quote! {
+            {
+                let ticks = cur.read_datetime64()?;
+                <#ty as ::ben_wire::ch_value::DateTime64Value>::from_ticks(ticks, #scale)
+                    .ok_or_else(|| ::anyhow::anyhow!("DateTime64({}) value {} is out of range", #scale, ticks))?
+            }
+        },
+    }
+}
+
+pub fn encode_inner_element(ty: &Type, var_name: &str, scale: u32) -> syn::Result<TokenStream2> {
     use syn::{TypeArray, TypePath};
     let var_ident = quote::format_ident!("{}", var_name);
 
+    if let Some(kind) = scalar_kind(ty) {
+        return Ok(encode_scalar(kind, quote!(#var_ident), scale));
+    }
+
     if let Type::Path(TypePath { path, .. }) = ty {
         if let Some(seg) = path.segments.last() {
             let name = seg.ident.to_string();
             // Note: `var_ident` is a reference (&T). We must dereference for Copy types.
             match name.as_str() {
                 "u8" => return Ok(quote!( out.push(*#var_ident); )),
-                "u64" | "i64" | "u32" | "u128" => {
+                "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" => {
                     return Ok(quote!( out.extend_from_slice(&(*#var_ident).to_le_bytes()); ));
                 }
-                "f64" => {
+                "f32" | "f64" => {
                     return Ok(
                         quote!( out.extend_from_slice(&(*#var_ident).to_bits().to_le_bytes()); ),
                     );
                 }
                 "bool" => return Ok(quote!( out.push(*#var_ident as u8); )),
                 "String" | "&str" => {
-                    return Ok(quote!({
-                        let bytes = #var_ident.as_bytes();
-                        let len = bytes.len() as u32;
-                        out.extend_from_slice(&len.to_le_bytes());
-                        out.extend_from_slice(bytes);
-                    }));
+                    return Ok(quote!( ::ben_wire::ch_binary::write_str(#var_ident, out); ));
                 }
 
                 _ => {}
@@ -40,10 +92,8 @@ pub fn encode_inner_element(ty: &Type, var_name: &str) -> syn::Result<TokenStrea
         }
     }
 
-    if is_ben_enum(ty) {
-        return Ok(quote! {
-            out.extend_from_slice(&#var_ident.__ben_enum_to_i16().to_le_bytes());
-        });
+    if is_named_type(ty) {
+        return Ok(encode_enum(ty, quote!(#var_ident)));
     }
 
     if let Type::Array(TypeArray { elem, .. }) = ty {
@@ -70,13 +120,7 @@ pub fn encode_stmt_for_field(
     if let Some(ref et) = f.enum_type {
         return match et.as_str() {
             "string" => Ok(quote! {
-                {
-                    let s = self.#field_ident.as_str();
-                    let bytes = s.as_bytes();
-                    let len = bytes.len() as u32;
-                    out.extend_from_slice(&len.to_le_bytes());
-                    out.extend_from_slice(bytes);
-                }
+                ::ben_wire::ch_binary::write_str(self.#field_ident.as_str(), out);
             }),
             "u64" => Ok(quote! {
                 {
@@ -103,25 +147,42 @@ pub fn encode_stmt_for_field(
         };
     }
 
+    // A Tuple and a flattened child are both the child's columns back to back.
+    if f.nested.is_some() {
+        return Ok(match vec_inner_ty(ty) {
+            Some(_) => quote! {
+                {
+                    let items = &self.#field_ident;
+                    ::ben_wire::ch_binary::write_uvarint(items.len(), out);
+                    for elem in items {
+                        ::ben_wire::rowbinary::RowBinaryEncode::encode_rowbinary(elem, out)?;
+                    }
+                }
+            },
+            None => quote! {
+                ::ben_wire::rowbinary::RowBinaryEncode::encode_rowbinary(&self.#field_ident, out)?;
+            },
+        });
+    }
+
     if let Some(inner_ty) = option_inner_ty(ty) {
-        let inner_stmt = encode_inner_element(inner_ty, "elem")?;
+        let inner_stmt = encode_inner_element(inner_ty, "elem", f.scale())?;
         return Ok(quote! {
             if let Some(ref elem) = self.#field_ident {
-                out.push(1); // Not Null
+                ::ben_wire::ch_binary::write_null_marker(false, out);
                 #inner_stmt
             } else {
-                out.push(0); // Null
+                ::ben_wire::ch_binary::write_null_marker(true, out);
             }
         });
     }
 
     if let Some(inner_ty) = vec_inner_ty(ty) {
-        let encode_elem = encode_inner_element(inner_ty, "elem")?;
+        let encode_elem = encode_inner_element(inner_ty, "elem", f.scale())?;
         return Ok(quote! {
             {
                 let items = &self.#field_ident;
-                let len = items.len() as u64;
-                out.extend_from_slice(&len.to_le_bytes());
+                ::ben_wire::ch_binary::write_uvarint(items.len(), out);
                 for elem in items {
                     #encode_elem
                 }
@@ -130,13 +191,12 @@ pub fn encode_stmt_for_field(
     }
 
     if let Some((key_ty, val_ty)) = hashmap_key_value(ty) {
-        let key_enc = encode_inner_element(key_ty, "k")?;
-        let val_enc = encode_inner_element(val_ty, "v")?;
+        let key_enc = encode_inner_element(key_ty, "k", f.scale())?;
+        let val_enc = encode_inner_element(val_ty, "v", f.scale())?;
         return Ok(quote! {
             {
                 let items = &self.#field_ident;
-                let len = items.len() as u64;
-                out.extend_from_slice(&len.to_le_bytes());
+                ::ben_wire::ch_binary::write_uvarint(items.len(), out);
                 for (k, v) in items {
                     #key_enc
                     #val_enc
@@ -145,10 +205,12 @@ pub fn encode_stmt_for_field(
         });
     }
 
-    if is_ben_enum(ty) {
-        return Ok(quote! {
-            out.extend_from_slice(&self.#field_ident.__ben_enum_to_i16().to_le_bytes());
-        });
+    if let Some(kind) = scalar_kind(ty) {
+        return Ok(encode_scalar(kind, quote!(&self.#field_ident), f.scale()));
+    }
+
+    if is_named_type(ty) {
+        return Ok(encode_enum(ty, quote!(&self.#field_ident)));
     }
 
     if let Type::Array(TypeArray { elem, .. }) = ty {
@@ -173,16 +235,15 @@ pub fn encode_stmt_for_field(
     let name = seg.ident.to_string();
 
     Ok(match name.as_str() {
-        "u64" | "i64" | "u32" | "u128" => {
+        "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" => {
             quote! { out.extend_from_slice(&self.#field_ident.to_le_bytes()); }
         }
-        "f64" => quote! { out.extend_from_slice(&self.#field_ident.to_bits().to_le_bytes()); },
+        "f32" | "f64" => {
+            quote! { out.extend_from_slice(&self.#field_ident.to_bits().to_le_bytes()); }
+        }
         "bool" => quote! { out.push(self.#field_ident as u8); },
         "String" | "&str" => quote! {
-            let bytes = self.#field_ident.as_bytes();
-            let len: u32 = bytes.len() as u32;
-            out.extend_from_slice(&len.to_le_bytes());
-            out.extend_from_slice(bytes);
+            ::ben_wire::ch_binary::write_str(&self.#field_ident, out);
         },
         _ => {
             return Err(syn::Error::new(
@@ -198,10 +259,10 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         let inner_expr = decode_expr_for_type(inner_ty, f)?;
         return Ok(quote! {
             {
-                if cur.read_u8()? == 1 {
-                    Some(#inner_expr)
-                } else {
+                if cur.read_null_marker()? {
                     None
+                } else {
+                    Some(#inner_expr)
                 }
             }
         });
@@ -211,8 +272,8 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         let inner_decode = decode_expr_for_type(inner_ty, f)?;
         return Ok(quote! {
             {
-                let len = cur.read_u64()?;
-                let mut vec = Vec::with_capacity(len as usize);
+                let len = cur.read_len()?;
+                let mut vec = Vec::with_capacity(len);
                 for _ in 0..len {
                     let val = #inner_decode;
                     vec.push(val);
@@ -227,8 +288,8 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         let val_expr = decode_expr_for_type(val_ty, f)?;
         return Ok(quote! {
             {
-                let len = cur.read_u64()?;
-                let mut map = ::std::collections::HashMap::with_capacity(len as usize);
+                let len = cur.read_len()?;
+                let mut map = ::std::collections::HashMap::with_capacity(len);
                 for _ in 0..len {
                     let k = #key_expr;
                     let v = #val_expr;
@@ -276,11 +337,21 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         };
     }
 
-    if is_ben_enum(ty) {
+    if let Some(kind) = scalar_kind(ty) {
+        return Ok(decode_scalar(kind, ty, f.scale()));
+    }
+
+    if is_named_type(ty) && f.nested.is_some() {
+        return Ok(quote! {
+            <#ty as ::ben_wire::rowbinary::RowBinaryNested>::decode_nested(cur)?
+        });
+    }
+
+    if is_named_type(ty) {
         return Ok(quote! {
             {
-                let val = cur.read_i16()?;
-                <#ty>::__ben_enum_from_i16(val)
+                let val = cur.read_enum(<#ty as ::ben_contracts::BenEnumDesc>::BITS)?;
+                <#ty as ::ben_contracts::BenEnumDesc>::from_i16(val)
                     .ok_or_else(|| ::anyhow::anyhow!("Invalid enum value {}", val))?
             }
         });
@@ -290,8 +361,10 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         if let Some(seg) = tp.path.segments.last() {
             let id = seg.ident.to_string();
             return match id.as_str() {
-                "u64" => Ok(quote! { cur.read_u64()? }),
+                "u8" => Ok(quote! { cur.read_u8()? }),
+                "u16" => Ok(quote! { cur.read_u16()? }),
                 "u32" => Ok(quote! { cur.read_u32()? }),
+                "u64" => Ok(quote! { cur.read_u64()? }),
                 "u128" => Ok(quote! { cur.read_u128()? }),
                 "i64" => Ok(quote! { cur.read_i64()? }),
                 "i32" => Ok(quote! { cur.read_i32()? }),
@@ -317,3 +390,66 @@ pub fn decode_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStre
         "BenSchema: unsupported RowBinaryDecode type",
     ))
 }
+
+/// Like [`decode_expr_for_type`], but strings and `Vec<u8>` borrow from the
+/// cursor's buffer. Types with nothing to borrow decode as usual.
+pub fn decode_ref_expr_for_type(ty: &Type, f: &SchemaField) -> syn::Result<TokenStream2> {
+    if f.enum_type.is_some() || borrowed_ty(ty).is_none() {
+        return decode_expr_for_type(ty, f);
+    }
+
+    if is_string(ty) {
+        return Ok(quote! { cur.read_str()? });
+    }
+
+    if is_byte_vec(ty) {
+        return Ok(quote! { cur.read_byte_array()? });
+    }
+
+    if let Some(inner_ty) = option_inner_ty(ty) {
+        let inner_expr = decode_ref_expr_for_type(inner_ty, f)?;
+        return Ok(quote! {
+            {
+                if cur.read_null_marker()? {
+                    None
+                } else {
+                    Some(#inner_expr)
+                }
+            }
+        });
+    }
+
+    if let Some(inner_ty) = vec_inner_ty(ty) {
+        let inner_decode = decode_ref_expr_for_type(inner_ty, f)?;
+        return Ok(quote! {
+            {
+                let len = cur.read_len()?;
+                let mut vec = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let val = #inner_decode;
+                    vec.push(val);
+                }
+                vec
+            }
+        });
+    }
+
+    if let Some((key_ty, val_ty)) = hashmap_key_value(ty) {
+        let key_expr = decode_ref_expr_for_type(key_ty, f)?;
+        let val_expr = decode_ref_expr_for_type(val_ty, f)?;
+        return Ok(quote! {
+            {
+                let len = cur.read_len()?;
+                let mut map = ::std::collections::HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let k = #key_expr;
+                    let v = #val_expr;
+                    map.insert(k, v);
+                }
+                map
+            }
+        });
+    }
+
+    decode_expr_for_type(ty, f)
+}
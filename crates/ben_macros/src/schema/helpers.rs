@@ -60,6 +60,48 @@ pub fn hashmap_key_value(ty: &syn::Type) -> Option<(&syn::Type, &syn::Type)> {
     Some((key_ty, val_ty))
 }
 
+fn is_ident(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident(name))
+}
+
+pub fn is_string(ty: &Type) -> bool {
+    is_ident(ty, "String")
+}
+
+/// `Vec<u8>`, which the borrowed view exposes as `&'a [u8]`.
+pub fn is_byte_vec(ty: &Type) -> bool {
+    vec_inner_ty(ty).is_some_and(|inner| is_ident(inner, "u8"))
+}
+
+/// Field type in the `#[bschema(borrowed)]` view, or `None` when the field
+/// has nothing to borrow and keeps its owned type.
+pub fn borrowed_ty(ty: &Type) -> Option<Type> {
+    if is_string(ty) {
+        return Some(syn::parse_quote!(&'a str));
+    }
+    if is_byte_vec(ty) {
+        return Some(syn::parse_quote!(&'a [u8]));
+    }
+    if let Some(inner) = option_inner_ty(ty) {
+        let inner = borrowed_ty(inner)?;
+        return Some(syn::parse_quote!(::std::option::Option<#inner>));
+    }
+    if let Some(inner) = vec_inner_ty(ty) {
+        let inner = borrowed_ty(inner)?;
+        return Some(syn::parse_quote!(::std::vec::Vec<#inner>));
+    }
+    if let Some((k, v)) = hashmap_key_value(ty) {
+        let (bk, bv) = (borrowed_ty(k), borrowed_ty(v));
+        if bk.is_none() && bv.is_none() {
+            return None;
+        }
+        let k = bk.unwrap_or_else(|| k.clone());
+        let v = bv.unwrap_or_else(|| v.clone());
+        return Some(syn::parse_quote!(::std::collections::HashMap<#k, #v>));
+    }
+    None
+}
+
 pub fn is_primitive(ty: &Type) -> bool {
     if let syn::Type::Path(tp) = ty {
         if let Some(seg) = tp.path.segments.last() {
@@ -69,6 +111,7 @@ pub fn is_primitive(ty: &Type) -> bool {
                 "u8" | "u16"
                     | "u32"
                     | "u64"
+                    | "u128"
                     | "i8"
                     | "i16"
                     | "i32"
@@ -84,40 +127,112 @@ pub fn is_primitive(ty: &Type) -> bool {
     false
 }
 
-pub fn is_ben_enum(ty: &Type) -> bool {
-    // If it's a TypePath but NOT Option, Vec, HashMap, or Primitive, we assume it's a BenEnum.
-    !option_inner_ty(ty).is_some()
-        && !vec_inner_ty(ty).is_some()
-        && !hashmap_key_value(ty).is_some()
+/// Library types with a fixed ClickHouse type, matched by name like the
+/// primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar {
+    /// `Ipv4Addr` as `IPv4`.
+    Ipv4,
+    /// `Ipv6Addr` as `IPv6`.
+    Ipv6,
+    /// `IpAddr` as `IPv6`, IPv4 addresses IPv4-mapped.
+    Ip,
+    /// `uuid::Uuid` as `UUID`.
+    Uuid,
+    /// `SystemTime`, `time::OffsetDateTime` or `chrono::DateTime<Utc>` as
+    /// `DateTime64(scale)`.
+    DateTime,
+}
+
+pub fn scalar_kind(ty: &Type) -> Option<Scalar> {
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    let seg = tp.path.segments.last()?;
+    let bare = seg.arguments.is_none();
+    match seg.ident.to_string().as_str() {
+        "Ipv4Addr" if bare => Some(Scalar::Ipv4),
+        "Ipv6Addr" if bare => Some(Scalar::Ipv6),
+        "IpAddr" if bare => Some(Scalar::Ip),
+        "Uuid" if bare => Some(Scalar::Uuid),
+        "SystemTime" | "OffsetDateTime" if bare => Some(Scalar::DateTime),
+        "DateTime" if !bare => Some(Scalar::DateTime),
+        _ => None,
+    }
+}
+
+/// A plain named type that is none of the built-in column types. On its own
+/// it must be a `BenEnum`, which the generated code checks through
+/// `BenEnumDesc`; with `nested = ...` it is a nested `BenSchema` struct.
+pub fn is_named_type(ty: &Type) -> bool {
+    let Type::Path(tp) = ty else {
+        return false;
+    };
+    tp.qself.is_none()
+        && tp
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.arguments.is_none())
+        && option_inner_ty(ty).is_none()
+        && vec_inner_ty(ty).is_none()
+        && hashmap_key_value(ty).is_none()
         && !is_primitive(ty)
-        && matches!(ty, Type::Path(_))
+        && scalar_kind(ty).is_none()
 }
 
-pub fn clickhouse_type_for(ty: &syn::Type) -> syn::Result<String> {
+/// Stands in for a `BenEnum`'s `Enum8(...)`/`Enum16(...)` in the result of
+/// [`clickhouse_type_for`]; only the enum's derive knows its variants.
+pub const ENUM_SLOT: &str = "\u{E001}";
+
+/// The `BenEnum`s in `ty`, in the order [`clickhouse_type_for`] writes them.
+pub fn enum_tys(ty: &Type) -> Vec<&Type> {
+    if let Some(inner) = option_inner_ty(ty).or_else(|| vec_inner_ty(ty)) {
+        return enum_tys(inner);
+    }
+    if let Some((k, v)) = hashmap_key_value(ty) {
+        let mut out = enum_tys(k);
+        out.extend(enum_tys(v));
+        return out;
+    }
+    if is_named_type(ty) { vec![ty] } else { vec![] }
+}
+
+/// `scale` is the precision of `DateTime64` columns.
+pub fn clickhouse_type_for(ty: &syn::Type, scale: u32) -> syn::Result<String> {
     use syn::TypePath;
 
     // Option<T> -> Nullable(T)
     if let Some(inner) = option_inner_ty(ty) {
-        let inner_ty = clickhouse_type_for(inner)?;
+        let inner_ty = clickhouse_type_for(inner, scale)?;
         return Ok(format!("Nullable({inner_ty})"));
     }
 
     // Vec<T> -> Array(T)
     if let Some(inner) = vec_inner_ty(ty) {
-        let inner_ty = clickhouse_type_for(inner)?;
+        let inner_ty = clickhouse_type_for(inner, scale)?;
         return Ok(format!("Array({inner_ty})"));
     }
 
     // HashMap<K,V> -> Map(K,V)
     if let Some((k, v)) = hashmap_key_value(ty) {
-        let k_ty = clickhouse_type_for(k)?;
-        let v_ty = clickhouse_type_for(v)?;
+        let k_ty = clickhouse_type_for(k, scale)?;
+        let v_ty = clickhouse_type_for(v, scale)?;
         return Ok(format!("Map({k_ty}, {v_ty})"));
     }
 
-    // BenEnum -> Int16 (Implicit)
-    if is_ben_enum(ty) {
-        return Ok("Int16".to_string());
+    if let Some(scalar) = scalar_kind(ty) {
+        return Ok(match scalar {
+            Scalar::Ipv4 => "IPv4".into(),
+            Scalar::Ipv6 | Scalar::Ip => "IPv6".into(),
+            Scalar::Uuid => "UUID".into(),
+            Scalar::DateTime => format!("DateTime64({scale})"),
+        });
+    }
+
+    // BenEnum -> Enum8/Enum16, filled in at compile time
+    if is_named_type(ty) {
+        return Ok(ENUM_SLOT.to_string());
     }
 
     // Primitive types
@@ -129,6 +244,7 @@ pub fn clickhouse_type_for(ty: &syn::Type) -> syn::Result<String> {
                 "u16" => Ok("UInt16".into()),
                 "u32" => Ok("UInt32".into()),
                 "u64" => Ok("UInt64".into()),
+                "u128" => Ok("UInt128".into()),
                 "i8" => Ok("Int8".into()),
                 "i16" => Ok("Int16".into()),
                 "i32" => Ok("Int32".into()),
@@ -5,12 +5,12 @@ mod validate;
 
 use darling::FromDeriveInput;
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
-use syn::{DeriveInput, LitStr, spanned::Spanned};
+use quote::{ToTokens, quote};
+use syn::{DeriveInput, LitStr};
 
 use crate::schema::{
-    encoding::{decode_expr_for_type, encode_stmt_for_field},
-    helpers::{clickhouse_type_for, option_inner_ty},
+    encoding::{decode_expr_for_type, decode_ref_expr_for_type, encode_stmt_for_field},
+    helpers::{borrowed_ty, clickhouse_type_for, option_inner_ty, vec_inner_ty},
 };
 
 pub fn derive_ben_schema(input: DeriveInput) -> syn::Result<TokenStream2> {
@@ -23,70 +23,86 @@ pub fn derive_ben_schema(input: DeriveInput) -> syn::Result<TokenStream2> {
     // Validate basic bschema invariants
     validate::validate(&spec)?;
 
+    let columns = render::columns(&spec)?;
+
+    // Nested and enum columns are typed by other derives, so the texts that
+    // mention them are assembled in const context instead of here.
+    let deferred = Deferred::new(&spec);
+
     // Build ClickHouse columns for manifest
     let mut column_defs = Vec::new();
+    let mut rowbinary_columns = Vec::new();
+    let codec_opt = match render::table_codec(&spec)? {
+        Some(s) => quote! { Some(#s.to_string()) },
+        None => quote! { None },
+    };
 
-    if let darling::ast::Data::Struct(ref fields) = spec.data {
-        for f in fields.iter() {
-            let f_ident = f.ident.as_ref().unwrap();
-            let name = f_ident.to_string();
-            let ty = &f.ty;
-
-            let ch_ty = if let Some(ref et) = f.enum_type {
-                match et.as_str() {
-                    "string" => "String".to_string(),
-                    "u64" => "UInt64".to_string(),
-                    "i64" => "Int64".to_string(),
-                    "f64" => "Float64".to_string(),
-                    other => {
-                        return Err(syn::Error::new(
-                            f.ty.span(),
-                            format!("Unknown enum backing type '{}'", other),
-                        ));
-                    }
-                }
-            } else {
-                clickhouse_type_for(ty)?
-            };
-            let nullable = option_inner_ty(ty).is_some();
-            let default_opt = match &f.default {
-                Some(s) => quote! { Some(#s.to_string()) },
-                None => quote! { None },
-            };
+    for c in &columns {
+        let name = &c.name;
+        let nullable = c.field.is_some_and(|f| option_inner_ty(&f.ty).is_some());
+        let infra = c.field.is_none();
+        let default_opt = match &c.default {
+            Some(s) => quote! { Some(#s.to_string()) },
+            None => quote! { None },
+        };
+        let ttl_opt = match &c.ttl {
+            Some(s) => quote! { Some(#s.to_string()) },
+            None => quote! { None },
+        };
 
-            column_defs.push(quote! {
-                ::ben_contracts::schema::ColumnDef {
-                    name: #name.to_string(),
-                    // `#ch_ty` becomes a string literal, we turn it into a runtime String:
-                    ch_type: #ch_ty.to_string(),
-                    nullable: #nullable,
-                    default: #default_opt,
+        if let Some((f, validate::Nested::Flatten)) = c.field.and_then(|f| Some((f, f.nested()?))) {
+            let child = &f.ty;
+            let prefix = format!("{name}_");
+            rowbinary_columns.push(quote! {
+                ::ben_wire::schema_text::ColumnGroup::Prefixed {
+                    prefix: #prefix,
+                    columns: <#child as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS,
                 }
             });
+            column_defs.push(quote! {
+                columns.extend(
+                    <#child as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS
+                        .iter()
+                        .map(|(name, ch_type)| ::ben_contracts::schema::ColumnDef {
+                            name: format!("{}{}", #prefix, name),
+                            ch_type: ch_type.to_string(),
+                            nullable: ::ben_wire::schema_text::is_nullable(ch_type),
+                            default: None,
+                            codec: None,
+                            ttl: None,
+                            infra: false,
+                        }),
+                );
+            });
+            continue;
         }
-    }
-
-    let json = render::manifest_json(&spec)?;
-    let fingerprint = render::fingerprint(&json);
-    let ddl = render::ddl(&spec)?;
-
-    let table = &spec.table;
-    let version = spec.version;
-    let table_lit = LitStr::new(table, Span::call_site());
-    let json_lit = LitStr::new(&json, Span::call_site());
-    let fingerprint_lit = LitStr::new(&fingerprint, Span::call_site());
-    let ddl_lit = LitStr::new(&ddl, Span::call_site());
-
-    let evt_hash_bytes = render::evt_hash(&json);
-    let evt_hash_tokens = {
-        let bytes = evt_hash_bytes;
-        quote! { [ #(#bytes),* ] }
-    };
 
-    let field_count_u16: u16 = match &spec.data {
-        darling::ast::Data::Struct(fields) => u16::try_from(fields.len()).unwrap_or(u16::MAX),
-        _ => 0,
-    };
+        let ch_ty = if deferred.has_type(name) {
+            deferred.type_const(name)
+        } else {
+            let ch_ty = &c.ch_type;
+            quote! { #ch_ty }
+        };
+        if !infra {
+            rowbinary_columns.push(if deferred.any {
+                quote! { ::ben_wire::schema_text::ColumnGroup::One(#name, #ch_ty) }
+            } else {
+                quote! { (#name, #ch_ty) }
+            });
+        }
+        column_defs.push(quote! {
+            columns.push(::ben_contracts::schema::ColumnDef {
+                name: #name.to_string(),
+                // `#ch_ty` becomes a string literal, we turn it into a runtime String:
+                ch_type: #ch_ty.to_string(),
+                nullable: #nullable,
+                default: #default_opt,
+                codec: #codec_opt,
+                ttl: #ttl_opt,
+                infra: #infra,
+            });
+        });
+    }
 
     let mut encode_stmts: Vec<TokenStream2> = Vec::new();
     let mut decode_stmts: Vec<TokenStream2> = Vec::new();
@@ -120,66 +136,404 @@ pub fn derive_ben_schema(input: DeriveInput) -> syn::Result<TokenStream2> {
         }
     }
 
-    Ok(quote! {
+    let columns_const = if deferred.any {
+        quote! {{
+            use ::ben_wire::schema_text::{ColumnGroup, as_str, columns, columns_len, names, names_len};
+            const GROUPS: &[ColumnGroup] = &[ #(#rowbinary_columns),* ];
+            const NAMES: [u8; names_len(GROUPS)] = names(GROUPS);
+            const COLUMNS: [(&str, &str); columns_len(GROUPS)] = columns(GROUPS, as_str(&NAMES));
+            &COLUMNS
+        }}
+    } else {
+        quote! { &[ #(#rowbinary_columns),* ] }
+    };
+    let tuple_type = text_const(&[
+        quote! { Str("Tuple(") },
+        quote! {
+            Columns {
+                prefix: "",
+                columns: <#ident as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS,
+                layout: ::ben_wire::schema_text::Layout::Tuple,
+            }
+        },
+        quote! { Str(")") },
+    ]);
+
+    let mut out = quote! {
+        impl ::ben_wire::rowbinary::RowBinaryEncode for #ident {
+            fn encode_rowbinary(&self, out: &mut ::std::vec::Vec<u8>) -> ::ben_wire::rowbinary::RowBinaryResult {
+                #( #encode_stmts )*
+                Ok(())
+            }
+        }
+
+        impl ::ben_wire::rowbinary::RowBinaryColumns for #ident {
+            const COLUMNS: &'static [(&'static str, &'static str)] = #columns_const;
+        }
+
+        impl ::ben_wire::rowbinary::RowBinaryNested for #ident {
+            const TUPLE_TYPE: &'static str = #tuple_type;
+
+            fn decode_nested(cur: &mut ::ben_wire::rowbinary::RowBinCursor<'_>) -> ::anyhow::Result<Self> {
+                #(#decode_stmts)*
+                Ok(Self {
+                    #(#field_inits),*
+                })
+            }
+        }
+    };
+    out.extend(deferred.type_consts());
+
+    // A nested struct has no table, manifest or envelope of its own.
+    if spec.nested {
+        return Ok(out);
+    }
+
+    let json = render::manifest_json(&spec)?;
+    let ddl = render::ddl(&spec)?;
+
+    let table = &spec.table;
+    let version = spec.version;
+    let table_lit = LitStr::new(table, Span::call_site());
+
+    let (json_lit, ddl_lit, fingerprint_lit, evt_hash_tokens, field_count) = if deferred.any {
+        let codec = match &spec.compression {
+            Some(c) => format!(" {}", validate::codec(c).unwrap_or_default()),
+            None => String::new(),
+        };
+        (
+            text_const(&deferred.pieces(&json, quote! { Json })),
+            text_const(&deferred.pieces(&ddl, quote! { Ddl { suffix: #codec } })),
+            quote! {{
+                const FINGERPRINT: [u8; 71] =
+                    ::ben_wire::schema_text::fingerprint(&#ident::__BEN_SCHEMA_EVT_HASH);
+                ::ben_wire::schema_text::as_str(&FINGERPRINT)
+            }},
+            quote! { #ident::__BEN_SCHEMA_EVT_HASH },
+            quote! { <#ident as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS.len() as u16 },
+        )
+    } else {
+        let evt_hash_bytes = render::evt_hash(&json);
+        let field_count_u16: u16 =
+            u16::try_from(columns.iter().filter(|c| c.field.is_some()).count()).unwrap_or(u16::MAX);
+        (
+            LitStr::new(&json, Span::call_site()).into_token_stream(),
+            LitStr::new(&ddl, Span::call_site()).into_token_stream(),
+            LitStr::new(&render::fingerprint(&json), Span::call_site()).into_token_stream(),
+            quote! { [ #(#evt_hash_bytes),* ] },
+            quote! { #field_count_u16 },
+        )
+    };
+    let evt_hash_const = if deferred.any {
+        quote! { ::ben_wire::schema_text::sha256(#ident::__BEN_SCHEMA_JSON.as_bytes()) }
+    } else {
+        evt_hash_tokens.clone()
+    };
+
+    // `store = false` events travel on the wire but get no table.
+    let table_def = render::stored(&spec).then(|| {
+        quote! {
+            ::ben_contracts::schema::TableDef {
+                name: #table_lit.to_string(),
+                fingerprint: #ident::__BEN_SCHEMA_FINGERPRINT.to_string(),
+                ddl: #ident::__BEN_SCHEMA_DDL.to_string(),
+                columns: {
+                    let mut columns = Vec::new();
+                    #(#column_defs)*
+                    columns
+                },
+            }
+        }
+    });
+
+    let registry_static =
+        quote::format_ident!("__BEN_SCHEMA_REG_{}", ident.to_string().to_uppercase());
+
+    let borrowed_view = if spec.borrowed {
+        borrowed_view(&spec, &evt_hash_tokens, &field_count)?
+    } else {
+        TokenStream2::new()
+    };
+
+    out.extend(quote! {
         impl #ident {
-            pub const __BEN_SCHEMA_TABLE: &'static str        = #fingerprint_lit;
+            pub const __BEN_SCHEMA_TABLE: &'static str        = #table_lit;
             pub const __BEN_SCHEMA_VERSION: u32               = #version;
             pub const __BEN_SCHEMA_FINGERPRINT: &'static str  = #fingerprint_lit;
             pub const __BEN_SCHEMA_DDL: &'static str          = #ddl_lit;
             pub const __BEN_SCHEMA_JSON: &'static str         = #json_lit;
-            pub const __BEN_SCHEMA_EVT_HASH: [u8; 32]         = #evt_hash_tokens;
-            pub const __BEN_SCHEMA_FIELD_COUNT: u16           = #field_count_u16;
+            pub const __BEN_SCHEMA_EVT_HASH: [u8; 32]         = #evt_hash_const;
+            pub const __BEN_SCHEMA_FIELD_COUNT: u16           = #field_count;
+
+            #[doc(hidden)]
+            pub const __BEN_SCHEMA_INFO: ::ben_contracts::registry::SchemaInfo =
+                ::ben_contracts::registry::SchemaInfo {
+                    table: #table_lit,
+                    version: #version,
+                    evt_hash: #evt_hash_tokens,
+                    columns: <Self as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS,
+                };
         }
 
+        #[doc(hidden)]
+        #[::ben_contracts::linkme::distributed_slice(::ben_contracts::registry::BEN_SCHEMA_REGISTRY)]
+        #[linkme(crate = ::ben_contracts::linkme)]
+        static #registry_static: fn() -> &'static ::ben_contracts::registry::SchemaInfo =
+            || &#ident::__BEN_SCHEMA_INFO;
+
         impl ::ben_contracts::schema::BenSchema for #ident {
-            const __BEN_SCHEMA_TABLE: &'static str       = #fingerprint_lit;
+            const __BEN_SCHEMA_TABLE: &'static str       = #table_lit;
             const __BEN_SCHEMA_VERSION: u32              = #version;
-            const __BEN_SCHEMA_FINGERPRINT: &'static str = #fingerprint_lit;
-            const __BEN_SCHEMA_DDL: &'static str         = #ddl_lit;
-            const __BEN_SCHEMA_JSON: &'static str        = #json_lit;
+            const __BEN_SCHEMA_FINGERPRINT: &'static str = #ident::__BEN_SCHEMA_FINGERPRINT;
+            const __BEN_SCHEMA_DDL: &'static str         = #ident::__BEN_SCHEMA_DDL;
+            const __BEN_SCHEMA_JSON: &'static str        = #ident::__BEN_SCHEMA_JSON;
         }
 
         pub fn schema_manifest() -> ::ben_contracts::schema::SchemaManifest {
             ::ben_contracts::schema::SchemaManifest {
-                tables: vec![
-                    ::ben_contracts::schema::TableDef {
-                        name: #fingerprint_lit.to_string(),
-                        ddl: #ddl_lit.to_string(),
-                        columns: vec![
-                            #(#column_defs),*
-                        ],
-                    },
-                ],
-            }
-        }
-
-        impl ::ben_wire::rowbinary::RowBinaryEncode for #ident {
-            fn encode_rowbinary(&self, out: &mut ::std::vec::Vec<u8>) -> ::ben_wire::rowbinary::RowBinaryResult {
-                #( #encode_stmts )*
-                Ok(())
+                tables: vec![ #table_def ],
             }
         }
 
         impl ::ben_wire::rowbinary::RowBinaryDecode for #ident {
             const EVT_HASH: [u8; 32] = #evt_hash_tokens;
-            const FIELD_COUNT: u16 = #field_count_u16;
+            const FIELD_COUNT: u16 = #field_count;
 
             fn from_rowbinary(cur: &mut ::ben_wire::rowbinary::RowBinCursor<'_>) -> ::anyhow::Result<Self> {
-                #(#decode_stmts)*
-                Ok(Self {
-                    #(#field_inits),*
-                })
+                <Self as ::ben_wire::rowbinary::RowBinaryNested>::decode_nested(cur)
             }
         }
 
         impl ::ben_wire::rowbinary::EncodeQuic for #ident {
             const EVT_HASH: [u8; 32] = #evt_hash_tokens;
-            const FIELD_COUNT: u16   = #field_count_u16;
+            const FIELD_COUNT: u16   = #field_count;
         }
 
         impl ::ben_wire::rowbinary::DecodeQuic for #ident {
             const EVT_HASH: [u8; 32] = #evt_hash_tokens;
-            const FIELD_COUNT: u16   = #field_count_u16;
+            const FIELD_COUNT: u16   = #field_count;
+        }
+
+        #borrowed_view
+    });
+    Ok(out)
+}
+
+/// The columns of a struct whose types only exist in const context, and the
+/// text built from them: nested structs, whose fields this derive cannot see,
+/// and `BenEnum`s, whose variants it cannot see.
+struct Deferred<'a> {
+    ident: &'a syn::Ident,
+    any: bool,
+    fields: Vec<(String, &'a syn::Type, Kind<'a>)>,
+}
+
+enum Kind<'a> {
+    Nested(validate::Nested),
+    /// The column type with [`helpers::ENUM_SLOT`] where the enum goes.
+    Enum(String, &'a syn::Type),
+}
+
+impl<'a> Deferred<'a> {
+    fn new(spec: &'a validate::SchemaSpec) -> Self {
+        let fields: Vec<_> = match &spec.data {
+            darling::ast::Data::Struct(fields) => fields
+                .iter()
+                .filter_map(|f| {
+                    let name = f.ident.as_ref()?.to_string();
+                    if let Some(mode) = f.nested() {
+                        return Some((name, &f.ty, Kind::Nested(mode)));
+                    }
+                    if f.enum_type.is_some() {
+                        return None;
+                    }
+                    let ch_type = render::column_type(f).ok()?;
+                    let enum_ty = *helpers::enum_tys(&f.ty).first()?;
+                    Some((name, &f.ty, Kind::Enum(ch_type, enum_ty)))
+                })
+                .collect(),
+            _ => vec![],
+        };
+        Self {
+            ident: &spec.ident,
+            any: !fields.is_empty(),
+            fields,
+        }
+    }
+
+    fn type_ident(field: &str) -> syn::Ident {
+        quote::format_ident!("__BEN_TYPE_{}", field.to_uppercase())
+    }
+
+    /// Whether `field` is typed by a const from [`Deferred::type_consts`].
+    fn has_type(&self, field: &str) -> bool {
+        self.fields.iter().any(|(name, _, kind)| {
+            name == field && !matches!(kind, Kind::Nested(validate::Nested::Flatten))
+        })
+    }
+
+    /// The ClickHouse type of a tuple or enum field, as a const path.
+    fn type_const(&self, field: &str) -> TokenStream2 {
+        let (ident, name) = (self.ident, Self::type_ident(field));
+        quote! { #ident::#name }
+    }
+
+    /// `Tuple(...)`, or `Array(Tuple(...))` for a `Vec` of children; the
+    /// enum's `Enum8(...)` in its place in an enum column's type.
+    fn type_consts(&self) -> TokenStream2 {
+        let ident = self.ident;
+        let consts = self.fields.iter().filter_map(|(field, ty, kind)| {
+            let value = match kind {
+                Kind::Nested(validate::Nested::Tuple) => {
+                    let child = validate::nested_struct_ty(ty).unwrap_or(ty);
+                    let tuple =
+                        quote! { <#child as ::ben_wire::rowbinary::RowBinaryNested>::TUPLE_TYPE };
+                    match vec_inner_ty(ty) {
+                        Some(_) => text_const(&[
+                            quote! { Str("Array(") },
+                            quote! { Str(#tuple) },
+                            quote! { Str(")") },
+                        ]),
+                        None => tuple,
+                    }
+                }
+                Kind::Nested(validate::Nested::Flatten) => return None,
+                Kind::Enum(ch_type, enum_ty) => {
+                    let enum_type = quote! { <#enum_ty as ::ben_contracts::BenEnumDesc>::CH_TYPE };
+                    match ch_type.split_once(helpers::ENUM_SLOT) {
+                        Some(("", "")) | None => enum_type,
+                        Some((before, after)) => text_const(&[
+                            quote! { Str(#before) },
+                            quote! { Str(#enum_type) },
+                            quote! { Str(#after) },
+                        ]),
+                    }
+                }
+            };
+            let name = Self::type_ident(field);
+            Some(quote! { const #name: &'static str = #value; })
+        });
+        quote! {
+            #[doc(hidden)]
+            impl #ident {
+                #(#consts)*
+            }
+        }
+    }
+
+    /// `Piece`s for `text`, rendered with markers where deferred text goes.
+    fn pieces(&self, text: &str, layout: TokenStream2) -> Vec<TokenStream2> {
+        render::split_markers(text)
+            .into_iter()
+            .filter_map(|seg| match seg {
+                render::Segment::Text(t) if t.is_empty() => None,
+                render::Segment::Text(t) => Some(quote! { Str(#t) }),
+                render::Segment::Type { field, quoted } => {
+                    let ty = self.type_const(&field);
+                    Some(if quoted {
+                        quote! { JsonStr(#ty) }
+                    } else {
+                        quote! { Str(#ty) }
+                    })
+                }
+                render::Segment::EnumMap { field } => {
+                    let ty = self.type_const(&field);
+                    Some(quote! { EnumMap(#ty) })
+                }
+                render::Segment::Columns { field } => {
+                    let (_, child, _) = self.fields.iter().find(|(f, ..)| *f == field)?;
+                    let prefix = format!("{field}_");
+                    Some(quote! {
+                        Columns {
+                            prefix: #prefix,
+                            columns: <#child as ::ben_wire::rowbinary::RowBinaryColumns>::COLUMNS,
+                            layout: ::ben_wire::schema_text::Layout::#layout,
+                        }
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+/// A `&'static str` const expression over `schema_text::Piece`s.
+fn text_const(pieces: &[TokenStream2]) -> TokenStream2 {
+    quote! {{
+        use ::ben_wire::schema_text::{Piece::*, as_str, text, text_len};
+        const PIECES: &[::ben_wire::schema_text::Piece] = &[ #(#pieces),* ];
+        const TEXT: [u8; text_len(PIECES)] = text(PIECES);
+        as_str(&TEXT)
+    }}
+}
+
+/// `#[bschema(borrowed)]`: a `<Ident>Ref<'a>` twin whose `String` and
+/// `Vec<u8>` fields are `&'a str` / `&'a [u8]` into the decoded buffer.
+fn borrowed_view(
+    spec: &validate::SchemaSpec,
+    evt_hash_tokens: &TokenStream2,
+    field_count: &TokenStream2,
+) -> syn::Result<TokenStream2> {
+    let ident = &spec.ident;
+    let vis = &spec.vis;
+    let ref_ident = quote::format_ident!("{}Ref", ident);
+    let doc = format!("Borrowed view of [`{ident}`] for allocation-free decoding.");
+
+    let mut fields = Vec::new();
+    let mut decode_stmts = Vec::new();
+    let mut field_inits = Vec::new();
+    let mut borrows = false;
+
+    if let darling::ast::Data::Struct(ref data) = spec.data {
+        for f in data.iter() {
+            let field_ident = f
+                .ident
+                .as_ref()
+                .expect("BenSchema only supports named struct fields");
+            let ty = match borrowed_ty(&f.ty) {
+                Some(ty) if f.enum_type.is_none() => {
+                    borrows = true;
+                    ty
+                }
+                _ => f.ty.clone(),
+            };
+            fields.push(quote! { pub #field_ident: #ty });
+
+            let decode_expr = decode_ref_expr_for_type(&f.ty, f).map_err(|e| {
+                syn::Error::new(
+                    field_ident.span(),
+                    format!("decode error on field `{}`: {}", field_ident, e),
+                )
+            })?;
+            decode_stmts.push(quote! {
+                let #field_ident = #decode_expr;
+            });
+            field_inits.push(quote! { #field_ident });
+        }
+    }
+
+    if !borrows {
+        return Err(syn::Error::new(
+            ident.span(),
+            "BenSchema: #[bschema(borrowed)] needs at least one String or Vec<u8> field",
+        ));
+    }
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #ref_ident<'a> {
+            #(#fields),*
+        }
+
+        impl<'a> ::ben_wire::rowbinary::RowBinaryDecodeRef<'a> for #ref_ident<'a> {
+            const EVT_HASH: [u8; 32] = #evt_hash_tokens;
+            const FIELD_COUNT: u16 = #field_count;
+
+            fn from_rowbinary_ref(cur: &mut ::ben_wire::rowbinary::RowBinCursor<'a>) -> ::anyhow::Result<Self> {
+                #(#decode_stmts)*
+                Ok(Self {
+                    #(#field_inits),*
+                })
+            }
         }
     })
 }
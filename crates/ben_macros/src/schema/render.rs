@@ -1,70 +1,216 @@
-use super::clickhouse_type_for;
-use super::validate::{SchemaField, SchemaSpec};
+use super::validate::{Nested, SchemaField, SchemaSpec, codec};
+use super::{clickhouse_type_for, helpers::ENUM_SLOT, option_inner_ty};
 // use crate::schema::parse::parse_enum_pairs;
+use ben_contracts::schema::column_spec;
+use ben_wire::schema::{INFRA_COLUMNS, infra_column};
+use proc_macro2::Span;
 use serde::Serialize;
 use serde_json::json;
 use sha2::{Digest, Sha256};
-use syn::Type;
+use syn::{Type, spanned::Spanned};
 
 #[derive(Serialize)]
 struct JsonField {
     name: String,
-    rust_type: String,
+    rust_type: Option<String>,
     ch_type: String,
     key: bool,
     unique: bool,
     nullable: bool,
     tags: bool,
     ben_meta: bool,
-    enum_map: Option<std::collections::BTreeMap<String, i16>>,
+    /// Filled in by the pipeline, not carried in the event row.
+    infra: bool,
+    default: Option<String>,
+    ttl: Option<String>,
+    /// Label to value for a `BenEnum` column, derived from its type.
+    enum_map: Option<String>,
 }
 
-pub fn manifest_json(spec: &SchemaSpec) -> syn::Result<String> {
-    let mut infra_cols: Vec<JsonField> = vec![];
-    let mut user_cols: Vec<JsonField> = vec![];
-
-    infra_cols.push(JsonField {
-        name: "tenant_id".to_string(),
-        rust_type: "Uuid".to_string(),
-        ch_type: "UUID".to_string(),
-        key: true,
-        unique: false,
-        tags: false,
-        ben_meta: true,
-        nullable: false,
-        enum_map: None,
-    });
+/// One table column. The same list drives the DDL, the manifest, the
+/// `SchemaManifest` columns and the RowBinary header.
+pub struct Column<'a> {
+    pub name: String,
+    pub ch_type: String,
+    pub default: Option<String>,
+    pub ttl: Option<String>,
+    /// `None` for an infra column the struct leaves to the pipeline.
+    pub field: Option<&'a SchemaField>,
+}
+
+/// Stands in for text only known in const context: the type of a nested or
+/// `BenEnum` column, the `enum_map` of the latter, or the columns of a
+/// flattened child. See [`split_markers`].
+const MARK: char = '\u{E000}';
+
+fn type_marker(field: &str) -> String {
+    format!("{MARK}type:{field}{MARK}")
+}
+
+fn enum_map_marker(field: &str) -> String {
+    format!("{MARK}enum_map:{field}{MARK}")
+}
+
+fn columns_marker(field: &str) -> String {
+    format!("{MARK}columns:{field}{MARK}")
+}
+
+/// A run of rendered text, split at the markers.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    /// The type of nested or enum field `field`; `quoted` when it sat in a
+    /// JSON string.
+    Type {
+        field: String,
+        quoted: bool,
+    },
+    /// The `enum_map` JSON object of enum field `field`.
+    EnumMap {
+        field: String,
+    },
+    /// The columns of flattened field `field`.
+    Columns {
+        field: String,
+    },
+}
+
+pub fn split_markers(text: &str) -> Vec<Segment> {
+    let mut out = Vec::new();
+    let mut parts = text.split(MARK);
+    let mut pending = parts.next().unwrap_or_default().to_string();
+    while let (Some(marker), Some(after)) = (parts.next(), parts.next()) {
+        let (kind, field) = marker.split_once(':').unwrap_or_default();
+        // JSON puts both markers in a string; the piece brings its own quotes.
+        let quoted = pending.ends_with('"') && after.starts_with('"');
+        if quoted {
+            pending.pop();
+        }
+        out.push(Segment::Text(std::mem::take(&mut pending)));
+        out.push(match kind {
+            "type" => Segment::Type {
+                field: field.to_string(),
+                quoted,
+            },
+            "enum_map" => Segment::EnumMap {
+                field: field.to_string(),
+            },
+            _ => Segment::Columns {
+                field: field.to_string(),
+            },
+        });
+        pending = after
+            .strip_prefix('"')
+            .filter(|_| quoted)
+            .unwrap_or(after)
+            .to_string();
+    }
+    out.push(Segment::Text(pending));
+    out
+}
+
+/// The struct's fields in declaration order, then every infra column the
+/// struct does not declare itself. Nested structs have no infra columns.
+pub fn columns(spec: &SchemaSpec) -> syn::Result<Vec<Column<'_>>> {
+    let mut out = Vec::new();
 
     for f in fields(spec) {
-        let mut enum_map = None;
-        let ch_type = if let Some(ref et) = f.enum_type {
-            map_enum_type_to_clickhouse(et)
-        } else {
-            clickhouse_type_for(&f.ty)?.to_string()
-        };
-        user_cols.push(JsonField {
-            name: f.ident.as_ref().map(|i| i.to_string()).unwrap_or_default(),
-            rust_type: type_to_string_lossy(&f.ty),
-            key: f.key,
-            unique: f.unique,
-            tags: f.tags,
-            ben_meta: f.ben_meta,
-            nullable: f.nullable,
-            ch_type: clickhouse_type_for(&f.ty)?.to_string(),
-            enum_map,
+        let name = f.ident.as_ref().map(|i| i.to_string()).unwrap_or_default();
+        if let Some(nested) = f.nested() {
+            out.push(Column {
+                ch_type: match nested {
+                    Nested::Tuple => type_marker(&name),
+                    Nested::Flatten => columns_marker(&name),
+                },
+                name,
+                default: f.default.clone(),
+                ttl: f.ttl.clone(),
+                field: Some(f),
+            });
+            continue;
+        }
+
+        let mut ch_type = column_type(f)?;
+        let mut default = f.default.clone();
+
+        if let Some(infra) = infra_column(&name).filter(|_| !spec.nested) {
+            // i64 milliseconds share DateTime64(3)'s RowBinary layout.
+            if ch_type == "Int64" && infra.ch_type == "DateTime64(3)" {
+                ch_type = infra.ch_type.to_string();
+            }
+            if ch_type != infra.ch_type {
+                return Err(syn::Error::new(
+                    f.ty.span(),
+                    format!(
+                        "`{name}` is an infra column of type {}, not {ch_type}",
+                        infra.ch_type
+                    ),
+                ));
+            }
+            default.get_or_insert_with(|| infra.default_expr(spec.version));
+        }
+        if ch_type.contains(ENUM_SLOT) {
+            ch_type = type_marker(&name);
+        }
+
+        out.push(Column {
+            name,
+            ch_type,
+            default,
+            ttl: f.ttl.clone(),
+            field: Some(f),
         });
     }
-    user_cols.sort_by(|a, b| a.name.cmp(&b.name)); // stable fingerprint for user columns
 
-    let mut cols = Vec::new();
-    cols.extend(infra_cols);
-    cols.extend(user_cols);
+    for infra in INFRA_COLUMNS.iter().filter(|_| !spec.nested) {
+        if !out.iter().any(|c| c.name == infra.name) {
+            out.push(Column {
+                name: infra.name.to_string(),
+                ch_type: infra.ch_type.to_string(),
+                default: Some(infra.default_expr(spec.version)),
+                ttl: None,
+                field: None,
+            });
+        }
+    }
+
+    Ok(out)
+}
+
+pub fn manifest_json(spec: &SchemaSpec) -> syn::Result<String> {
+    // Declaration order is the wire order, so reordering fields changes the evt_hash.
+    let cols: Vec<serde_json::Value> = columns(spec)?
+        .into_iter()
+        .map(|c| match c.field.and_then(|f| f.nested()) {
+            Some(Nested::Flatten) => json!(c.ch_type),
+            _ => json!(JsonField {
+                rust_type: c.field.map(|f| type_to_string_lossy(&f.ty)),
+                key: c.field.is_some_and(|f| f.key),
+                unique: c.field.is_some_and(|f| f.unique),
+                tags: c.field.is_some_and(|f| f.tags),
+                ben_meta: c.field.is_some_and(|f| f.ben_meta),
+                infra: c.field.is_none(),
+                nullable: c.field.is_some_and(|f| option_inner_ty(&f.ty).is_some()),
+                enum_map: (c.field.is_some_and(|f| f.nested().is_none())
+                    && c.ch_type.contains(MARK))
+                .then(|| enum_map_marker(&c.name)),
+                name: c.name,
+                ch_type: c.ch_type,
+                default: c.default,
+                ttl: c.ttl,
+            }),
+        })
+        .collect();
 
     let obj = json!({
         "table": spec.table,
         "version": spec.version,
+        "engine": engine(spec),
         "order_by": spec.order_by,
+        "partition_by": spec.partition_by,
+        "ttl": spec.ttl,
         "compression": spec.compression,
+        "store": stored(spec),
         "columns": cols,
         "description": spec.description,
     });
@@ -72,21 +218,48 @@ pub fn manifest_json(spec: &SchemaSpec) -> syn::Result<String> {
     Ok(serde_json::to_string_pretty(&obj).unwrap())
 }
 
-fn map_enum_type_to_clickhouse(et: &str) -> String {
+/// ClickHouse type of a user column, after `enum_type` and `cardinality`.
+pub fn column_type(f: &SchemaField) -> syn::Result<String> {
+    let base = match f.enum_type.as_deref() {
+        Some(et) => enum_backing_type(et)
+            .ok_or_else(|| {
+                syn::Error::new(f.ty.span(), format!("Unknown enum backing type '{et}'"))
+            })?
+            .to_string(),
+        None => clickhouse_type_for(&f.ty, f.scale())?,
+    };
+    Ok(if f.low_cardinality() {
+        format!("LowCardinality({base})")
+    } else {
+        base
+    })
+}
+
+/// The `enum_type` values the encoder supports.
+fn enum_backing_type(et: &str) -> Option<&'static str> {
     match et {
-        "string" => "String".into(),
-        "u64" => "UInt64".into(),
-        "i64" => "Int64".into(),
-        "u32" => "UInt32".into(),
-        "i32" => "Int32".into(),
-        "f64" => "Float64".into(),
-        "f32" => "Float32".into(),
-        "bool" => "Bool".into(),
-        "datetime64" => "DateTime64".into(),
-        unknown => panic!("Unknown enum underlying type: {}", unknown),
+        "string" => Some("String"),
+        "u64" => Some("UInt64"),
+        "i64" => Some("Int64"),
+        "f64" => Some("Float64"),
+        _ => None,
+    }
+}
+
+/// `unique` columns only hold if the engine collapses rows with equal keys.
+pub fn engine(spec: &SchemaSpec) -> String {
+    match &spec.engine {
+        Some(e) => e.clone(),
+        None if fields(spec).iter().any(|f| f.unique) => "ReplacingMergeTree".into(),
+        None => "MergeTree".into(),
     }
 }
 
+/// `store = false` marks a wire-only event with no table.
+pub fn stored(spec: &SchemaSpec) -> bool {
+    spec.store.unwrap_or(true)
+}
+
 pub fn fingerprint(json: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(json.as_bytes());
@@ -105,33 +278,41 @@ pub fn evt_hash(json: &str) -> [u8; 32] {
     out
 }
 
-pub fn ddl(spec: &SchemaSpec) -> syn::Result<String> {
-    let mut cols = Vec::new();
+/// The `CODEC(..)` clause every column gets from `compression`.
+pub fn table_codec(spec: &SchemaSpec) -> syn::Result<Option<String>> {
+    spec.compression
+        .as_deref()
+        .map(|c| codec(c).map_err(|e| syn::Error::new(Span::call_site(), e)))
+        .transpose()
+}
 
-    for f in fields(spec) {
-        let name = f
-            .ident
-            .as_ref()
-            .expect("Field in named struct must have an ident")
-            .to_string();
+pub fn ddl(spec: &SchemaSpec) -> syn::Result<String> {
+    if !stored(spec) {
+        return Ok(String::new());
+    }
 
-        let ch_ty = if let Some(ref et) = f.enum_type {
-            map_enum_type_to_clickhouse(et)
-        } else {
-            clickhouse_type_for(&f.ty)?
-        };
+    let codec = table_codec(spec)?;
 
-        cols.push(format!("  `{}` {}", name, ch_ty));
+    let mut cols = Vec::new();
+    for c in columns(spec)? {
+        if c.field.and_then(|f| f.nested()) == Some(Nested::Flatten) {
+            cols.push(c.ch_type);
+            continue;
+        }
+        cols.push(format!(
+            "  {}",
+            column_spec(
+                &c.name,
+                &c.ch_type,
+                c.default.as_deref(),
+                codec.as_deref(),
+                c.ttl.as_deref(),
+            )
+        ));
     }
 
-    cols.push("  `system_mask` UInt64".to_string());
-    cols.push("  `struct_mask` UInt64".to_string());
-    cols.push("  `field_mask` UInt64".to_string());
-
     let cols_joined = cols.join(",\n");
 
-    let engine = spec.engine.as_deref().unwrap_or("MergeTree");
-
     let order = spec
         .order_by
         .clone()
@@ -142,7 +323,7 @@ pub fn ddl(spec: &SchemaSpec) -> syn::Result<String> {
         "(".to_string(),
         cols_joined,
         ")".to_string(),
-        format!("ENGINE = {}", engine),
+        format!("ENGINE = {}", engine(spec)),
         format!("ORDER BY ({})", order),
     ];
 
@@ -150,6 +331,10 @@ pub fn ddl(spec: &SchemaSpec) -> syn::Result<String> {
         ddl_parts.push(format!("PARTITION BY {}", p));
     }
 
+    if let Some(t) = &spec.ttl {
+        ddl_parts.push(format!("TTL {}", t));
+    }
+
     ddl_parts.push(";".to_string());
 
     Ok(ddl_parts.join("\n"))
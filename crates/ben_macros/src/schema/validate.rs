@@ -6,6 +6,13 @@ use quote::ToTokens;
 use darling::{FromDeriveInput, FromField};
 use syn::{Ident, Type};
 
+use super::{
+    clickhouse_type_for,
+    helpers::{ENUM_SLOT, enum_tys, is_named_type, vec_inner_ty},
+    option_inner_ty,
+};
+use ben_wire::schema::infra_column;
+
 #[derive(Debug, FromDeriveInput)]
 #[darling(attributes(bschema), supports(struct_named))]
 pub struct SchemaSpec {
@@ -25,8 +32,18 @@ pub struct SchemaSpec {
     pub description: Option<String>,
     #[darling(default)]
     pub store: Option<bool>,
+    /// Table-level `TTL` expression.
+    #[darling(default)]
+    pub ttl: Option<String>,
+    /// Also emit a `<Ident>Ref<'a>` view that decodes without allocating.
+    #[darling(default)]
+    pub borrowed: bool,
+    /// Only stored inside other structs' rows; has no table of its own.
+    #[darling(default)]
+    pub nested: bool,
 
     pub ident: Ident,
+    pub vis: syn::Visibility,
     pub data: darling::ast::Data<(), SchemaField>,
 }
 
@@ -56,9 +73,46 @@ pub struct SchemaField {
     pub default: Option<String>,
     #[darling(default)]
     pub ttl: Option<String>,
+    /// Precision of a `DateTime64` column, 0..=9.
+    #[darling(default)]
+    pub scale: Option<u32>,
+    /// `"tuple"` or `"flatten"` for a nested `#[derive(BenSchema)]` struct.
+    #[darling(default)]
+    pub nested: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nested {
+    /// One `Tuple(...)` column.
+    Tuple,
+    /// One `field_child` column per child column.
+    Flatten,
+}
+
+impl SchemaField {
+    pub fn low_cardinality(&self) -> bool {
+        self.cardinality.as_deref() == Some("low")
+    }
+
+    /// Milliseconds unless `scale` says otherwise, like `ingest_ts`.
+    pub fn scale(&self) -> u32 {
+        self.scale.unwrap_or(3)
+    }
+
+    pub fn nested(&self) -> Option<Nested> {
+        match self.nested.as_deref()? {
+            "tuple" => Some(Nested::Tuple),
+            "flatten" => Some(Nested::Flatten),
+            _ => None,
+        }
+    }
 }
 
 pub fn validate(spec: &SchemaSpec) -> syn::Result<()> {
+    if spec.nested {
+        return validate_nested(spec);
+    }
+
     // table name: snake_case, sane length
     if spec.table.is_empty() {
         return err(spec, "bschema(table = \"...\") is required");
@@ -90,11 +144,30 @@ pub fn validate(spec: &SchemaSpec) -> syn::Result<()> {
         }
         // enum mapping must be well-formed if provided
         if let Some(ref r#type) = f.enum_type {}
+
+        validate_column(spec, f)?;
     }
     if key_count == 0 {
         return err(spec, "at least one #[bschema(key)] is required");
     }
 
+    if spec.ttl.as_deref().is_some_and(|t| !is_expr(t)) {
+        return err(spec, "ttl must be a single non-empty expression");
+    }
+    if let Some(Err(e)) = spec.compression.as_deref().map(codec) {
+        return err(spec, &e);
+    }
+    let replacing = spec
+        .engine
+        .as_deref()
+        .is_none_or(|e| e.starts_with("Replacing"));
+    if !replacing && fields(spec).iter().any(|f| f.unique) {
+        return err(
+            spec,
+            "#[bschema(unique)] needs a Replacing*MergeTree engine to deduplicate",
+        );
+    }
+
     // order_by references must exist (if provided)
     if let Some(ref ob) = spec.order_by {
         for name in ob.split(',').map(|s| s.trim()) {
@@ -106,6 +179,214 @@ pub fn validate(spec: &SchemaSpec) -> syn::Result<()> {
     Ok(())
 }
 
+/// `#[bschema(nested)]`: no table, so none of the table attributes apply.
+fn validate_nested(spec: &SchemaSpec) -> syn::Result<()> {
+    let table_attrs = [
+        ("table", !spec.table.is_empty()),
+        ("order_by", spec.order_by.is_some()),
+        ("partition_by", spec.partition_by.is_some()),
+        ("engine", spec.engine.is_some()),
+        ("compression", spec.compression.is_some()),
+        ("store", spec.store.is_some()),
+        ("ttl", spec.ttl.is_some()),
+        ("borrowed", spec.borrowed),
+    ];
+    if let Some((name, _)) = table_attrs.iter().find(|(_, set)| *set) {
+        return err(
+            spec,
+            &format!("#[bschema(nested)] structs take no `{name}`"),
+        );
+    }
+    if fields(spec).is_empty() {
+        return err(spec, "#[bschema(nested)] structs need at least one field");
+    }
+    for f in fields(spec) {
+        if f.key || f.unique || f.ttl.is_some() {
+            return Err(syn::Error::new(
+                span(f),
+                "key, unique and ttl only apply to table columns, not nested ones",
+            ));
+        }
+        validate_column(spec, f)?;
+    }
+    Ok(())
+}
+
+fn validate_column(spec: &SchemaSpec, f: &SchemaField) -> syn::Result<()> {
+    let fail = |msg: &str| Err(syn::Error::new(span(f), msg));
+
+    if let Some(ref mode) = f.nested {
+        return validate_nested_field(f, mode);
+    }
+
+    if f.nullable && option_inner_ty(&f.ty).is_none() {
+        return fail("#[bschema(nullable)] requires an Option<T> field");
+    }
+
+    // The manifest's enum_map has room for one.
+    if f.enum_type.is_none() && enum_tys(&f.ty).len() > 1 {
+        return fail("a column can hold only one BenEnum");
+    }
+
+    if let Some(scale) = f.scale {
+        if scale > 9 {
+            return fail(&format!("scale must be 0..=9, got {scale}"));
+        }
+        if !clickhouse_type_for(&f.ty, scale)?.contains("DateTime64(") {
+            return fail("scale only applies to SystemTime and other DateTime64 fields");
+        }
+    }
+
+    match f.cardinality.as_deref() {
+        None | Some("high") => {}
+        Some("low") => {
+            let base = clickhouse_type_for(&f.ty, f.scale())?;
+            if base.contains(ENUM_SLOT) && f.enum_type.is_none() {
+                return fail("BenEnum columns are Enum8/Enum16, which cannot be LowCardinality");
+            }
+            let inner = base
+                .strip_prefix("Nullable(")
+                .and_then(|t| t.strip_suffix(')'))
+                .unwrap_or(&base);
+            let supported = inner == "String"
+                || inner == "Date"
+                || inner == "Date32"
+                || inner.starts_with("UInt")
+                || inner.starts_with("Int")
+                || inner.starts_with("Float");
+            if !supported && f.enum_type.is_none() {
+                return fail(&format!(
+                    "cardinality = \"low\" is not supported for {base}"
+                ));
+            }
+        }
+        Some(other) => {
+            return fail(&format!(
+                "cardinality must be \"low\" or \"high\", got \"{other}\""
+            ));
+        }
+    }
+
+    if f.default.as_deref().is_some_and(|d| !is_expr(d)) {
+        return fail("default must be a single non-empty expression");
+    }
+
+    if let Some(ref ttl) = f.ttl {
+        if !is_expr(ttl) {
+            return fail("ttl must be a single non-empty expression");
+        }
+        if in_sorting_key(spec, f) {
+            return fail("ClickHouse does not allow a TTL on a sorting key column");
+        }
+    }
+
+    if f.unique && !in_sorting_key(spec, f) {
+        return fail("#[bschema(unique)] columns must be part of the key or order_by");
+    }
+
+    Ok(())
+}
+
+fn validate_nested_field(f: &SchemaField, mode: &str) -> syn::Result<()> {
+    let fail = |msg: &str| Err(syn::Error::new(span(f), msg));
+
+    let Some(nested) = f.nested() else {
+        return fail(&format!(
+            "nested must be \"tuple\" or \"flatten\", got \"{mode}\""
+        ));
+    };
+    let column_attrs = [
+        ("key", f.key),
+        ("unique", f.unique),
+        ("tags", f.tags),
+        ("ben_meta", f.ben_meta),
+        ("nullable", f.nullable),
+        ("enum_type", f.enum_type.is_some()),
+        ("cardinality", f.cardinality.is_some()),
+        ("scale", f.scale.is_some()),
+    ];
+    if let Some((name, _)) = column_attrs.iter().find(|(_, set)| *set) {
+        return fail(&format!(
+            "`{name}` cannot be combined with nested = \"{mode}\""
+        ));
+    }
+    if infra_column(&ident_name(f)).is_some() {
+        return fail("a nested column cannot take an infra column's name");
+    }
+    if f.default.as_deref().is_some_and(|d| !is_expr(d)) {
+        return fail("default must be a single non-empty expression");
+    }
+    if f.ttl.as_deref().is_some_and(|t| !is_expr(t)) {
+        return fail("ttl must be a single non-empty expression");
+    }
+
+    if option_inner_ty(&f.ty).is_some() {
+        return fail(
+            "ClickHouse cannot make a nested column Nullable; make the child's fields Option instead",
+        );
+    }
+    match nested {
+        Nested::Tuple if nested_struct_ty(&f.ty).is_some() => Ok(()),
+        Nested::Tuple => fail("nested = \"tuple\" needs a BenSchema struct or a Vec of one"),
+        Nested::Flatten if f.default.is_some() || f.ttl.is_some() => {
+            fail("default and ttl do not apply to flattened columns; set them on the child")
+        }
+        Nested::Flatten if is_named_type(&f.ty) => Ok(()),
+        Nested::Flatten => fail("nested = \"flatten\" needs a BenSchema struct"),
+    }
+}
+
+/// The child struct of a `nested = "tuple"` field: `T` or `Vec<T>`.
+pub fn nested_struct_ty(ty: &Type) -> Option<&Type> {
+    let inner = vec_inner_ty(ty).unwrap_or(ty);
+    is_named_type(inner).then_some(inner)
+}
+
+fn in_sorting_key(spec: &SchemaSpec, f: &SchemaField) -> bool {
+    match spec.order_by {
+        Some(ref ob) => ob.split(',').any(|s| s.trim() == ident_name(f)),
+        None => f.key,
+    }
+}
+
+/// Rejects empty expressions and anything that could end the statement.
+fn is_expr(s: &str) -> bool {
+    !s.trim().is_empty() && !s.contains(';')
+}
+
+/// `CODEC(...)` for `compression = "lz4" | "lz4hc(N)" | "zstd(N)" | "none"`.
+pub fn codec(spec: &str) -> Result<String, String> {
+    let spec = spec.trim();
+    let (name, level) = match spec.split_once('(') {
+        Some((name, rest)) => {
+            let level = rest
+                .strip_suffix(')')
+                .and_then(|l| l.trim().parse::<u8>().ok())
+                .ok_or_else(|| format!("bad compression level in \"{spec}\""))?;
+            (name.trim(), Some(level))
+        }
+        None => (spec, None),
+    };
+
+    let (canon, levels) = match name.to_ascii_lowercase().as_str() {
+        "none" => ("NONE", None),
+        "lz4" => ("LZ4", None),
+        "lz4hc" => ("LZ4HC", Some(1..=12)),
+        "zstd" => ("ZSTD", Some(1..=22)),
+        _ => {
+            return Err(format!(
+                "compression must be lz4, lz4hc, zstd or none, got \"{spec}\""
+            ));
+        }
+    };
+
+    match (level, levels) {
+        (None, _) => Ok(format!("CODEC({canon})")),
+        (Some(l), Some(r)) if r.contains(&l) => Ok(format!("CODEC({canon}({l}))")),
+        (Some(l), _) => Err(format!("compression level {l} is out of range for {canon}")),
+    }
+}
+
 fn fields(spec: &SchemaSpec) -> Vec<&SchemaField> {
     match &spec.data {
         darling::ast::Data::Struct(fields) => fields.iter().collect(),
@@ -125,7 +406,7 @@ fn ident_name(f: &SchemaField) -> String {
 fn err(spec: &SchemaSpec, msg: &str) -> syn::Result<()> {
     Err(syn::Error::new(
         proc_macro2::Span::call_site(),
-        format!("BenSchema: {msg} (table {}", spec.table),
+        format!("BenSchema: {msg} (table {})", spec.table),
     ))
 }
 
@@ -0,0 +1,108 @@
+use ben_contracts::registry::SchemaRegistry;
+use ben_macros::BenSchema;
+use ben_wire::{
+    rowbinary::RowBinaryColumns,
+    schema::{Field, FieldType},
+};
+
+#[derive(Debug, BenSchema)]
+#[bschema(
+    table = "attr_event",
+    version = 3,
+    compression = "zstd(3)",
+    ttl = "toDateTime(ts / 1000) + INTERVAL 30 DAY"
+)]
+struct AttrEvent {
+    #[bschema(key, unique)]
+    id: u64,
+
+    #[bschema(cardinality = "low")]
+    host: Option<String>,
+
+    #[bschema(default = "'unknown'")]
+    region: String,
+
+    #[bschema(ttl = "toDateTime(ts / 1000) + INTERVAL 1 DAY")]
+    payload: String,
+
+    ts: u64,
+}
+
+mod wire_only {
+    use ben_macros::BenSchema;
+
+    #[derive(Debug, BenSchema)]
+    #[bschema(table = "wire_only", version = 1, store = false)]
+    pub struct WireOnly {
+        #[bschema(key)]
+        pub id: u64,
+    }
+}
+
+#[test]
+fn column_attributes_reach_the_ddl() {
+    let ddl = AttrEvent::__BEN_SCHEMA_DDL;
+    println!("{ddl}");
+
+    assert!(ddl.contains("`id` UInt64 CODEC(ZSTD(3))"));
+    assert!(ddl.contains("`host` LowCardinality(Nullable(String)) CODEC(ZSTD(3))"));
+    assert!(ddl.contains("`region` String DEFAULT 'unknown' CODEC(ZSTD(3))"));
+    assert!(
+        ddl.contains("`payload` String CODEC(ZSTD(3)) TTL toDateTime(ts / 1000) + INTERVAL 1 DAY")
+    );
+    assert!(ddl.contains("ENGINE = ReplacingMergeTree"));
+    assert!(ddl.contains("ORDER BY (id)"));
+    assert!(ddl.contains("\nTTL toDateTime(ts / 1000) + INTERVAL 30 DAY\n"));
+}
+
+#[test]
+fn column_attributes_reach_the_manifest() {
+    let json: serde_json::Value = serde_json::from_str(AttrEvent::__BEN_SCHEMA_JSON).unwrap();
+    assert_eq!(json["engine"], "ReplacingMergeTree");
+    assert_eq!(json["compression"], "zstd(3)");
+    assert_eq!(json["store"], true);
+
+    let col = |name: &str| {
+        json["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(col("id")["unique"], true);
+    assert_eq!(col("host")["ch_type"], "LowCardinality(Nullable(String))");
+    assert_eq!(col("host")["nullable"], true);
+    assert_eq!(col("region")["default"], "'unknown'");
+    assert_eq!(
+        col("payload")["ttl"],
+        "toDateTime(ts / 1000) + INTERVAL 1 DAY"
+    );
+
+    let manifest = schema_manifest();
+    let table = &manifest.tables[0];
+    assert_eq!(table.name, "attr_event");
+    assert_eq!(table.columns[2].default.as_deref(), Some("'unknown'"));
+}
+
+#[test]
+fn low_cardinality_columns_decode_as_their_storage_type() {
+    assert_eq!(
+        AttrEvent::COLUMNS[1],
+        ("host", "LowCardinality(Nullable(String))")
+    );
+
+    let reg = SchemaRegistry::linked().unwrap();
+    let host = &reg.get(&AttrEvent::__BEN_SCHEMA_EVT_HASH).unwrap().fields[1];
+    assert!(host.nullable);
+    assert_eq!(host.ty.storage(), &FieldType::String);
+    assert_eq!(Field::from_ch_type("host", &host.ch_type()).unwrap(), *host);
+}
+
+#[test]
+fn store_false_has_no_table() {
+    assert_eq!(wire_only::WireOnly::__BEN_SCHEMA_DDL, "");
+    assert!(wire_only::schema_manifest().tables.is_empty());
+    assert!(wire_only::WireOnly::__BEN_SCHEMA_JSON.contains("\"store\": false"));
+}
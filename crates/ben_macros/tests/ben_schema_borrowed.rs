@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use ben_macros::BenSchema;
+use ben_wire::rowbinary::{
+    DecodeQuic, EncodeQuic, RowBinCursor, RowBinaryDecodeRef, RowBinaryEncode,
+};
+
+#[derive(Debug, PartialEq, BenSchema)]
+#[bschema(table = "http_access", version = 1, order_by = "id", borrowed)]
+pub struct HttpAccess {
+    #[bschema(key)]
+    id: u64,
+    host: String,
+    route: Option<String>,
+    body: Vec<u8>,
+    hops: Vec<String>,
+    #[bschema(tags)]
+    tags: HashMap<String, String>,
+    ok: bool,
+}
+
+fn sample() -> HttpAccess {
+    HttpAccess {
+        id: 7,
+        host: "edge-01.example".to_string(),
+        route: Some("/login".to_string()),
+        body: vec![0xDE, 0xAD, 0xBE, 0xEF],
+        hops: vec!["lb".to_string(), "cache".to_string()],
+        tags: HashMap::from([("tenant".to_string(), "acme".to_string())]),
+        ok: true,
+    }
+}
+
+fn points_into(buf: &[u8], bytes: &[u8]) -> bool {
+    buf.as_ptr_range().contains(&bytes.as_ptr())
+}
+
+#[test]
+fn borrowed_view_points_into_the_buffer() {
+    let owned = sample();
+    let mut buf = Vec::new();
+    owned.encode_rowbinary(&mut buf).unwrap();
+
+    let mut cur = RowBinCursor::new(&buf);
+    let view = HttpAccessRef::from_rowbinary_ref(&mut cur).unwrap();
+    assert_eq!(cur.pos, buf.len());
+
+    assert_eq!(view.id, 7);
+    assert_eq!(view.host, "edge-01.example");
+    assert_eq!(view.route, Some("/login"));
+    assert_eq!(view.body, &[0xDE, 0xAD, 0xBE, 0xEF]);
+    assert_eq!(view.hops, ["lb", "cache"]);
+    assert_eq!(view.tags.get("tenant"), Some(&"acme"));
+    assert!(view.ok);
+
+    assert!(points_into(&buf, view.host.as_bytes()));
+    assert!(points_into(&buf, view.route.unwrap().as_bytes()));
+    assert!(points_into(&buf, view.body));
+}
+
+#[test]
+fn borrowed_and_owned_quic_decode_agree() {
+    let owned = sample();
+    let mut frame = Vec::new();
+    owned.encode_quic(&mut frame).unwrap();
+
+    let view = HttpAccessRef::decode_quic_ref(&frame).unwrap();
+    let decoded = HttpAccess::decode_quic(&frame).unwrap();
+
+    assert_eq!(decoded, owned);
+    assert_eq!(view.host, decoded.host);
+    assert_eq!(view.body, decoded.body.as_slice());
+}
+
+#[test]
+fn borrowed_decode_rejects_foreign_frames() {
+    let mut frame = Vec::new();
+    sample().encode_quic(&mut frame).unwrap();
+    frame[0] ^= 0xFF;
+
+    assert!(HttpAccessRef::decode_quic_ref(&frame).is_err());
+    assert!(HttpAccessRef::decode_quic_ref(&frame[..10]).is_err());
+}
+
+#[test]
+fn truncated_rows_are_errors() {
+    let mut buf = Vec::new();
+    sample().encode_rowbinary(&mut buf).unwrap();
+
+    for end in 0..buf.len() {
+        let mut cur = RowBinCursor::new(&buf[..end]);
+        assert!(HttpAccessRef::from_rowbinary_ref(&mut cur).is_err());
+    }
+}
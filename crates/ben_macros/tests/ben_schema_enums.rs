@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use ben_contracts::{BenEnumDesc, registry::SchemaRegistry};
+use ben_macros::{BenEnum, BenSchema};
+use ben_wire::{
+    rowbinary::{DecodeQuic, EncodeQuic, RowBinaryColumns, RowBinaryEncode, RowBinaryNested},
+    schema::FieldType,
+};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, Copy, PartialEq, BenEnum)]
+enum Level {
+    #[benum(value = 1)]
+    Low,
+    High,
+    #[benum(label = "it's bad")]
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, BenEnum)]
+enum Status {
+    #[benum(value = 200)]
+    Ok,
+    #[benum(value = 404, label = "not found")]
+    NotFound,
+}
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(nested)]
+struct Check {
+    name: String,
+    status: Status,
+}
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(table = "alert", version = 1)]
+struct Alert {
+    #[bschema(key)]
+    id: u64,
+    level: Level,
+    prev: Option<Level>,
+    history: Vec<Status>,
+    by_host: HashMap<String, Level>,
+    #[bschema(nested = "flatten")]
+    check: Check,
+}
+
+const LEVEL: &str = r"Enum8('Low' = 1, 'High' = 2, 'it\'s bad' = 3)";
+const STATUS: &str = "Enum16('Ok' = 200, 'not found' = 404)";
+
+fn alert() -> Alert {
+    Alert {
+        id: 9,
+        level: Level::Critical,
+        prev: Some(Level::Low),
+        history: vec![Status::Ok, Status::NotFound],
+        by_host: HashMap::from([("db1".to_string(), Level::High)]),
+        check: Check {
+            name: "disk".into(),
+            status: Status::NotFound,
+        },
+    }
+}
+
+#[test]
+fn enums_pick_the_narrowest_clickhouse_enum() {
+    assert_eq!(Level::CH_TYPE, LEVEL);
+    assert_eq!(Status::CH_TYPE, STATUS);
+    assert_eq!(
+        Alert::COLUMNS,
+        [
+            ("id", "UInt64"),
+            ("level", LEVEL),
+            ("prev", &*format!("Nullable({LEVEL})")),
+            ("history", &*format!("Array({STATUS})")),
+            ("by_host", &*format!("Map(String, {LEVEL})")),
+            ("check_name", "String"),
+            ("check_status", STATUS),
+        ]
+    );
+    assert_eq!(
+        Check::TUPLE_TYPE,
+        format!("Tuple(name String, status {STATUS})")
+    );
+    assert!(Alert::__BEN_SCHEMA_DDL.contains(&format!("  `level` {LEVEL},\n")));
+}
+
+#[test]
+fn manifest_records_the_enum_map() {
+    let json: serde_json::Value = serde_json::from_str(Alert::__BEN_SCHEMA_JSON).unwrap();
+    let enum_map = |name: &str| {
+        let cols = json["columns"].as_array().unwrap();
+        cols.iter().find(|c| c["name"] == name).unwrap()["enum_map"].clone()
+    };
+    let level = serde_json::json!({ "Low": 1, "High": 2, "it's bad": 3 });
+    assert_eq!(enum_map("level"), level);
+    assert_eq!(enum_map("prev"), level);
+    assert_eq!(enum_map("by_host"), level);
+    assert_eq!(
+        enum_map("check_status"),
+        serde_json::json!({ "Ok": 200, "not found": 404 })
+    );
+    assert_eq!(enum_map("id"), serde_json::Value::Null);
+
+    let digest: [u8; 32] = Sha256::digest(Alert::__BEN_SCHEMA_JSON.as_bytes()).into();
+    assert_eq!(Alert::__BEN_SCHEMA_EVT_HASH, digest);
+}
+
+#[test]
+fn enums_use_one_or_two_bytes_on_the_wire() {
+    let mut buf = Vec::new();
+    Check {
+        name: "d".into(),
+        status: Status::NotFound,
+    }
+    .encode_rowbinary(&mut buf)
+    .unwrap();
+    assert_eq!(buf, [1, b'd', 0x94, 0x01]);
+
+    let mut buf = Vec::new();
+    alert().encode_rowbinary(&mut buf).unwrap();
+    // id, then level as a single Int8.
+    assert_eq!(&buf[8..10], [3, 0]);
+
+    let mut buf = Vec::new();
+    alert().encode_quic(&mut buf).unwrap();
+    assert_eq!(Alert::decode_quic(&buf).unwrap(), alert());
+}
+
+#[test]
+fn unknown_enum_values_fail_to_decode() {
+    let mut buf = Vec::new();
+    Check {
+        name: "d".into(),
+        status: Status::Ok,
+    }
+    .encode_rowbinary(&mut buf)
+    .unwrap();
+    buf[2] = 0x95;
+    let mut cur = ben_wire::rowbinary::RowBinCursor::new(&buf);
+    let err = Check::decode_nested(&mut cur).unwrap_err();
+    assert!(err.to_string().contains("Invalid enum value"));
+}
+
+#[test]
+fn registry_parses_enum_columns() {
+    let mut reg = SchemaRegistry::new();
+    let hash = reg.load_manifest_json(Alert::__BEN_SCHEMA_JSON).unwrap();
+    let schema = reg.get(&hash).unwrap();
+    assert_eq!(
+        schema.fields[1].ty,
+        FieldType::Enum8(vec![
+            ("Low".into(), 1),
+            ("High".into(), 2),
+            ("it's bad".into(), 3),
+        ])
+    );
+    assert!(schema.fields[2].nullable);
+    assert_eq!(schema.fields[6].ch_type(), STATUS);
+}
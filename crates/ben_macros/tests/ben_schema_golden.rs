@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use ben_macros::BenSchema;
+use ben_wire::rowbinary::{RowBinCursor, RowBinaryColumns, RowBinaryDecode, RowBinaryEncode};
+
+#[derive(Debug, PartialEq, BenSchema)]
+#[bschema(table = "golden_event", version = 1, order_by = "id")]
+struct GoldenEvent {
+    #[bschema(key)]
+    id: u64,
+    name: String,
+    note: Option<String>,
+    gone: Option<String>,
+    values: Vec<i64>,
+    attrs: HashMap<String, String>,
+    flag: bool,
+    ratio: f64,
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    s.split_whitespace()
+        .map(|b| u8::from_str_radix(b, 16).unwrap())
+        .collect()
+}
+
+// SELECT 42::UInt64, 'héllo', 'x'::Nullable(String), NULL::Nullable(String),
+//        [1, -1]::Array(Int64), map('k', 'v'), true, 1.5::Float64
+// FORMAT RowBinary
+fn golden() -> Vec<u8> {
+    hex(concat!(
+        "2a 00 00 00 00 00 00 00 ",
+        "06 68 c3 a9 6c 6c 6f ",
+        "00 01 78 ",
+        "01 ",
+        "02 01 00 00 00 00 00 00 00 ff ff ff ff ff ff ff ff ",
+        "01 01 6b 01 76 ",
+        "01 ",
+        "00 00 00 00 00 00 f8 3f",
+    ))
+}
+
+fn event() -> GoldenEvent {
+    GoldenEvent {
+        id: 42,
+        name: "héllo".to_string(),
+        note: Some("x".to_string()),
+        gone: None,
+        values: vec![1, -1],
+        attrs: HashMap::from([("k".to_string(), "v".to_string())]),
+        flag: true,
+        ratio: 1.5,
+    }
+}
+
+#[test]
+fn derived_encoder_matches_clickhouse() {
+    let mut out = Vec::new();
+    event().encode_rowbinary(&mut out).unwrap();
+    assert_eq!(out, golden());
+}
+
+#[test]
+fn derived_decoder_reads_clickhouse_output() {
+    let bytes = golden();
+    let mut cur = RowBinCursor::new(&bytes);
+    assert_eq!(GoldenEvent::from_rowbinary(&mut cur).unwrap(), event());
+    assert_eq!(cur.pos, bytes.len());
+}
+
+#[test]
+fn derived_columns_follow_encode_order() {
+    assert_eq!(
+        GoldenEvent::COLUMNS,
+        &[
+            ("id", "UInt64"),
+            ("name", "String"),
+            ("note", "Nullable(String)"),
+            ("gone", "Nullable(String)"),
+            ("values", "Array(Int64)"),
+            ("attrs", "Map(String, String)"),
+            ("flag", "Bool"),
+            ("ratio", "Float64"),
+        ]
+    );
+}
@@ -0,0 +1,108 @@
+use ben_contracts::registry::SchemaRegistry;
+use ben_macros::BenSchema;
+use ben_wire::{
+    rowbinary::RowBinaryColumns,
+    schema::{FieldType, INFRA_COLUMNS},
+};
+
+#[derive(Debug, BenSchema)]
+#[bschema(table = "infra_event", version = 4)]
+struct InfraEvent {
+    #[bschema(key)]
+    id: u64,
+    /// Filled in by the sender rather than left to the server clock.
+    ingest_ts: i64,
+    field_mask: u128,
+    host: String,
+}
+
+fn ddl_columns() -> Vec<String> {
+    InfraEvent::__BEN_SCHEMA_DDL
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix('`'))
+        .map(|l| l.split('`').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn table_name_is_not_the_fingerprint() {
+    assert_eq!(InfraEvent::__BEN_SCHEMA_TABLE, "infra_event");
+    assert_eq!(schema_manifest().tables[0].name, "infra_event");
+}
+
+#[test]
+fn every_infra_column_appears_once_after_the_struct_fields() {
+    assert_eq!(
+        ddl_columns(),
+        [
+            "id",
+            "ingest_ts",
+            "field_mask",
+            "host",
+            "tenant_id",
+            "schema_version",
+            "system_mask",
+            "struct_mask",
+        ]
+    );
+    for infra in INFRA_COLUMNS {
+        assert_eq!(ddl_columns().iter().filter(|c| *c == infra.name).count(), 1);
+    }
+
+    let ddl = InfraEvent::__BEN_SCHEMA_DDL;
+    assert!(ddl.contains("`ingest_ts` DateTime64(3) DEFAULT now64(3)"));
+    assert!(ddl.contains("`schema_version` UInt32 DEFAULT 4"));
+    assert!(ddl.contains("`tenant_id` UUID DEFAULT toUUID("));
+}
+
+#[test]
+fn manifest_and_table_def_match_the_ddl() {
+    let json: serde_json::Value = serde_json::from_str(InfraEvent::__BEN_SCHEMA_JSON).unwrap();
+    let cols = json["columns"].as_array().unwrap();
+    let names: Vec<_> = cols.iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ddl_columns());
+
+    let infra: Vec<_> = cols
+        .iter()
+        .filter(|c| c["infra"] == true)
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        infra,
+        ["tenant_id", "schema_version", "system_mask", "struct_mask"]
+    );
+
+    let manifest = schema_manifest();
+    let table = &manifest.tables[0];
+    let defs: Vec<_> = table.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(defs, ddl_columns());
+    assert!(table.columns[1].default.as_deref() == Some("now64(3)") && !table.columns[1].infra);
+    assert!(table.columns[4].infra);
+}
+
+#[test]
+fn encoder_header_covers_only_the_struct_fields() {
+    assert_eq!(
+        InfraEvent::COLUMNS,
+        [
+            ("id", "UInt64"),
+            ("ingest_ts", "DateTime64(3)"),
+            ("field_mask", "UInt128"),
+            ("host", "String"),
+        ]
+    );
+
+    let reg = SchemaRegistry::linked().unwrap();
+    let schema = reg.get(&InfraEvent::__BEN_SCHEMA_EVT_HASH).unwrap();
+    assert_eq!(
+        schema.field_names(),
+        ["id", "ingest_ts", "field_mask", "host"]
+    );
+    assert_eq!(schema.fields[1].ty, FieldType::DateTime64 { scale: 3 });
+
+    let mut loaded = SchemaRegistry::new();
+    loaded
+        .load_manifest_json(InfraEvent::__BEN_SCHEMA_JSON)
+        .unwrap();
+    assert_eq!(loaded.get(&InfraEvent::__BEN_SCHEMA_EVT_HASH), Some(schema));
+}
@@ -0,0 +1,74 @@
+use ben_macros::{BenSchema, BenView};
+use ben_wire::{
+    rowbinary::{DecodeQuic, EncodeQuic, RowBinaryColumns, RowBinaryEncode},
+    slot::SlotValue,
+    view::view,
+};
+
+#[derive(Debug, Clone, PartialEq, BenSchema, BenView, serde::Serialize)]
+#[bschema(table = "narrow_event", version = 1)]
+struct NarrowEvent {
+    #[bschema(key)]
+    id: u64,
+    port: u16,
+    bytes: u32,
+    delta: i8,
+    offset: i16,
+    skew: i32,
+    ratio: f32,
+    ports: Vec<u16>,
+    skews: Option<i32>,
+}
+
+fn narrow() -> NarrowEvent {
+    NarrowEvent {
+        id: 7,
+        port: 443,
+        bytes: 3_000_000_000,
+        delta: -3,
+        offset: -1_000,
+        skew: -70_000,
+        ratio: 0.25,
+        ports: vec![80, 65_535],
+        skews: Some(i32::MIN),
+    }
+}
+
+#[test]
+fn narrow_types_round_trip_rowbinary() {
+    assert_eq!(
+        NarrowEvent::COLUMNS[1..7],
+        [
+            ("port", "UInt16"),
+            ("bytes", "UInt32"),
+            ("delta", "Int8"),
+            ("offset", "Int16"),
+            ("skew", "Int32"),
+            ("ratio", "Float32"),
+        ]
+    );
+
+    let mut buf = Vec::new();
+    narrow().encode_rowbinary(&mut buf).unwrap();
+    assert_eq!(buf.len(), 8 + 2 + 4 + 1 + 2 + 4 + 4 + (1 + 2 * 2) + (1 + 4));
+
+    let mut quic = Vec::new();
+    narrow().encode_quic(&mut quic).unwrap();
+    assert_eq!(NarrowEvent::decode_quic(&quic).unwrap(), narrow());
+}
+
+#[test]
+fn narrow_types_round_trip_rowpack_and_view() {
+    let mut reg = ben_contracts::registry::SchemaRegistry::new();
+    let hash = reg
+        .load_manifest_json(NarrowEvent::__BEN_SCHEMA_JSON)
+        .unwrap();
+    let schema = reg.get(&hash).unwrap();
+
+    let mut buf = Vec::new();
+    let row = ben_wire::serde_bridge::to_row(&narrow(), schema, &mut buf).unwrap();
+    assert!(matches!(row[1], SlotValue::U16(443)));
+    assert!(matches!(row[3], SlotValue::I8(-3)));
+    assert!(matches!(row[6], SlotValue::F32(v) if v == 0.25));
+    assert_eq!(view::<NarrowEvent>(schema, &row).unwrap(), narrow());
+}
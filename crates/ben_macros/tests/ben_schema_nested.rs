@@ -0,0 +1,149 @@
+use ben_contracts::registry::SchemaRegistry;
+use ben_macros::BenSchema;
+use ben_wire::{
+    rowbinary::{DecodeQuic, EncodeQuic, RowBinaryColumns, RowBinaryNested},
+    schema::FieldType,
+};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(nested)]
+struct Geo {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(nested)]
+struct Endpoint {
+    host: String,
+    port: u64,
+    #[bschema(nested = "tuple")]
+    geo: Geo,
+}
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(table = "conn_event", version = 2, compression = "zstd(3)")]
+struct ConnEvent {
+    #[bschema(key)]
+    id: u64,
+    #[bschema(nested = "flatten")]
+    src: Endpoint,
+    #[bschema(nested = "tuple")]
+    dst: Endpoint,
+    #[bschema(nested = "tuple")]
+    hops: Vec<Geo>,
+    note: Option<String>,
+}
+
+fn event() -> ConnEvent {
+    let geo = |lat, lon| Geo { lat, lon };
+    ConnEvent {
+        id: 7,
+        src: Endpoint {
+            host: "10.0.0.1".into(),
+            port: 443,
+            geo: geo(1.5, 2.5),
+        },
+        dst: Endpoint {
+            host: "example.org".into(),
+            port: 8443,
+            geo: geo(-3.0, 4.0),
+        },
+        hops: vec![geo(0.0, 0.0), geo(9.0, -9.0)],
+        note: None,
+    }
+}
+
+#[test]
+fn tuple_types_follow_the_child_columns() {
+    assert_eq!(Geo::TUPLE_TYPE, "Tuple(lat Float64, lon Float64)");
+    assert_eq!(
+        Endpoint::TUPLE_TYPE,
+        "Tuple(host String, port UInt64, geo Tuple(lat Float64, lon Float64))"
+    );
+}
+
+#[test]
+fn flatten_prefixes_the_child_columns() {
+    let names: Vec<_> = ConnEvent::COLUMNS.iter().map(|(n, _)| *n).collect();
+    assert_eq!(
+        names,
+        [
+            "id", "src_host", "src_port", "src_geo", "dst", "hops", "note"
+        ]
+    );
+    assert_eq!(
+        ConnEvent::COLUMNS[5].1,
+        "Array(Tuple(lat Float64, lon Float64))"
+    );
+    assert_eq!(ConnEvent::__BEN_SCHEMA_FIELD_COUNT, 7);
+
+    let ddl = ConnEvent::__BEN_SCHEMA_DDL;
+    assert!(ddl.contains("  `src_port` UInt64 CODEC(ZSTD(3)),\n"));
+    assert!(ddl.contains(&format!(
+        "  `dst` {} CODEC(ZSTD(3)),\n",
+        Endpoint::TUPLE_TYPE
+    )));
+    assert!(ddl.contains("`tenant_id` UUID"));
+}
+
+#[test]
+fn manifest_is_valid_json_and_hashed_like_any_other() {
+    let json: serde_json::Value = serde_json::from_str(ConnEvent::__BEN_SCHEMA_JSON).unwrap();
+    let cols = json["columns"].as_array().unwrap();
+    let user: Vec<_> = cols
+        .iter()
+        .filter(|c| c["infra"] == false)
+        .map(|c| (c["name"].as_str().unwrap(), c["ch_type"].as_str().unwrap()))
+        .collect();
+    assert_eq!(user, ConnEvent::COLUMNS);
+    assert_eq!(cols[4]["rust_type"], "Endpoint");
+
+    let digest: [u8; 32] = Sha256::digest(ConnEvent::__BEN_SCHEMA_JSON.as_bytes()).into();
+    assert_eq!(ConnEvent::__BEN_SCHEMA_EVT_HASH, digest);
+    assert_eq!(
+        ConnEvent::__BEN_SCHEMA_FINGERPRINT,
+        format!("sha256:{}", hex(&digest))
+    );
+
+    let defs: Vec<_> = schema_manifest().tables[0]
+        .columns
+        .iter()
+        .filter(|c| !c.infra)
+        .map(|c| (c.name.clone(), c.ch_type.clone()))
+        .collect();
+    let expected: Vec<_> = ConnEvent::COLUMNS
+        .iter()
+        .map(|(n, t)| (n.to_string(), t.to_string()))
+        .collect();
+    assert_eq!(defs, expected);
+}
+
+#[test]
+fn nested_rows_round_trip() {
+    let mut buf = Vec::new();
+    event().encode_quic(&mut buf).unwrap();
+    assert_eq!(ConnEvent::decode_quic(&buf).unwrap(), event());
+}
+
+#[test]
+fn registry_parses_tuple_columns() {
+    let mut reg = SchemaRegistry::new();
+    let hash = reg
+        .load_manifest_json(ConnEvent::__BEN_SCHEMA_JSON)
+        .unwrap();
+    let schema = reg.get(&hash).unwrap();
+    assert_eq!(schema.fields.len(), ConnEvent::COLUMNS.len());
+
+    let FieldType::Tuple(elems) = &schema.fields[4].ty else {
+        panic!("dst is {:?}", schema.fields[4].ty);
+    };
+    let names: Vec<_> = elems.iter().map(|(n, _)| n.as_str()).collect();
+    assert_eq!(names, ["host", "port", "geo"]);
+    assert_eq!(schema.fields[4].ch_type(), Endpoint::TUPLE_TYPE);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
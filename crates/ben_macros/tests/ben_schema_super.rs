@@ -59,7 +59,7 @@ fn super_schema_test() {
     // --- 1. Validate schema consts ---
     //
 
-    //assert_eq!(SuperEvent::__BEN_SCHEMA_TABLE, "super_event");
+    assert_eq!(SuperEvent::__BEN_SCHEMA_TABLE, "super_event");
     assert_eq!(SuperEvent::__BEN_SCHEMA_VERSION, 9);
     assert!(SuperEvent::__BEN_SCHEMA_DDL.contains("CREATE TABLE IF NOT EXISTS super_event"));
     assert!(SuperEvent::__BEN_SCHEMA_FINGERPRINT.starts_with("sha256:"));
@@ -88,7 +88,7 @@ fn super_schema_test() {
     let ddl = SuperEvent::__BEN_SCHEMA_DDL;
     println!("{ddl}");
     assert!(ddl.contains("`id` UInt64"));
-    assert!(ddl.contains("`kind` String"));
+    assert!(ddl.contains("`kind` LowCardinality(String)"));
     assert!(ddl.contains("`note` Nullable(String)"));
     assert!(ddl.contains("`values` Array(Int64)"));
     assert!(ddl.contains("`info` Map(String, String)"));
@@ -124,7 +124,7 @@ fn super_schema_test() {
     // --- 5. QUIC framing correctness ---
     //
     let mut quic = Vec::new();
-    evt.encode_quic(&mut quic);
+    evt.encode_quic(&mut quic).unwrap();
 
     // header: 32 bytes hash + 2 bytes field count
     let expected_header_len = 32 + 2;
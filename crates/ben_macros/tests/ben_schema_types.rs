@@ -0,0 +1,113 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ben_macros::BenSchema;
+use ben_wire::rowbinary::{DecodeQuic, EncodeQuic, RowBinaryColumns, RowBinaryEncode};
+
+#[derive(Debug, Clone, PartialEq, BenSchema)]
+#[bschema(table = "net_event", version = 1)]
+struct NetEvent {
+    #[bschema(key)]
+    id: u64,
+    ingest_ts: SystemTime,
+    src: Ipv4Addr,
+    dst: Ipv6Addr,
+    peer: IpAddr,
+    hops: Vec<IpAddr>,
+    trace: uuid::Uuid,
+    parent: Option<uuid::Uuid>,
+    #[bschema(scale = 6)]
+    seen: time::OffsetDateTime,
+    #[bschema(scale = 0)]
+    expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn event() -> NetEvent {
+    NetEvent {
+        id: 1,
+        ingest_ts: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        src: Ipv4Addr::new(10, 1, 2, 3),
+        dst: Ipv6Addr::LOCALHOST,
+        peer: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+        hops: vec![
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            IpAddr::V4(Ipv4Addr::BROADCAST),
+        ],
+        trace: uuid::Uuid::from_u128(0x0011_2233_4455_6677_8899_aabb_ccdd_eeff),
+        parent: None,
+        seen: time::OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_000).unwrap(),
+        expires: chrono::DateTime::from_timestamp(1_800_000_000, 0),
+    }
+}
+
+#[test]
+fn library_types_map_to_clickhouse_types() {
+    assert_eq!(
+        NetEvent::COLUMNS,
+        [
+            ("id", "UInt64"),
+            ("ingest_ts", "DateTime64(3)"),
+            ("src", "IPv4"),
+            ("dst", "IPv6"),
+            ("peer", "IPv6"),
+            ("hops", "Array(IPv6)"),
+            ("trace", "UUID"),
+            ("parent", "Nullable(UUID)"),
+            ("seen", "DateTime64(6)"),
+            ("expires", "Nullable(DateTime64(0))"),
+        ]
+    );
+    // A SystemTime claims the infra column like an i64 of milliseconds would.
+    assert!(NetEvent::__BEN_SCHEMA_DDL.contains("`ingest_ts` DateTime64(3) DEFAULT now64(3)"));
+}
+
+#[test]
+fn values_use_the_clickhouse_layout() {
+    let mut buf = Vec::new();
+    event().encode_rowbinary(&mut buf).unwrap();
+
+    let ts = &buf[8..16];
+    assert_eq!(ts, 1_700_000_000_123i64.to_le_bytes());
+    let src = &buf[16..20];
+    assert_eq!(src, 0x0a01_0203u32.to_le_bytes());
+    let peer = &buf[36..52];
+    assert_eq!(
+        peer,
+        Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped().octets()
+    );
+}
+
+#[test]
+fn library_types_round_trip() {
+    let mut buf = Vec::new();
+    event().encode_quic(&mut buf).unwrap();
+    assert_eq!(NetEvent::decode_quic(&buf).unwrap(), event());
+}
+
+#[test]
+fn datetimes_truncate_to_their_scale() {
+    let mut e = event();
+    e.ingest_ts += Duration::from_micros(999);
+    e.seen += time::Duration::nanoseconds(999);
+
+    let mut buf = Vec::new();
+    e.encode_quic(&mut buf).unwrap();
+    let back = NetEvent::decode_quic(&buf).unwrap();
+    assert_eq!(back.ingest_ts, event().ingest_ts);
+    assert_eq!(back.seen, event().seen);
+}
+
+#[test]
+fn registry_parses_library_columns() {
+    let mut reg = ben_contracts::registry::SchemaRegistry::new();
+    let hash = reg.load_manifest_json(NetEvent::__BEN_SCHEMA_JSON).unwrap();
+    let schema = reg.get(&hash).unwrap();
+    let types: Vec<_> = schema.fields.iter().map(|f| f.ch_type()).collect();
+    let expected: Vec<_> = NetEvent::COLUMNS
+        .iter()
+        .map(|(_, t)| t.to_string())
+        .collect();
+    assert_eq!(types, expected);
+}
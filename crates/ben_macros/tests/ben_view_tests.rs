@@ -0,0 +1,283 @@
+use ben_macros::{BenSchema, BenView};
+use ben_wire::{
+    error::ViewError,
+    schema::{Field, FieldType, Schema},
+    slot::SlotValue,
+    view::{BenView, view},
+};
+
+#[derive(Debug, PartialEq, BenSchema, BenView)]
+#[bschema(table = "login", version = 1, order_by = "user")]
+struct Login {
+    #[bschema(key)]
+    user: String,
+    heat: u64,
+}
+
+#[derive(Debug, PartialEq, BenView)]
+#[bview(event = "login")]
+struct LoginReader {
+    user: String,
+    heat: f64,
+    ip: Option<String>,
+    #[bview(rename = "tenant_name")]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, BenView)]
+#[bview(schema = Login)]
+struct StrictLogin {
+    #[allow(dead_code)]
+    region: String,
+}
+
+fn field(name: &str, ty: FieldType, nullable: bool) -> Field {
+    Field {
+        name: name.to_string(),
+        ty,
+        nullable,
+    }
+}
+
+fn login_v1() -> Schema {
+    Schema {
+        event: "login".to_string(),
+        version: 1,
+        evt_hash: Login::__BEN_SCHEMA_EVT_HASH,
+        fields: vec![
+            field("user", FieldType::String, false),
+            field("heat", FieldType::UInt64, false),
+        ],
+    }
+}
+
+/// v2 reorders, adds a column and makes `ip` a real field.
+fn login_v2() -> Schema {
+    Schema {
+        event: "login".to_string(),
+        version: 2,
+        evt_hash: [0x77; 32],
+        fields: vec![
+            field("heat", FieldType::UInt64, false),
+            field("ip", FieldType::String, true),
+            field("user", FieldType::String, false),
+            field("session_ms", FieldType::UInt64, false),
+        ],
+    }
+}
+
+#[test]
+fn derived_consts() {
+    assert_eq!(Login::EVT_HASH, Login::__BEN_SCHEMA_EVT_HASH);
+    assert_eq!(Login::EVENT, None);
+    assert_eq!(Login::FIELD_NAMES, &["user", "heat"]);
+    assert_eq!(Login::FIELD_COUNT, 2);
+
+    assert_eq!(LoginReader::EVENT, Some("login"));
+    assert_eq!(
+        LoginReader::FIELD_NAMES,
+        &["user", "heat", "ip", "tenant_name"]
+    );
+    assert_eq!(StrictLogin::EVT_HASH, Login::__BEN_SCHEMA_EVT_HASH);
+}
+
+#[test]
+fn derived_view_reads_its_own_schema() {
+    let row = [SlotValue::Str("alice"), SlotValue::U64(200)];
+
+    let login: Login = view(&login_v1(), &row).unwrap();
+    assert_eq!(
+        login,
+        Login {
+            user: "alice".to_string(),
+            heat: 200
+        }
+    );
+}
+
+#[test]
+fn exact_hash_view_rejects_other_versions() {
+    let row = [
+        SlotValue::U64(1),
+        SlotValue::Missing,
+        SlotValue::Str("bob"),
+        SlotValue::U64(3),
+    ];
+
+    let err = view::<Login>(&login_v2(), &row).unwrap_err();
+    assert!(matches!(err, ViewError::EvtHashMismatch { .. }));
+}
+
+#[test]
+fn v1_reader_decodes_v2_rows() {
+    let row = [
+        SlotValue::U64(300),
+        SlotValue::Str("10.0.0.9"),
+        SlotValue::Str("carol"),
+        SlotValue::U64(12),
+    ];
+
+    let reader: LoginReader = view(&login_v2(), &row).unwrap();
+    assert_eq!(
+        reader,
+        LoginReader {
+            user: "carol".to_string(),
+            heat: 300.0,
+            ip: Some("10.0.0.9".to_string()),
+            tenant: None,
+        }
+    );
+}
+
+#[test]
+fn missing_nullable_fields_default_to_none() {
+    let row = [SlotValue::Str("dave"), SlotValue::U64(5)];
+
+    let reader: LoginReader = view(&login_v1(), &row).unwrap();
+    assert_eq!(reader.ip, None);
+    assert_eq!(reader.tenant, None);
+    assert_eq!(reader.heat, 5.0);
+}
+
+#[test]
+fn missing_required_field_is_an_error() {
+    let row = [SlotValue::Str("erin"), SlotValue::U64(5)];
+
+    let err = view::<StrictLogin>(&login_v1(), &row).unwrap_err();
+    assert!(matches!(
+        err,
+        ViewError::MissingNotAllowed { field: "region" }
+    ));
+}
+
+#[test]
+fn incompatible_types_are_rejected() {
+    let row = [SlotValue::Str("frank"), SlotValue::Str("hot")];
+
+    let err = view::<LoginReader>(&login_v1(), &row).unwrap_err();
+    assert!(matches!(err, ViewError::TypeMismatch { field: "heat" }));
+}
+
+#[test]
+fn row_must_match_its_schema() {
+    let row = [SlotValue::Str("gina")];
+
+    let err = view::<LoginReader>(&login_v1(), &row).unwrap_err();
+    assert!(matches!(
+        err,
+        ViewError::FieldCountMismatch {
+            expected: 2,
+            got: 1
+        }
+    ));
+}
+
+#[derive(Debug, PartialEq, BenView)]
+#[bview(event = "flow")]
+struct FlowReader {
+    port: u16,
+    delta: i64,
+    loss: f64,
+    payload: Vec<u8>,
+    hops: Vec<Option<u32>>,
+    labels: std::collections::HashMap<String, i16>,
+}
+
+#[test]
+fn narrow_and_nested_columns() {
+    use ben_wire::{
+        rowpack::encode_rowpack,
+        slot::{SlotMap, SlotSeq},
+    };
+
+    let schema = Schema {
+        event: "flow".to_string(),
+        version: 1,
+        evt_hash: [0x31; 32],
+        fields: vec![
+            field("port", FieldType::UInt16, false),
+            field("delta", FieldType::Int8, false),
+            field("loss", FieldType::Float32, false),
+            field("payload", FieldType::Bytes, false),
+            field(
+                "hops",
+                FieldType::Array(Box::new(FieldType::Nullable(Box::new(FieldType::UInt32)))),
+                false,
+            ),
+            field(
+                "labels",
+                FieldType::Map(Box::new(FieldType::String), Box::new(FieldType::Int16)),
+                false,
+            ),
+        ],
+    };
+
+    let mut hops = Vec::new();
+    encode_rowpack(&[SlotValue::U32(7), SlotValue::Missing], &mut hops).unwrap();
+    let mut labels = Vec::new();
+    encode_rowpack(&[SlotValue::Str("x"), SlotValue::I16(-2)], &mut labels).unwrap();
+
+    let row = [
+        SlotValue::U16(443),
+        SlotValue::I8(-1),
+        SlotValue::F32(0.5),
+        SlotValue::Bytes(&[1, 2]),
+        SlotValue::Array(SlotSeq::new(2, &hops)),
+        SlotValue::Map(SlotMap::new(1, &labels)),
+    ];
+
+    let reader: FlowReader = view(&schema, &row).unwrap();
+    assert_eq!(
+        reader,
+        FlowReader {
+            port: 443,
+            delta: -1,
+            loss: 0.5,
+            payload: vec![1, 2],
+            hops: vec![Some(7), None],
+            labels: [("x".to_string(), -2)].into(),
+        }
+    );
+}
+
+#[derive(Debug, PartialEq, BenView)]
+#[bview(event = "login", lenient)]
+struct LenientLogin {
+    user: String,
+    heat: u32,
+}
+
+#[test]
+fn lenient_views_parse_text_columns() {
+    let row = [SlotValue::Str("hal"), SlotValue::Str("17")];
+
+    assert!(matches!(
+        view::<LoginReader>(&login_v1(), &row).unwrap_err(),
+        ViewError::TypeMismatch { field: "heat" }
+    ));
+
+    let login: LenientLogin = view(&login_v1(), &row).unwrap();
+    assert_eq!(login.heat, 17);
+}
+
+#[test]
+fn slot_conversions_respect_column_types() {
+    use ben_wire::{slot::Coercion, view::FromSlot};
+
+    let millis = SlotValue::DateTime64 {
+        epoch: 1_500,
+        scale: 3,
+    };
+    let micros = SlotValue::DateTime64 {
+        epoch: 1_500_000,
+        scale: 6,
+    };
+    for slot in [millis, micros] {
+        assert_eq!(i64::from_slot(&slot, Coercion::Strict), Some(1_500_000_000));
+    }
+
+    // An address is not a number, even though it is stored as one.
+    let ip = SlotValue::IPv4(0x0A00_0001);
+    assert_eq!(u64::from_slot(&ip, Coercion::Strict), None);
+    assert_eq!(u32::from_slot(&ip, Coercion::Lenient), None);
+}
@@ -0,0 +1,120 @@
+use ben_contracts::{
+    rules::{self, Action},
+    specs,
+};
+use ben_macros::Bitspec;
+use ben_wire::slot::SlotValue;
+use bitspec_engine::{BitMask, pack::BitInfo};
+
+mod common;
+use common::Row;
+
+#[allow(dead_code)]
+#[derive(Debug, Bitspec)]
+struct Conn {
+    #[bspec(brule(rule = "BIG", op = gt(1000)))]
+    bytes: f64,
+
+    #[bspec(brule(rule = "NOT_TLS", op = not(eq("tls"))))]
+    proto: &'static str,
+
+    #[bspec(brule(rule = "odd-port", op = mod_eq(2, 1)))]
+    #[bspec(brule(rule = "LOW_PORT", op = between(-1, 1023.5)))]
+    port: f64,
+
+    #[bspec(brule(rule = "LOW_PORT", op = lt(1024)))]
+    src_port: f64,
+
+    #[bspec(thresholds(rule = "Latency", op = "gte", values = "SLOW=100, STALLED=1000"))]
+    latency_ms: f64,
+}
+
+#[test]
+fn consts_name_each_rule() {
+    assert_eq!(Conn::BIG, BitMask::bit(0).unwrap());
+    assert_eq!(Conn::NOT_TLS, BitMask::bit(1).unwrap());
+    assert_eq!(Conn::ODD_PORT, BitMask::bit(2).unwrap());
+    assert_eq!(Conn::LOW_PORT.ones().collect::<Vec<_>>(), [3, 4]);
+    assert_eq!(Conn::LATENCY_SLOW, BitMask::bit(5).unwrap());
+    assert_eq!(Conn::LATENCY_STALLED, BitMask::bit(6).unwrap());
+}
+
+#[test]
+fn table_describes_every_bit() {
+    let pack = &Conn::BITSPEC_PACK;
+    assert_eq!(pack.bits.len(), usize::from(pack.bit_count));
+    for (i, info) in pack.bits.iter().enumerate() {
+        assert_eq!(usize::from(info.bit), i);
+    }
+
+    assert_eq!(
+        pack.info(1),
+        Some(&BitInfo {
+            bit: 1,
+            rule: "NOT_TLS",
+            level: None,
+            field: "proto",
+            op: r#"not(eq("tls"))"#,
+        })
+    );
+    assert_eq!(pack.info(3).unwrap().op, "between(-1, 1023.5)");
+    assert_eq!(pack.info(6).unwrap().level, Some("STALLED"));
+    assert_eq!(pack.info(7), None);
+}
+
+#[test]
+fn explain_lists_matched_rules() {
+    let row = Row(vec![
+        ("bytes", SlotValue::F64(4096.0)),
+        ("proto", SlotValue::Str("tls")),
+        ("port", SlotValue::U64(443)),
+        ("src_port", SlotValue::F64(50000.0)),
+        ("latency_ms", SlotValue::F64(250.0)),
+    ]);
+    let (mask, _) = Conn::BITSPEC_PACK.eval(&row);
+
+    assert!(mask.contains_all(&(Conn::BIG | Conn::LATENCY_SLOW)));
+    assert!(!mask.intersects(&Conn::NOT_TLS));
+    assert_eq!(
+        Conn::BITSPEC_PACK.explain(&mask),
+        [
+            "BIG: bytes gt(1000)",
+            "odd-port: port mod_eq(2, 1)",
+            "LOW_PORT: port between(-1, 1023.5)",
+            "Latency.SLOW: latency_ms gte(100)",
+        ]
+    );
+    assert!(Conn::BITSPEC_PACK.explain(&BitMask::EMPTY).is_empty());
+}
+
+#[test]
+fn rules_refer_to_bits_by_name() {
+    let rules = specs! {
+        => PASS @prio 0;
+        ANY(Conn::NOT_TLS) => RouteC;
+        ALL(Conn::BIG) & ANY(Conn::LATENCY_SLOW, Conn::LATENCY_STALLED) & NONE(Conn::NOT_TLS)
+            => Quarantine("slow bulk transfer") @prio 200;
+    };
+    assert_eq!(
+        rules.iter().map(|r| r.priority).collect::<Vec<_>>(),
+        [200, 100, 0]
+    );
+    assert_eq!(rules[0].all, Conn::BIG);
+    assert_eq!(rules[0].any, Conn::LATENCY_SLOW | Conn::LATENCY_STALLED);
+    assert_eq!(rules[0].none, Conn::NOT_TLS);
+
+    let slow_tls = Row(vec![
+        ("bytes", SlotValue::F64(4096.0)),
+        ("proto", SlotValue::Str("tls")),
+        ("latency_ms", SlotValue::F64(1500.0)),
+    ]);
+    let (mask, _) = Conn::BITSPEC_PACK.eval(&slow_tls);
+    assert!(matches!(
+        rules::eval(mask, &rules),
+        Action::Quarantine("slow bulk transfer")
+    ));
+
+    let plain = Row(vec![("proto", SlotValue::Str("http"))]);
+    let (mask, _) = Conn::BITSPEC_PACK.eval(&plain);
+    assert!(matches!(rules::eval(mask, &rules), Action::RouteC));
+}
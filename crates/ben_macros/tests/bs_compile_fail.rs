@@ -0,0 +1,5 @@
+#[test]
+fn bad_bspec_attributes_fail_to_compile() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/bspec_*.rs");
+}
@@ -0,0 +1,168 @@
+use ben_macros::Bitspec;
+use ben_wire::{
+    schema::{Field, FieldType, Schema},
+    slot::SlotValue,
+};
+use bitspec_engine::plan::BindError;
+
+mod common;
+use common::{Row, fired};
+
+#[allow(dead_code)]
+#[derive(Debug, Bitspec)]
+struct Flow {
+    #[bspec(brule(rule = "EXFIL", op = ratio(bytes_out, bytes_in) > 10))]
+    #[bspec(brule(rule = "OUT_GT_IN", op = bytes_out > bytes_in))]
+    #[bspec(brule(rule = "SMALL_GAP", op = (bytes_out - bytes_in) * 2 <= 100))]
+    bytes_out: u64,
+
+    bytes_in: u64,
+
+    #[bspec(brule(rule = "DRIFT", op = abs(delta) >= 5))]
+    delta: f64,
+
+    #[bspec(brule(rule = "NULL_NOTE", op = is_null()))]
+    #[bspec(brule(rule = "NO_NOTE", op = is_missing()))]
+    #[bspec(brule(rule = "HAS_NOTE", op = not(is_missing())))]
+    #[bspec(brule(rule = "QUIET", op = any(is_missing(), eq(""))))]
+    note: String,
+}
+
+const FIELDS: [&str; 4] = ["bytes_out", "bytes_in", "delta", "note"];
+
+fn schema() -> Schema {
+    let field = |name: &str, ty| Field {
+        name: name.to_string(),
+        ty,
+        nullable: true,
+    };
+    Schema {
+        event: "flow".to_string(),
+        version: 1,
+        evt_hash: [0; 32],
+        fields: vec![
+            field("bytes_out", FieldType::UInt64),
+            field("bytes_in", FieldType::UInt64),
+            field("delta", FieldType::Float64),
+            field("note", FieldType::String),
+        ],
+    }
+}
+
+/// Rules fired by name, checked against the bound plan.
+fn fired_both(row: &[SlotValue<'static>]) -> Vec<&'static str> {
+    let by_name = fired(&Flow::BITSPEC_PACK, &Row::zip(&FIELDS, row));
+    let plan = Flow::BITSPEC_PACK.bind(&schema()).unwrap();
+    let (mask, _) = plan.eval(row);
+    let bound: Vec<_> = Flow::BITSPEC_PACK.matched(&mask).map(|b| b.rule).collect();
+    assert_eq!(bound, by_name, "bound plan disagrees on {row:?}");
+    by_name
+}
+
+#[test]
+fn fields_relate_within_a_row() {
+    let row = [
+        SlotValue::U64(5000),
+        SlotValue::U64(100),
+        SlotValue::F64(-7.5),
+        SlotValue::Str("bulk"),
+    ];
+    assert_eq!(
+        fired_both(&row),
+        ["EXFIL", "OUT_GT_IN", "DRIFT", "HAS_NOTE"]
+    );
+
+    let row = [
+        SlotValue::U64(130),
+        SlotValue::U64(100),
+        SlotValue::F64(4.0),
+        SlotValue::Str(""),
+    ];
+    assert_eq!(
+        fired_both(&row),
+        ["OUT_GT_IN", "SMALL_GAP", "HAS_NOTE", "QUIET"]
+    );
+}
+
+#[test]
+fn zero_divisors_and_missing_operands_fire_nothing() {
+    let row = [
+        SlotValue::U64(5000),
+        SlotValue::U64(0),
+        SlotValue::Missing,
+        SlotValue::Str("x"),
+    ];
+    assert_eq!(fired_both(&row), ["OUT_GT_IN", "HAS_NOTE"]);
+
+    let row = [
+        SlotValue::U64(5000),
+        SlotValue::Missing,
+        SlotValue::F64(0.0),
+        SlotValue::Str("x"),
+    ];
+    assert_eq!(fired_both(&row), ["HAS_NOTE"]);
+}
+
+#[test]
+fn null_and_missing_checks() {
+    let row = [
+        SlotValue::U64(1),
+        SlotValue::U64(1),
+        SlotValue::F64(0.0),
+        SlotValue::Missing,
+    ];
+    assert_eq!(
+        fired_both(&row),
+        ["SMALL_GAP", "NULL_NOTE", "NO_NOTE", "QUIET"]
+    );
+
+    // Absent from the row altogether: missing, but not null.
+    assert_eq!(
+        fired(&Flow::BITSPEC_PACK, &Row::zip(&FIELDS, &row[..3])),
+        ["SMALL_GAP", "NO_NOTE", "QUIET"]
+    );
+}
+
+#[test]
+fn ops_are_described_as_written() {
+    assert_eq!(
+        Flow::BITSPEC_PACK.explain(&Flow::EXFIL),
+        ["EXFIL: bytes_out ratio(bytes_out, bytes_in) > 10"]
+    );
+    assert_eq!(
+        Flow::BITSPEC_PACK.info(2).unwrap().op,
+        "(bytes_out - bytes_in) * 2 <= 100"
+    );
+    assert_eq!(Flow::BITSPEC_PACK.info(3).unwrap().op, "abs(delta) >= 5");
+}
+
+#[test]
+fn read_fields_must_be_in_the_schema() {
+    let mut schema = schema();
+    schema.fields.remove(1);
+    let err = Flow::BITSPEC_PACK.bind(&schema).unwrap_err();
+    assert_eq!(
+        err,
+        BindError::UnknownFields {
+            event: "flow".into(),
+            fields: vec!["bytes_in"],
+        }
+    );
+}
+
+#[test]
+fn bound_reads_follow_the_schema_order() {
+    let mut schema = schema();
+    schema.fields.reverse();
+    let plan = Flow::BITSPEC_PACK.bind(&schema).unwrap();
+
+    let row = [
+        SlotValue::Str("bulk"),
+        SlotValue::F64(-7.5),
+        SlotValue::U64(100),
+        SlotValue::U64(5000),
+    ];
+    let (mask, _) = plan.eval(&row);
+    let names: Vec<_> = Flow::BITSPEC_PACK.matched(&mask).map(|b| b.rule).collect();
+    assert_eq!(names, ["EXFIL", "OUT_GT_IN", "DRIFT", "HAS_NOTE"]);
+}
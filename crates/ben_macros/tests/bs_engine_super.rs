@@ -1,11 +1,8 @@
 //! Full-system test: bitspec macro + engine evaluation.
 
 use ben_macros::Bitspec;
-use ben_wire::slot::SlotValue;
-use bitspec_engine::{
-    FactValue, RowAccess, pack::BitspecPack, predicate::PredicateSpec, threshold::ThresholdLevel,
-    threshold::ThresholdOp, threshold::ThresholdSpec,
-};
+use ben_wire::slot::{Coercion, SlotValue};
+use bitspec_engine::{FactValue, RowAccess, pack::BitspecPack};
 //
 // ---------------------------------------------------------
 // Local test Row implementation
@@ -35,16 +32,16 @@ impl RowAccess for TestRow {
         self.map.get(key)?.as_str().ok()
     }
     fn get_f64(&self, key: &str) -> Option<f64> {
-        self.map.get(key)?.as_f64().ok().copied()
+        self.map.get(key)?.as_f64().ok()
     }
     fn get_u64(&self, key: &str) -> Option<u64> {
-        self.map.get(key)?.as_u64().ok().copied()
+        self.map.get(key)?.as_u64().ok()
     }
     fn get_i64(&self, key: &str) -> Option<i64> {
-        self.map.get(key)?.as_i64().ok().copied()
+        self.map.get(key)?.as_i64().ok()
     }
     fn get_bool(&self, key: &str) -> Option<bool> {
-        self.map.get(key)?.as_bool().ok().copied()
+        self.map.get(key)?.as_bool().ok()
     }
 
     fn get_slot(&self, field_id: &str) -> Option<&SlotValue> {
@@ -142,7 +139,7 @@ fn test_bitspec_macro_and_engine() {
     let (mask, facts) = pack.eval(&row);
 
     // Print the mask for debugging
-    println!("mask bits: {:064b}", mask);
+    println!("mask bits: {mask:?}");
 
     //
     // ---------------------------------------------------------
@@ -158,10 +155,10 @@ fn test_bitspec_macro_and_engine() {
     //
     // Threshold levels begin at Bit 4.
 
-    assert!(mask & (1 << 0) != 0, "NUM_GT_10 should fire");
-    assert!(mask & (1 << 1) != 0, "TXT_START_HE should fire");
-    assert!(mask & (1 << 2) != 0, "FLAG_TRUE should fire");
-    assert!(mask & (1 << 3) != 0, "COMPLEX_ALL should fire");
+    assert!(mask.contains(0), "NUM_GT_10 should fire");
+    assert!(mask.contains(1), "TXT_START_HE should fire");
+    assert!(mask.contains(2), "FLAG_TRUE should fire");
+    assert!(mask.contains(3), "COMPLEX_ALL should fire");
 
     //
     // ---------------------------------------------------------
@@ -175,13 +172,13 @@ fn test_bitspec_macro_and_engine() {
     //
 
     // heat = 350 ≥ WARM (100)
-    assert!(mask & (1 << 4) != 0, "WARM threshold should fire");
+    assert!(mask.contains(4), "WARM threshold should fire");
 
     // heat = 350 ≥ HOT (200)
-    assert!(mask & (1 << 5) != 0, "HOT threshold should fire");
+    assert!(mask.contains(5), "HOT threshold should fire");
 
     // heat = 350 < SCORCH (500)
-    assert!(mask & (1 << 6) == 0, "SCORCH threshold should NOT fire");
+    assert!(!mask.contains(6), "SCORCH threshold should NOT fire");
 
     //
     // ---------------------------------------------------------
@@ -233,9 +230,42 @@ fn test_bitspec_macro_and_engine() {
 
     let (mask2, facts2) = pack.eval(&row_missing);
 
-    assert_eq!(mask2 & (1 << 0), 0, "num=3 should NOT fire NUM_GT_10");
+    assert!(!mask2.contains(0), "num=3 should NOT fire NUM_GT_10");
     assert!(
         facts2.is_empty(),
         "no facts should be recorded when heat missing"
     );
 }
+
+#[test]
+fn integer_and_text_slots_feed_float_rules() {
+    let pack: &BitspecPack = &ExampleEvent::BITSPEC_PACK;
+
+    // integers widen losslessly in the default strict mode
+    let row = TestRow::new()
+        .with("num", SlotValue::U64(42))
+        .with("heat", SlotValue::I32(250));
+    let (mask, facts) = pack.eval(&row);
+    assert!(mask.contains(0), "NUM_GT_10 should fire on a U64");
+    assert!(mask.contains(5), "HOT should fire on an I32");
+    assert!(facts.contains_key("heat.level"));
+
+    // numbers in strings need a lenient row
+    let strict = TestRow::new().with("num", SlotValue::Str("42"));
+    assert!(!pack.eval(&strict).0.contains(0));
+
+    let lenient = LenientRow(strict);
+    assert!(pack.eval(&lenient).0.contains(0));
+}
+
+struct LenientRow(TestRow);
+
+impl RowAccess for LenientRow {
+    fn get_slot(&self, field_id: &str) -> Option<&SlotValue> {
+        self.0.get_slot(field_id)
+    }
+
+    fn coercion(&self) -> Coercion {
+        Coercion::Lenient
+    }
+}
@@ -0,0 +1,198 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use ben_macros::Bitspec;
+use ben_wire::slot::SlotValue;
+use bitspec_engine::predicate::{Cidr, PredOp};
+
+mod common;
+use common::{Row, fired};
+
+#[allow(dead_code)]
+#[derive(Debug, Bitspec)]
+struct Login {
+    #[bspec(brule(rule = "CORP_HOST", op = ends_with(".corp.example")))]
+    #[bspec(brule(rule = "PROD_DB", op = glob("db-*.prod-?")))]
+    #[bspec(brule(rule = "NUMBERED", op = matches(r"^[a-z]+-\d{2}\b")))]
+    host: String,
+
+    #[bspec(brule(rule = "SCRIPTED", op = in_set(["wget", "curl", "python-requests", "curl"])))]
+    agent: String,
+
+    #[bspec(brule(rule = "ADMIN_PORT", op = in_set([3389, 22, -1, 22])))]
+    port: u16,
+
+    #[bspec(brule(rule = "INTERNAL", op = in_cidr("10.0.0.0/8", "192.168.0.0/16")))]
+    #[bspec(brule(rule = "ULA", op = in_cidr("fd00::/8")))]
+    src_ip: Ipv4Addr,
+}
+
+fn v4(s: &str) -> SlotValue<'static> {
+    SlotValue::IPv4(s.parse::<Ipv4Addr>().unwrap().into())
+}
+
+fn v6(s: &str) -> SlotValue<'static> {
+    SlotValue::IPv6(s.parse::<Ipv6Addr>().unwrap().into())
+}
+
+#[test]
+fn string_ops() {
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("host", SlotValue::Str("web-01.corp.example"))])
+        ),
+        ["CORP_HOST", "NUMBERED"]
+    );
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("host", SlotValue::Str("db-orders.prod-3"))])
+        ),
+        ["PROD_DB"]
+    );
+    assert!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("host", SlotValue::Str("db-orders.prod-12"))])
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn set_membership() {
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![
+                ("agent", SlotValue::Str("curl")),
+                ("port", SlotValue::U16(3389)),
+            ])
+        ),
+        ["SCRIPTED", "ADMIN_PORT"]
+    );
+    assert!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![
+                ("agent", SlotValue::Str("Mozilla/5.0")),
+                ("port", SlotValue::U16(443)),
+            ])
+        )
+        .is_empty()
+    );
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("port", SlotValue::I64(-1))])
+        ),
+        ["ADMIN_PORT"]
+    );
+}
+
+#[test]
+fn cidr_membership() {
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", v4("10.200.1.9"))])
+        ),
+        ["INTERNAL"]
+    );
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", v4("192.168.44.1"))])
+        ),
+        ["INTERNAL"]
+    );
+    assert!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", v4("192.169.0.1"))])
+        )
+        .is_empty()
+    );
+    assert!(fired(&Login::BITSPEC_PACK, &Row(vec![("src_ip", v4("11.0.0.1"))])).is_empty());
+
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", v6("::ffff:10.0.0.1"))])
+        ),
+        ["INTERNAL"]
+    );
+    assert_eq!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", v6("fd12:3456::1"))])
+        ),
+        ["ULA"]
+    );
+    assert!(fired(&Login::BITSPEC_PACK, &Row(vec![("src_ip", v6("fe80::1"))])).is_empty());
+    assert!(
+        fired(
+            &Login::BITSPEC_PACK,
+            &Row(vec![("src_ip", SlotValue::Str("10.0.0.1"))])
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn ops_are_normalized_at_compile_time() {
+    let ops: Vec<_> = Login::BITSPEC_PACK
+        .predicates
+        .iter()
+        .map(|p| &p.op)
+        .collect();
+    assert!(matches!(
+        ops[3],
+        PredOp::InStrSet(["curl", "python-requests", "wget"])
+    ));
+    assert!(matches!(ops[4], PredOp::InIntSet([-1, 22, 3389])));
+
+    let PredOp::InCidr(nets) = ops[5] else {
+        panic!("{:?}", ops[5]);
+    };
+    assert_eq!(
+        nets[0],
+        Cidr {
+            net: u128::from(Ipv4Addr::new(10, 0, 0, 0).to_ipv6_mapped()),
+            prefix: 104,
+        }
+    );
+    assert_eq!(
+        Login::BITSPEC_PACK.info(3).unwrap().op,
+        r#"in_set(["wget", "curl", "python-requests", "curl"])"#
+    );
+}
+
+#[test]
+fn glob_wildcards() {
+    let glob = |pat: &'static str, s: &'static str| PredOp::Glob(pat).eval(&SlotValue::Str(s));
+    assert!(glob("*", ""));
+    assert!(glob("a*b*c", "a-x-b-y-c"));
+    assert!(glob("a*c", "abcbc"));
+    assert!(glob("?é?", "xéy"));
+    assert!(glob("a?c", "aéc"));
+    assert!(glob("*.é*", "x.y.éz"));
+    assert!(glob("*.log", "ß.log"));
+    assert!(!glob("a*c", "abcb"));
+    assert!(!glob("a?", "a"));
+    assert!(!glob("abc", "abcd"));
+    assert!(!glob("a?c", "aééc"));
+}
+
+#[test]
+fn hand_built_patterns_do_not_panic() {
+    use bitspec_engine::predicate::Pattern;
+
+    assert!(Pattern::try_new("(unclosed").is_err());
+    let ok = Pattern::try_new(r"^db-\d+$").unwrap();
+    assert!(ok.is_match("db-12") && !ok.is_match("db-x"));
+
+    static BROKEN: Pattern = Pattern::new("(unclosed");
+    assert!(BROKEN.check().is_err() && ok.check().is_ok());
+    assert!(!PredOp::Matches(&BROKEN).eval(&SlotValue::Str("(unclosed")));
+}
@@ -0,0 +1,222 @@
+use ben_macros::Bitspec;
+use ben_wire::{
+    schema::{Field, FieldType, Schema},
+    slot::{Coercion, SlotValue},
+};
+use bitspec_engine::{BitMask, FactValue, RowAccess, plan::BindError};
+
+#[allow(dead_code)]
+#[derive(Debug, Bitspec)]
+struct Conn {
+    #[bspec(brule(rule = "BIG", op = gt(1000)))]
+    bytes: f64,
+
+    #[bspec(brule(rule = "NOT_TLS", op = not(eq("tls"))))]
+    proto: &'static str,
+
+    #[bspec(thresholds(
+        rule = "LATENCY",
+        op = "gte",
+        values = "SLOW=100, STALLED=1000",
+        fact = "latency.level",
+        flags = "PAGE:STALLED"
+    ))]
+    latency_ms: f64,
+}
+
+fn field(name: &str, ty: FieldType) -> Field {
+    Field {
+        name: name.to_string(),
+        ty,
+        nullable: true,
+    }
+}
+
+/// Same fields in a different order, plus one the pack ignores.
+fn schema() -> Schema {
+    Schema {
+        event: "conn".to_string(),
+        version: 1,
+        evt_hash: [0; 32],
+        fields: vec![
+            field("latency_ms", FieldType::UInt32),
+            field("host", FieldType::String),
+            field("proto", FieldType::String),
+            field("bytes", FieldType::UInt64),
+        ],
+    }
+}
+
+struct NamedRow<'r>(&'r Schema, &'r [SlotValue<'r>]);
+
+impl RowAccess for NamedRow<'_> {
+    fn get_slot(&self, field_id: &str) -> Option<&SlotValue<'_>> {
+        self.1.get(self.0.index_of(field_id)?)
+    }
+}
+
+#[test]
+fn bound_plan_matches_name_lookup() {
+    let schema = schema();
+    let plan = Conn::BITSPEC_PACK.bind(&schema).unwrap();
+    assert_eq!(plan.field_count(), 4);
+
+    let rows = [
+        [
+            SlotValue::U32(1500),
+            SlotValue::Str("db1"),
+            SlotValue::Str("tcp"),
+            SlotValue::U64(4096),
+        ],
+        [
+            SlotValue::U32(120),
+            SlotValue::Str("db2"),
+            SlotValue::Str("tls"),
+            SlotValue::U64(10),
+        ],
+    ];
+    for row in &rows {
+        let (mask, facts) = plan.eval(row);
+        let (by_name, by_name_facts) = Conn::BITSPEC_PACK.eval(&NamedRow(&schema, row));
+        assert_eq!(mask, by_name);
+        assert_eq!(facts.len(), by_name_facts.len());
+    }
+
+    let (mask, facts) = plan.eval(&rows[0]);
+    assert_eq!(mask, BitMask::from_words([0b1111, 0]));
+    assert!(matches!(&facts["latency.level"], FactValue::Str(s) if s == "STALLED"));
+    assert!(matches!(&facts["PAGE"], FactValue::Str(s) if s == "STALLED"));
+
+    let (mask, _) = plan.eval(&rows[1]);
+    assert_eq!(mask, BitMask::bit(2).unwrap());
+}
+
+#[test]
+fn missing_slots_fire_nothing() {
+    let plan = Conn::BITSPEC_PACK.bind(&schema()).unwrap();
+    let row = [SlotValue::Missing; 4];
+    let (mask, facts) = plan.eval(&row);
+    assert!(mask.is_empty(), "not(...) must not fire on a missing field");
+    assert!(facts.is_empty());
+    assert!(
+        Conn::BITSPEC_PACK
+            .eval(&NamedRow(&schema(), &row))
+            .0
+            .is_empty()
+    );
+}
+
+#[test]
+fn coercion_applies_to_bound_rows() {
+    let plan = Conn::BITSPEC_PACK.bind(&schema()).unwrap();
+    let row = [
+        SlotValue::Str("2000"),
+        SlotValue::Missing,
+        SlotValue::Missing,
+        SlotValue::Missing,
+    ];
+    assert!(plan.eval(&row).0.is_empty());
+
+    let plan = plan.with_coercion(Coercion::Lenient);
+    assert_eq!(plan.eval(&row).0, BitMask::from_bits([2, 3]).unwrap());
+}
+
+#[test]
+fn unknown_fields_are_reported_at_bind_time() {
+    let mut schema = schema();
+    schema
+        .fields
+        .retain(|f| f.name == "host" || f.name == "proto");
+
+    let err = Conn::BITSPEC_PACK.bind(&schema).unwrap_err();
+    assert_eq!(
+        err,
+        BindError::UnknownFields {
+            event: "conn".into(),
+            fields: vec!["bytes", "latency_ms"],
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "bitspec fields not in schema `conn`: bytes, latency_ms"
+    );
+}
+
+#[test]
+fn hand_built_pack_with_a_bit_past_the_width_does_not_bind() {
+    use bitspec_engine::{
+        pack::BitspecPack,
+        predicate::{PredOp, PredicateSpec},
+    };
+
+    static PREDS: [PredicateSpec<'static>; 2] = [
+        PredicateSpec {
+            field_id: "host",
+            bit: 0,
+            op: PredOp::IsNull,
+        },
+        PredicateSpec {
+            field_id: "proto",
+            bit: 128,
+            op: PredOp::IsNull,
+        },
+    ];
+    let pack = BitspecPack::<2> {
+        predicates: &PREDS,
+        thresholds: &[],
+        bit_count: 129,
+        bits: &[],
+    };
+
+    let schema = schema();
+    assert_eq!(
+        pack.bind(&schema).unwrap_err(),
+        BindError::BitOutOfRange {
+            rule: "proto",
+            bit: 128,
+            width: 128,
+        }
+    );
+
+    // Evaluating by name skips the bit instead of panicking.
+    let row = [SlotValue::Missing; 4];
+    let (mask, _) = pack.eval(&NamedRow(&schema, &row));
+    assert_eq!(mask, BitMask::bit(0).unwrap());
+}
+
+#[test]
+fn hand_built_pack_with_a_bad_regex_does_not_bind() {
+    use bitspec_engine::{
+        pack::BitspecPack,
+        predicate::{Pattern, PredOp, PredicateSpec},
+    };
+
+    static HOST: Pattern = Pattern::new(r"^db-\d+$");
+    static BROKEN: Pattern = Pattern::new("(unclosed");
+    static NESTED: [PredOp<'static>; 2] = [PredOp::Matches(&HOST), PredOp::Matches(&BROKEN)];
+    static PREDS: [PredicateSpec<'static>; 2] = [
+        PredicateSpec {
+            field_id: "host",
+            bit: 0,
+            op: PredOp::Matches(&HOST),
+        },
+        PredicateSpec {
+            field_id: "proto",
+            bit: 1,
+            op: PredOp::Not(&PredOp::Any(&NESTED)),
+        },
+    ];
+    let pack = BitspecPack::<2> {
+        predicates: &PREDS,
+        thresholds: &[],
+        bit_count: 2,
+        bits: &[],
+    };
+
+    match pack.bind(&schema()).unwrap_err() {
+        BindError::BadPattern { rule, pattern, .. } => {
+            assert_eq!((rule, pattern), ("proto", "(unclosed"));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+}
@@ -0,0 +1,49 @@
+use ben_macros::Bitspec;
+use ben_wire::slot::SlotValue;
+use bitspec_engine::{BitMask, RowAccess};
+
+struct OneRow(SlotValue<'static>);
+
+impl RowAccess for OneRow {
+    fn get_slot(&self, _field_id: &str) -> Option<&SlotValue<'_>> {
+        Some(&self.0)
+    }
+}
+
+/// 151 bits: one brule, then 150 threshold levels reaching into word 2.
+#[allow(dead_code)]
+#[derive(Bitspec)]
+#[bspec(width = 192)]
+struct Wide {
+    #[bspec(brule(rule = "NEG", op = lt(0)))]
+    #[bspec(thresholds(
+        rule = "LVL",
+        op = "gte",
+        values = "L0=0, L1=1, L2=2, L3=3, L4=4, L5=5, L6=6, L7=7, L8=8, L9=9, L10=10, L11=11, L12=12, L13=13, L14=14, L15=15, L16=16, L17=17, L18=18, L19=19, L20=20, L21=21, L22=22, L23=23, L24=24, L25=25, L26=26, L27=27, L28=28, L29=29, L30=30, L31=31, L32=32, L33=33, L34=34, L35=35, L36=36, L37=37, L38=38, L39=39, L40=40, L41=41, L42=42, L43=43, L44=44, L45=45, L46=46, L47=47, L48=48, L49=49, L50=50, L51=51, L52=52, L53=53, L54=54, L55=55, L56=56, L57=57, L58=58, L59=59, L60=60, L61=61, L62=62, L63=63, L64=64, L65=65, L66=66, L67=67, L68=68, L69=69, L70=70, L71=71, L72=72, L73=73, L74=74, L75=75, L76=76, L77=77, L78=78, L79=79, L80=80, L81=81, L82=82, L83=83, L84=84, L85=85, L86=86, L87=87, L88=88, L89=89, L90=90, L91=91, L92=92, L93=93, L94=94, L95=95, L96=96, L97=97, L98=98, L99=99, L100=100, L101=101, L102=102, L103=103, L104=104, L105=105, L106=106, L107=107, L108=108, L109=109, L110=110, L111=111, L112=112, L113=113, L114=114, L115=115, L116=116, L117=117, L118=118, L119=119, L120=120, L121=121, L122=122, L123=123, L124=124, L125=125, L126=126, L127=127, L128=128, L129=129, L130=130, L131=131, L132=132, L133=133, L134=134, L135=135, L136=136, L137=137, L138=138, L139=139, L140=140, L141=141, L142=142, L143=143, L144=144, L145=145, L146=146, L147=147, L148=148, L149=149"
+    ))]
+    score: f64,
+}
+
+#[test]
+fn wide_packs_use_every_word() {
+    assert_eq!(Wide::BITSPEC_PACK.bit_count, 151);
+    assert_eq!(BitMask::<3>::WIDTH, 192);
+
+    let (mask, _) = Wide::BITSPEC_PACK.eval(&OneRow(SlotValue::F64(149.0)));
+    assert_eq!(mask.count_ones(), 150);
+    assert!(!mask.contains(0));
+    assert!(mask.contains(1) && mask.contains(64) && mask.contains(150));
+    assert!(!mask.contains(151));
+
+    let (mask, _) = Wide::BITSPEC_PACK.eval(&OneRow(SlotValue::F64(-1.0)));
+    assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn levels_past_bit_63_do_not_alias() {
+    let (mask, _) = Wide::BITSPEC_PACK.eval(&OneRow(SlotValue::F64(63.0)));
+    assert!(mask.contains(64));
+    assert!(!mask.contains(65));
+    assert_eq!(mask.words()[1], 1);
+    assert_eq!(mask.words()[2], 0);
+}
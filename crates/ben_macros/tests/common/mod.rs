@@ -0,0 +1,27 @@
+//! Helpers shared by the `Bitspec` derive tests.
+#![allow(dead_code)]
+
+use ben_wire::slot::SlotValue;
+use bitspec_engine::{RowAccess, pack::BitspecPack};
+
+/// A row looked up by field name; fields not listed are absent.
+pub struct Row(pub Vec<(&'static str, SlotValue<'static>)>);
+
+impl Row {
+    /// Pairs `values` with `fields` in order; extra fields are left out.
+    pub fn zip(fields: &[&'static str], values: &[SlotValue<'static>]) -> Self {
+        Row(fields.iter().copied().zip(values.iter().copied()).collect())
+    }
+}
+
+impl RowAccess for Row {
+    fn get_slot(&self, field_id: &str) -> Option<&SlotValue<'_>> {
+        self.0.iter().find(|(k, _)| *k == field_id).map(|(_, v)| v)
+    }
+}
+
+/// Names of the rules `pack` sets on `row`, in bit order.
+pub fn fired<const WORDS: usize>(pack: &BitspecPack<WORDS>, row: &Row) -> Vec<&'static str> {
+    let (mask, _) = pack.eval(row);
+    pack.matched(&mask).map(|b| b.rule).collect()
+}
@@ -0,0 +1,131 @@
+use ben_contracts::registry::{RegistryError, SchemaRegistry};
+use ben_macros::BenSchema;
+use ben_wire::{
+    envelope::{Encoding, Envelope, EnvelopeFlags},
+    rowpack::encode_rowpack,
+    schema::FieldType,
+    slot::SlotValue,
+};
+
+#[derive(Debug, BenSchema)]
+#[bschema(table = "registry_event", version = 2, order_by = "id")]
+struct RegistryEvent {
+    #[bschema(key)]
+    id: u64,
+    host: String,
+    note: Option<String>,
+    ports: Vec<i64>,
+}
+
+fn frame(evt_hash: [u8; 32], encoding: Encoding, row: &[SlotValue]) -> Vec<u8> {
+    let mut raw = Vec::new();
+    encode_rowpack(row, &mut raw).unwrap();
+    let mut buf = Vec::new();
+    Envelope::write_frame(
+        &mut buf,
+        evt_hash,
+        7,
+        encoding,
+        row.len() as u16,
+        EnvelopeFlags::empty(),
+        &raw,
+    )
+    .unwrap();
+    buf
+}
+
+#[test]
+fn derived_schemas_are_linked() {
+    let reg = SchemaRegistry::linked().unwrap();
+    let schema = reg.get(&RegistryEvent::__BEN_SCHEMA_EVT_HASH).unwrap();
+
+    assert_eq!(schema.event, "registry_event");
+    assert_eq!(schema.version, 2);
+    assert_eq!(schema.field_names(), ["id", "host", "note", "ports"]);
+    assert_eq!(schema.fields[1].ty, FieldType::String);
+    assert!(schema.fields[2].nullable);
+    assert_eq!(
+        schema.fields[3].ty,
+        FieldType::Array(Box::new(FieldType::Int64))
+    );
+}
+
+#[test]
+fn manifest_json_matches_linked_schema() {
+    let mut loaded = SchemaRegistry::new();
+    let hash = loaded
+        .load_manifest_json(RegistryEvent::__BEN_SCHEMA_JSON)
+        .unwrap();
+    assert_eq!(hash, RegistryEvent::__BEN_SCHEMA_EVT_HASH);
+
+    let linked = SchemaRegistry::linked().unwrap();
+    assert_eq!(loaded.get(&hash), linked.get(&hash));
+
+    // Loading the same manifest twice is idempotent.
+    loaded
+        .load_manifest_json(RegistryEvent::__BEN_SCHEMA_JSON)
+        .unwrap();
+    assert_eq!(loaded.len(), 1);
+}
+
+#[test]
+fn conflicting_schema_for_same_hash_is_rejected() {
+    let mut reg = SchemaRegistry::linked().unwrap();
+    let mut other = reg
+        .get(&RegistryEvent::__BEN_SCHEMA_EVT_HASH)
+        .unwrap()
+        .clone();
+    other.fields.pop();
+
+    let err = reg.insert(other).unwrap_err();
+    assert!(matches!(err, RegistryError::Conflict { .. }));
+}
+
+#[test]
+fn envelope_decodes_by_hash() {
+    let reg = SchemaRegistry::linked().unwrap();
+    let row = [
+        SlotValue::U64(9),
+        SlotValue::Str("edge-1"),
+        SlotValue::Missing,
+        SlotValue::Missing,
+    ];
+    let buf = frame(
+        RegistryEvent::__BEN_SCHEMA_EVT_HASH,
+        Encoding::Rowpack,
+        &row,
+    );
+    let env = Envelope::parse(&buf).unwrap();
+
+    let (schema, slots) = reg.decode(&env).unwrap();
+    assert_eq!(schema.event, "registry_event");
+    assert!(matches!(
+        slots[schema.index_of("host").unwrap()],
+        SlotValue::Str("edge-1")
+    ));
+}
+
+#[test]
+fn decode_rejects_unknown_hash_encoding_and_field_count() {
+    let reg = SchemaRegistry::linked().unwrap();
+    let hash = RegistryEvent::__BEN_SCHEMA_EVT_HASH;
+
+    let buf = frame([0xEE; 32], Encoding::Rowpack, &[SlotValue::U64(1)]);
+    let err = reg.decode(&Envelope::parse(&buf).unwrap()).unwrap_err();
+    assert!(matches!(err, RegistryError::UnknownHash(_)));
+
+    let buf = frame(hash, Encoding::RowBinary, &[SlotValue::U64(1)]);
+    let err = reg.decode(&Envelope::parse(&buf).unwrap()).unwrap_err();
+    assert!(matches!(err, RegistryError::Encoding(Encoding::RowBinary)));
+
+    let buf = frame(hash, Encoding::Rowpack, &[SlotValue::U64(1)]);
+    let err = reg.decode(&Envelope::parse(&buf).unwrap()).unwrap_err();
+    assert!(matches!(
+        err,
+        RegistryError::FieldCount {
+            expected: 4,
+            got: 1,
+            ..
+        }
+    ));
+}
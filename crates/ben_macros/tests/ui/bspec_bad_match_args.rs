@@ -0,0 +1,27 @@
+use ben_macros::Bitspec;
+
+#[derive(Bitspec)]
+struct BadRegex {
+    #[bspec(brule(rule = "R", op = matches("(unclosed")))]
+    host: String,
+}
+
+#[derive(Bitspec)]
+struct HostBits {
+    #[bspec(brule(rule = "C", op = in_cidr("10.0.0.1/8")))]
+    ip: u32,
+}
+
+#[derive(Bitspec)]
+struct LongPrefix {
+    #[bspec(brule(rule = "C", op = in_cidr("10.0.0.0/33")))]
+    ip: u32,
+}
+
+#[derive(Bitspec)]
+struct MixedSet {
+    #[bspec(brule(rule = "S", op = in_set(["a", 1])))]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use ben_macros::Bitspec;
+
+#[derive(Bitspec)]
+struct Clash {
+    #[bspec(brule(rule = "bitspec pack", op = gt(0)))]
+    score: f64,
+}
+
+fn main() {}
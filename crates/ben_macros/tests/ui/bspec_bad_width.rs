@@ -0,0 +1,10 @@
+use ben_macros::Bitspec;
+
+#[derive(Bitspec)]
+#[bspec(width = 100)]
+struct Odd {
+    #[bspec(brule(rule = "NEG", op = lt(0)))]
+    score: f64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ben_macros::Bitspec;
+
+#[derive(Bitspec)]
+struct Flow {
+    #[bspec(brule(rule = "EXFIL", op = ratio(bytes_out, bytes_inn) > 10))]
+    bytes_out: u64,
+    bytes_in: u64,
+}
+
+fn main() {}
@@ -0,0 +1,241 @@
+use std::marker::PhantomData;
+
+use anyhow::{Result, bail, ensure};
+
+use crate::rowbinary::{EncodeQuic, RowBinCursor, RowBinaryDecode, RowBinaryDecodeRef};
+
+/// Header of a batch frame:
+///
+/// ```text
+/// evt_hash [32] | field_count u16 | row_count u32 | rows_len u32 | rows...
+/// ```
+///
+/// `rows_len` makes the frame self-delimiting, so batches can be read off a
+/// stream without an outer length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchHeader {
+    pub evt_hash: [u8; 32],
+    pub field_count: u16,
+    pub row_count: u32,
+    pub rows_len: u32,
+}
+
+impl BatchHeader {
+    pub const LEN: usize = 42;
+
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        ensure!(
+            buf.len() >= Self::LEN,
+            "batch header: need {} bytes, got {}",
+            Self::LEN,
+            buf.len()
+        );
+        Ok(Self {
+            evt_hash: buf[..32].try_into()?,
+            field_count: u16::from_le_bytes(buf[32..34].try_into()?),
+            row_count: u32::from_le_bytes(buf[34..38].try_into()?),
+            rows_len: u32::from_le_bytes(buf[38..42].try_into()?),
+        })
+    }
+
+    /// Header plus rows.
+    pub fn frame_len(&self) -> usize {
+        Self::LEN + self.rows_len as usize
+    }
+
+    fn write(&self, out: &mut [u8]) {
+        out[..32].copy_from_slice(&self.evt_hash);
+        out[32..34].copy_from_slice(&self.field_count.to_le_bytes());
+        out[34..38].copy_from_slice(&self.row_count.to_le_bytes());
+        out[38..42].copy_from_slice(&self.rows_len.to_le_bytes());
+    }
+}
+
+/// Result of [`BatchEncoder::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Push {
+    Added,
+    /// The row would exceed the budget and was not added. Ship the batch with
+    /// [`BatchEncoder::finish`] and push the row again.
+    Full,
+}
+
+/// Accumulates rows of one event type into a batch frame until a byte or row
+/// budget is reached.
+pub struct BatchEncoder<T> {
+    buf: Vec<u8>,
+    rows: u32,
+    max_bytes: usize,
+    max_rows: u32,
+    _row: PhantomData<fn(&T)>,
+}
+
+impl<T: EncodeQuic> BatchEncoder<T> {
+    /// `max_bytes` bounds the whole frame, header included. A single row
+    /// larger than the budget still goes out, alone in its batch.
+    pub fn new(max_bytes: usize, max_rows: u32) -> Self {
+        let mut buf = Vec::with_capacity(max_bytes.min(1 << 20));
+        buf.resize(BatchHeader::LEN, 0);
+        Self {
+            buf,
+            rows: 0,
+            max_bytes,
+            max_rows: max_rows.max(1),
+            _row: PhantomData,
+        }
+    }
+
+    pub fn push(&mut self, row: &T) -> Result<Push> {
+        if self.rows >= self.max_rows {
+            return Ok(Push::Full);
+        }
+
+        let mark = self.buf.len();
+        if let Err(e) = row.encode_rowbinary(&mut self.buf) {
+            self.buf.truncate(mark);
+            return Err(e);
+        }
+
+        if self.buf.len() > self.max_bytes && self.rows > 0 {
+            self.buf.truncate(mark);
+            return Ok(Push::Full);
+        }
+        if self.buf.len() - BatchHeader::LEN > u32::MAX as usize {
+            self.buf.truncate(mark);
+            bail!("batch rows exceed u32::MAX bytes");
+        }
+
+        self.rows += 1;
+        Ok(Push::Added)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Bytes the frame would take if finished now.
+    pub fn frame_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the finished frame and starts a new, empty batch.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let header = BatchHeader {
+            evt_hash: <T as EncodeQuic>::EVT_HASH,
+            field_count: <T as EncodeQuic>::FIELD_COUNT,
+            row_count: self.rows,
+            rows_len: (self.buf.len() - BatchHeader::LEN) as u32,
+        };
+        header.write(&mut self.buf);
+
+        let mut next = Vec::with_capacity(self.buf.capacity());
+        next.resize(BatchHeader::LEN, 0);
+        self.rows = 0;
+        std::mem::replace(&mut self.buf, next)
+    }
+}
+
+/// A parsed batch frame. Rows are decoded lazily by [`Batch::rows`].
+#[derive(Debug, Clone, Copy)]
+pub struct Batch<'a> {
+    pub header: BatchHeader,
+    pub rows: &'a [u8],
+}
+
+impl<'a> Batch<'a> {
+    pub fn parse(buf: &'a [u8]) -> Result<Self> {
+        let header = BatchHeader::parse(buf)?;
+        ensure!(
+            buf.len() >= header.frame_len(),
+            "short batch: need {} bytes, got {}",
+            header.frame_len(),
+            buf.len()
+        );
+        Ok(Self {
+            header,
+            rows: &buf[BatchHeader::LEN..header.frame_len()],
+        })
+    }
+
+    /// Iterates owned rows after checking the batch holds `T`.
+    pub fn rows<T: RowBinaryDecode>(&self) -> Result<BatchRows<'a, T>> {
+        self.check(T::EVT_HASH, T::FIELD_COUNT)?;
+        Ok(BatchRows::new(self, T::from_rowbinary))
+    }
+
+    /// Iterates rows borrowing from the frame, see [`RowBinaryDecodeRef`].
+    pub fn rows_ref<T: RowBinaryDecodeRef<'a>>(&self) -> Result<BatchRows<'a, T>> {
+        self.check(T::EVT_HASH, T::FIELD_COUNT)?;
+        Ok(BatchRows::new(self, T::from_rowbinary_ref))
+    }
+
+    fn check(&self, evt_hash: [u8; 32], field_count: u16) -> Result<()> {
+        ensure!(self.header.evt_hash == evt_hash, "batch: wrong event hash");
+        ensure!(
+            self.header.field_count == field_count,
+            "batch: field count mismatch (got {}, expected {})",
+            self.header.field_count,
+            field_count
+        );
+        Ok(())
+    }
+}
+
+/// Lazy row iterator over a batch. Stops after the first error; a batch whose
+/// rows do not use up exactly `rows_len` bytes ends with an error.
+pub struct BatchRows<'a, T> {
+    cur: RowBinCursor<'a>,
+    remaining: u32,
+    decode: fn(&mut RowBinCursor<'a>) -> Result<T>,
+    done: bool,
+}
+
+impl<'a, T> BatchRows<'a, T> {
+    fn new(batch: &Batch<'a>, decode: fn(&mut RowBinCursor<'a>) -> Result<T>) -> Self {
+        Self {
+            cur: RowBinCursor::new(batch.rows),
+            remaining: batch.header.row_count,
+            decode,
+            done: false,
+        }
+    }
+}
+
+impl<T> Iterator for BatchRows<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.remaining == 0 {
+            self.done = true;
+            let trailing = self.cur.buf.len() - self.cur.pos;
+            return (trailing != 0).then(|| {
+                Err(anyhow::anyhow!(
+                    "{trailing} trailing bytes after last batch row"
+                ))
+            });
+        }
+
+        self.remaining -= 1;
+        let row = (self.decode)(&mut self.cur);
+        if row.is_err() {
+            self.done = true;
+        }
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, Some(self.remaining as usize + 1))
+        }
+    }
+}
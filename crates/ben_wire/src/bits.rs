@@ -0,0 +1,187 @@
+//! Fixed-width bitsets for rule masks. The width is `WORDS * 64` bits,
+//! chosen per use; [`DEFAULT_WORDS`] gives 128. A bit past the width is
+//! refused rather than wrapped.
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
+/// Index of one bit in a [`BitSet`].
+pub type Bit = u16;
+
+pub const DEFAULT_WORDS: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitSet<const WORDS: usize = DEFAULT_WORDS> {
+    words: [u64; WORDS],
+}
+
+impl<const WORDS: usize> BitSet<WORDS> {
+    /// Number of bits the set holds.
+    pub const WIDTH: usize = WORDS * 64;
+
+    pub const EMPTY: Self = Self { words: [0; WORDS] };
+
+    pub const fn from_words(words: [u64; WORDS]) -> Self {
+        Self { words }
+    }
+
+    /// Word 0 holds bits 0..64.
+    pub const fn words(&self) -> &[u64; WORDS] {
+        &self.words
+    }
+
+    /// The set holding only bit `i`; `None` past the width.
+    pub const fn bit(i: Bit) -> Option<Self> {
+        Self::EMPTY.with(i)
+    }
+
+    /// `None` past the width.
+    pub const fn with(mut self, i: Bit) -> Option<Self> {
+        let i = i as usize;
+        if i >= Self::WIDTH {
+            return None;
+        }
+        self.words[i / 64] |= 1u64 << (i % 64);
+        Some(self)
+    }
+
+    /// Returns false, leaving the set alone, when `i` is past the width.
+    pub fn set(&mut self, i: Bit) -> bool {
+        match self.with(i) {
+            Some(set) => {
+                *self = set;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `None` if any bit is past the width.
+    pub fn from_bits(bits: impl IntoIterator<Item = Bit>) -> Option<Self> {
+        bits.into_iter().try_fold(Self::EMPTY, Self::with)
+    }
+
+    pub const fn contains(&self, i: Bit) -> bool {
+        let i = i as usize;
+        i < Self::WIDTH && self.words[i / 64] & (1u64 << (i % 64)) != 0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        let mut w = 0;
+        while w < WORDS {
+            if self.words[w] != 0 {
+                return false;
+            }
+            w += 1;
+        }
+        true
+    }
+
+    pub const fn union(mut self, other: Self) -> Self {
+        let mut w = 0;
+        while w < WORDS {
+            self.words[w] |= other.words[w];
+            w += 1;
+        }
+        self
+    }
+
+    pub const fn intersection(mut self, other: Self) -> Self {
+        let mut w = 0;
+        while w < WORDS {
+            self.words[w] &= other.words[w];
+            w += 1;
+        }
+        self
+    }
+
+    /// Every bit of `other` is set here.
+    pub const fn contains_all(&self, other: &Self) -> bool {
+        let mut w = 0;
+        while w < WORDS {
+            if self.words[w] & other.words[w] != other.words[w] {
+                return false;
+            }
+            w += 1;
+        }
+        true
+    }
+
+    /// Some bit is set in both.
+    pub const fn intersects(&self, other: &Self) -> bool {
+        let mut w = 0;
+        while w < WORDS {
+            if self.words[w] & other.words[w] != 0 {
+                return true;
+            }
+            w += 1;
+        }
+        false
+    }
+
+    pub const fn count_ones(&self) -> u32 {
+        let mut n = 0;
+        let mut w = 0;
+        while w < WORDS {
+            n += self.words[w].count_ones();
+            w += 1;
+        }
+        n
+    }
+
+    /// Set bits, lowest first.
+    pub fn ones(&self) -> impl Iterator<Item = Bit> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let b = rest.trailing_zeros();
+                rest &= rest - 1;
+                Some((w * 64 + b as usize) as Bit)
+            })
+        })
+    }
+}
+
+impl<const WORDS: usize> Default for BitSet<WORDS> {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl<const WORDS: usize> BitOr for BitSet<WORDS> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl<const WORDS: usize> BitOrAssign for BitSet<WORDS> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+impl<const WORDS: usize> BitAnd for BitSet<WORDS> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl<const WORDS: usize> BitAndAssign for BitSet<WORDS> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = self.intersection(rhs);
+    }
+}
+
+/// Lists the set bits: `{0, 5, 130}`.
+impl<const WORDS: usize> fmt::Debug for BitSet<WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.ones()).finish()
+    }
+}
@@ -0,0 +1,104 @@
+//! Rust types with a fixed ClickHouse column type beyond the primitives:
+//! `DateTime64(P)` timestamps and `UUID`s. `SystemTime` is always available;
+//! the `time`, `chrono` and `uuid` features add those crates' types.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Stored as `DateTime64(P)`: ticks of 10^-P seconds since the Unix epoch.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be stored as DateTime64",
+    note = "enable ben_wire's `time` or `chrono` feature for those crates' UTC datetimes"
+)]
+pub trait DateTime64Value: Sized {
+    /// Rounds towards the past and saturates at the ends of `i64`.
+    fn to_ticks(&self, scale: u32) -> i64;
+
+    /// `None` when the instant is out of the type's range.
+    fn from_ticks(ticks: i64, scale: u32) -> Option<Self>;
+}
+
+/// Stored as `UUID`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be stored as UUID",
+    note = "enable ben_wire's `uuid` feature for `uuid::Uuid`"
+)]
+pub trait UuidValue: Sized {
+    /// Canonical (big-endian) byte order.
+    fn uuid_bytes(&self) -> [u8; 16];
+
+    fn from_uuid_bytes(bytes: [u8; 16]) -> Self;
+}
+
+/// Nanoseconds per tick of a `DateTime64(scale)`; ClickHouse allows 0..=9.
+fn tick_nanos(scale: u32) -> i128 {
+    10i128.pow(9 - scale.min(9))
+}
+
+pub(crate) fn nanos_to_ticks(nanos: i128, scale: u32) -> i64 {
+    let ticks = nanos.div_euclid(tick_nanos(scale));
+    ticks.clamp(i64::MIN.into(), i64::MAX.into()) as i64
+}
+
+fn ticks_to_nanos(ticks: i64, scale: u32) -> i128 {
+    i128::from(ticks) * tick_nanos(scale)
+}
+
+impl DateTime64Value for SystemTime {
+    fn to_ticks(&self, scale: u32) -> i64 {
+        let nanos = match self.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_nanos() as i128,
+            Err(before) => -(before.duration().as_nanos() as i128),
+        };
+        nanos_to_ticks(nanos, scale)
+    }
+
+    fn from_ticks(ticks: i64, scale: u32) -> Option<Self> {
+        let nanos = ticks_to_nanos(ticks, scale);
+        let offset = Duration::new(
+            u64::try_from(nanos.unsigned_abs() / 1_000_000_000).ok()?,
+            (nanos.unsigned_abs() % 1_000_000_000) as u32,
+        );
+        if nanos < 0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl DateTime64Value for time::OffsetDateTime {
+    fn to_ticks(&self, scale: u32) -> i64 {
+        nanos_to_ticks(self.unix_timestamp_nanos(), scale)
+    }
+
+    fn from_ticks(ticks: i64, scale: u32) -> Option<Self> {
+        Self::from_unix_timestamp_nanos(ticks_to_nanos(ticks, scale)).ok()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DateTime64Value for chrono::DateTime<chrono::Utc> {
+    fn to_ticks(&self, scale: u32) -> i64 {
+        let nanos = i128::from(self.timestamp()) * 1_000_000_000
+            + i128::from(self.timestamp_subsec_nanos());
+        nanos_to_ticks(nanos, scale)
+    }
+
+    fn from_ticks(ticks: i64, scale: u32) -> Option<Self> {
+        let nanos = ticks_to_nanos(ticks, scale);
+        let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+        Self::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl UuidValue for uuid::Uuid {
+    fn uuid_bytes(&self) -> [u8; 16] {
+        *self.as_bytes()
+    }
+
+    fn from_uuid_bytes(bytes: [u8; 16]) -> Self {
+        Self::from_bytes(bytes)
+    }
+}
@@ -1,52 +1,416 @@
+use std::borrow::Cow;
+
+use crate::{
+    error::EnvelopeError, rowbinary::RowBinCursor, rowpack::decode_rowpack, slot::SlotValue,
+};
+
+/// Leading bytes of a v2 frame. v1 frames start directly with the event hash;
+/// the v1 writers refuse hashes that begin with these bytes.
+pub const MAGIC: [u8; 4] = *b"BENv";
+pub const VERSION: u8 = 2;
+
+/// Default cap on an inflated payload; see [`Envelope::decode_with_limit`].
+pub const MAX_DECOMPRESSED_LEN: usize = 64 << 20;
+
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Encoding {
     RowBinary = 2,
+    Rowpack = 3,
+    RowBinaryZstd = 4,
+    RowBinaryLz4 = 5,
+    Json = 6,
+}
+
+impl Encoding {
+    /// True for the RowBinary variants, compressed or not.
+    pub fn is_rowbinary(self) -> bool {
+        matches!(
+            self,
+            Encoding::RowBinary | Encoding::RowBinaryZstd | Encoding::RowBinaryLz4
+        )
+    }
+
+    pub fn is_compressed(self) -> bool {
+        matches!(self, Encoding::RowBinaryZstd | Encoding::RowBinaryLz4)
+    }
+}
+
+impl TryFrom<u8> for Encoding {
+    type Error = EnvelopeError;
+
+    fn try_from(tag: u8) -> Result<Self, EnvelopeError> {
+        Ok(match tag {
+            2 => Encoding::RowBinary,
+            3 => Encoding::Rowpack,
+            4 => Encoding::RowBinaryZstd,
+            5 => Encoding::RowBinaryLz4,
+            6 => Encoding::Json,
+            other => return Err(EnvelopeError::UnknownEncoding(other)),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvelopeFlags(u8);
+
+impl EnvelopeFlags {
+    pub const COMPRESSED: Self = Self(1 << 0);
+    // Bit 1 is held for signatures; reserved until frames can be verified.
+    pub const SAMPLED: Self = Self(1 << 2);
+
+    const KNOWN: u8 = Self::COMPRESSED.0 | Self::SAMPLED.0;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Result<Self, EnvelopeError> {
+        if bits & !Self::KNOWN != 0 {
+            return Err(EnvelopeError::ReservedFlags(bits & !Self::KNOWN));
+        }
+        Ok(Self(bits))
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn set(&mut self, other: Self, on: bool) {
+        if on {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Envelope<'a> {
+    /// 1 for the legacy header, 2 for the magic-prefixed header.
+    pub version: u8,
+    /// Always carries `COMPRESSED` for compressed encodings; v1 frames imply it from the encoding.
+    pub flags: EnvelopeFlags,
     pub evt_hash: [u8; 32],
     pub epoch: u64,
     pub encoding: Encoding,
+    pub field_count: u16,
     pub payload: &'a [u8],
 }
 
+/// An envelope payload after it has been routed through the decoder for its encoding.
+#[derive(Debug)]
+pub enum Payload<'a> {
+    /// Plain RowBinary bytes. Compressed encodings are inflated into an owned buffer.
+    RowBinary(Cow<'a, [u8]>),
+    Rowpack(Vec<SlotValue<'a>>),
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+}
+
+impl<'a> Payload<'a> {
+    /// Cursor over the RowBinary bytes, or `None` for the other encodings.
+    pub fn rowbinary_cursor(&self) -> Option<RowBinCursor<'_>> {
+        match self {
+            Payload::RowBinary(bytes) => Some(RowBinCursor::new(bytes)),
+            _ => None,
+        }
+    }
+}
+
 impl<'a> Envelope<'a> {
-    pub fn parse(buf: &'a [u8]) -> anyhow::Result<Self> {
-        anyhow::ensure!(buf.len() >= 32 + 8 + 1 + 2, "short envelope");
+    /// hash + epoch + encoding + field_count
+    pub const V1_HEADER_LEN: usize = 32 + 8 + 1 + 2;
+    /// magic + version + flags + encoding + hash + epoch + field_count + payload_len + crc32c
+    pub const V2_HEADER_LEN: usize = 4 + 1 + 1 + 1 + 32 + 8 + 2 + 4 + 4;
 
-        let (hash_bytes, rest) = buf.split_at(32);
-        let mut evt_hash = [0u8; 32];
-        evt_hash.copy_from_slice(hash_bytes);
+    /// Parses a v2 frame when `buf` starts with [`MAGIC`], otherwise the v1 layout.
+    /// A v1 frame can't start with the magic, since [`Envelope::write_header`]
+    /// rejects such hashes, so the prefix is never ambiguous.
+    ///
+    /// v2 frames are length-delimited, so bytes past the payload are left for the
+    /// caller; see [`Envelope::frame_len`]. A v1 payload runs to the end of `buf`.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, EnvelopeError> {
+        if buf.starts_with(&MAGIC) {
+            Self::parse_v2(buf)
+        } else {
+            Self::parse_v1(buf)
+        }
+    }
 
-        let epoch = u64::from_le_bytes(rest[0..8].try_into()?);
+    fn parse_v1(buf: &'a [u8]) -> Result<Self, EnvelopeError> {
+        ensure_len(buf, Self::V1_HEADER_LEN)?;
 
-        let encoding = match rest[8] {
-            2 => Encoding::RowBinary,
-            _ => anyhow::bail!("unknown encoding"),
-        };
+        let evt_hash = array(&buf[0..32]);
+        let epoch = u64::from_le_bytes(array(&buf[32..40]));
+        let encoding = Encoding::try_from(buf[40])?;
+        let field_count = u16::from_le_bytes(array(&buf[41..43]));
+
+        let mut flags = EnvelopeFlags::empty();
+        flags.set(EnvelopeFlags::COMPRESSED, encoding.is_compressed());
+
+        Ok(Self {
+            version: 1,
+            flags,
+            evt_hash,
+            epoch,
+            encoding,
+            field_count,
+            payload: &buf[Self::V1_HEADER_LEN..],
+        })
+    }
+
+    fn parse_v2(buf: &'a [u8]) -> Result<Self, EnvelopeError> {
+        ensure_len(buf, Self::V2_HEADER_LEN)?;
+
+        let version = buf[4];
+        if version != VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+
+        let flags = EnvelopeFlags::from_bits(buf[5])?;
+        let encoding = Encoding::try_from(buf[6])?;
+        if flags.contains(EnvelopeFlags::COMPRESSED) != encoding.is_compressed() {
+            return Err(EnvelopeError::CompressedFlagMismatch { encoding });
+        }
 
-        let _field_count = u16::from_le_bytes(rest[9..11].try_into()?);
-        let payload = &rest[11..];
+        let evt_hash = array(&buf[7..39]);
+        let epoch = u64::from_le_bytes(array(&buf[39..47]));
+        let field_count = u16::from_le_bytes(array(&buf[47..49]));
+        let payload_len = u32::from_le_bytes(array(&buf[49..53])) as usize;
+        let expected = u32::from_le_bytes(array(&buf[53..57]));
+
+        let end = Self::V2_HEADER_LEN + payload_len;
+        ensure_len(buf, end)?;
+        let payload = &buf[Self::V2_HEADER_LEN..end];
+
+        let got = v2_checksum(&buf[..53], payload);
+        if got != expected {
+            return Err(EnvelopeError::ChecksumMismatch { expected, got });
+        }
 
         Ok(Self {
+            version,
+            flags,
             evt_hash,
             epoch,
             encoding,
+            field_count,
             payload,
         })
     }
 
+    /// Number of bytes this envelope occupied in the buffer it was parsed from.
+    pub fn frame_len(&self) -> usize {
+        let header = match self.version {
+            1 => Self::V1_HEADER_LEN,
+            _ => Self::V2_HEADER_LEN,
+        };
+        header + self.payload.len()
+    }
+
+    /// Routes the payload to the decoder matching `self.encoding`, inflating
+    /// at most [`MAX_DECOMPRESSED_LEN`] bytes.
+    pub fn decode(&self) -> anyhow::Result<Payload<'a>> {
+        self.decode_with_limit(MAX_DECOMPRESSED_LEN)
+    }
+
+    /// [`Envelope::decode`] with a caller-chosen cap on the inflated payload.
+    pub fn decode_with_limit(&self, limit: usize) -> anyhow::Result<Payload<'a>> {
+        match self.encoding {
+            Encoding::RowBinary => Ok(Payload::RowBinary(Cow::Borrowed(self.payload))),
+            Encoding::RowBinaryZstd => {
+                decompress_zstd(self.payload, limit).map(|raw| Payload::RowBinary(Cow::Owned(raw)))
+            }
+            Encoding::RowBinaryLz4 => {
+                decompress_lz4(self.payload, limit).map(|raw| Payload::RowBinary(Cow::Owned(raw)))
+            }
+            Encoding::Rowpack => Ok(Payload::Rowpack(decode_rowpack(
+                self.payload,
+                self.field_count as usize,
+            )?)),
+            Encoding::Json => decode_json(self.payload),
+        }
+    }
+
+    /// Writes a v1 header. Prefer [`Envelope::write_frame`] for new senders.
+    ///
+    /// Fails for a hash starting with [`MAGIC`], which would read back as v2.
+    pub fn write_header(
+        dst: &mut Vec<u8>,
+        evt_hash: [u8; 32],
+        epoch: u64,
+        encoding: Encoding,
+        field_count: u16,
+    ) -> Result<(), EnvelopeError> {
+        if evt_hash.starts_with(&MAGIC) {
+            return Err(EnvelopeError::MagicCollision);
+        }
+        dst.extend_from_slice(&evt_hash);
+        dst.extend_from_slice(&epoch.to_le_bytes());
+        dst.push(encoding as u8);
+        dst.extend_from_slice(&field_count.to_le_bytes());
+        Ok(())
+    }
+
     pub fn write_rowbinary_header(
         dst: &mut Vec<u8>,
         evt_hash: [u8; 32],
         epoch: u64,
         field_count: u16,
-    ) {
+    ) -> Result<(), EnvelopeError> {
+        Self::write_header(dst, evt_hash, epoch, Encoding::RowBinary, field_count)
+    }
+
+    /// Appends `raw` as the payload for `encoding`, compressing it for the
+    /// compressed RowBinary variants. Other encodings are copied through as-is.
+    pub fn write_payload(dst: &mut Vec<u8>, encoding: Encoding, raw: &[u8]) -> anyhow::Result<()> {
+        match encoding {
+            Encoding::RowBinaryZstd => dst.extend_from_slice(&compress_zstd(raw)?),
+            Encoding::RowBinaryLz4 => dst.extend_from_slice(&compress_lz4(raw)?),
+            Encoding::RowBinary | Encoding::Rowpack | Encoding::Json => dst.extend_from_slice(raw),
+        }
+        Ok(())
+    }
+
+    /// Writes a complete v2 frame. `raw` is compressed for the compressed
+    /// encodings, and the `COMPRESSED` flag is set to match.
+    pub fn write_frame(
+        dst: &mut Vec<u8>,
+        evt_hash: [u8; 32],
+        epoch: u64,
+        encoding: Encoding,
+        field_count: u16,
+        mut flags: EnvelopeFlags,
+        raw: &[u8],
+    ) -> anyhow::Result<()> {
+        flags.set(EnvelopeFlags::COMPRESSED, encoding.is_compressed());
+
+        let start = dst.len();
+        dst.extend_from_slice(&MAGIC);
+        dst.push(VERSION);
+        dst.push(flags.bits());
+        dst.push(encoding as u8);
         dst.extend_from_slice(&evt_hash);
         dst.extend_from_slice(&epoch.to_le_bytes());
-        dst.push(Encoding::RowBinary as u8);
         dst.extend_from_slice(&field_count.to_le_bytes());
+
+        let len_at = dst.len();
+        dst.extend_from_slice(&[0u8; 8]); // payload_len + crc32c, patched below
+
+        let payload_at = dst.len();
+        if let Err(e) = Self::write_payload(dst, encoding, raw) {
+            dst.truncate(start);
+            return Err(e);
+        }
+
+        let payload_len = dst.len() - payload_at;
+        let Ok(len) = u32::try_from(payload_len) else {
+            dst.truncate(start);
+            return Err(EnvelopeError::PayloadTooLarge { len: payload_len }.into());
+        };
+        dst[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+
+        let crc = v2_checksum(&dst[start..len_at + 4], &dst[payload_at..]);
+        dst[len_at + 4..payload_at].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(())
+    }
+}
+
+fn ensure_len(buf: &[u8], needed: usize) -> Result<(), EnvelopeError> {
+    if buf.len() < needed {
+        return Err(EnvelopeError::Truncated {
+            needed,
+            got: buf.len(),
+        });
+    }
+    Ok(())
+}
+
+#[inline]
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    out
+}
+
+/// CRC32C over every header byte before the checksum field, then the payload.
+fn v2_checksum(header: &[u8], payload: &[u8]) -> u32 {
+    crc32c::crc32c_append(crc32c::crc32c(header), payload)
+}
+
+#[cfg(feature = "zstd")]
+fn compress_zstd(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(zstd::bulk::compress(raw, 0)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress_zstd(_raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("ben_wire built without the `zstd` feature")
+}
+
+/// Reads one byte past `limit` so an oversized frame is caught without
+/// inflating the rest of it.
+#[cfg(feature = "zstd")]
+fn decompress_zstd(payload: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut out = Vec::new();
+    zstd::stream::Decoder::new(payload)?
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)?;
+    if out.len() > limit {
+        return Err(EnvelopeError::DecompressedTooLarge { limit }.into());
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_payload: &[u8], _limit: usize) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("ben_wire built without the `zstd` feature")
+}
+
+#[cfg(feature = "lz4")]
+fn compress_lz4(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(lz4_flex::compress_prepend_size(raw))
+}
+
+#[cfg(not(feature = "lz4"))]
+fn compress_lz4(_raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("ben_wire built without the `lz4` feature")
+}
+
+/// The sender's size prefix is checked before lz4_flex allocates for it.
+#[cfg(feature = "lz4")]
+fn decompress_lz4(payload: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    ensure_len(payload, 4)?;
+    let len = u32::from_le_bytes(array(&payload[..4])) as usize;
+    if len > limit {
+        return Err(EnvelopeError::DecompressedTooLarge { limit }.into());
     }
+    Ok(lz4_flex::decompress_size_prepended(payload)?)
+}
+
+#[cfg(not(feature = "lz4"))]
+fn decompress_lz4(_payload: &[u8], _limit: usize) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("ben_wire built without the `lz4` feature")
+}
+
+#[cfg(feature = "json")]
+fn decode_json<'a>(payload: &[u8]) -> anyhow::Result<Payload<'a>> {
+    Ok(Payload::Json(serde_json::from_slice(payload)?))
+}
+
+#[cfg(not(feature = "json"))]
+fn decode_json<'a>(_payload: &[u8]) -> anyhow::Result<Payload<'a>> {
+    anyhow::bail!("ben_wire built without the `json` feature")
 }
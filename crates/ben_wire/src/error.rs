@@ -1,4 +1,126 @@
-use crate::schema::Schema;
+use crate::{envelope::Encoding, schema::Schema};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvelopeError {
+    #[error("short envelope: need {needed} bytes, got {got}")]
+    Truncated { needed: usize, got: usize },
+
+    #[error("unknown encoding {0}")]
+    UnknownEncoding(u8),
+
+    #[error("unsupported envelope version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("reserved envelope flag bits set: {0:#04x}")]
+    ReservedFlags(u8),
+
+    #[error("compressed flag does not match encoding {encoding:?}")]
+    CompressedFlagMismatch { encoding: Encoding },
+
+    #[error("checksum mismatch: header says {expected:#010x}, computed {got:#010x}")]
+    ChecksumMismatch { expected: u32, got: u32 },
+
+    #[error("payload of {len} bytes does not fit a v2 envelope")]
+    PayloadTooLarge { len: usize },
+
+    #[error("payload inflates past the {limit}-byte limit")]
+    DecompressedTooLarge { limit: usize },
+
+    #[error("v1 event hash starts with the v2 magic and would parse as a v2 frame")]
+    MagicCollision,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RowpackError {
+    #[error("rowpack truncated in field {field} at byte {offset}: need {needed}, have {remaining}")]
+    Truncated {
+        field: usize,
+        offset: usize,
+        needed: usize,
+        remaining: usize,
+    },
+
+    #[error("unknown rowpack tag {tag} for field {field} at byte {offset}")]
+    UnknownTag {
+        field: usize,
+        offset: usize,
+        tag: u8,
+    },
+
+    #[error("invalid utf-8 in field {field} at byte {offset}")]
+    InvalidUtf8 { field: usize, offset: usize },
+
+    #[error(
+        "str id {id} in field {field} at byte {offset} is not defined (dictionary has {defined})"
+    )]
+    UnknownStrId {
+        field: usize,
+        offset: usize,
+        id: u32,
+        defined: u32,
+    },
+
+    #[error("str id in field {field} at byte {offset} but no dictionary was supplied")]
+    NoDictionary { field: usize, offset: usize },
+
+    #[error("string dictionary full at field {field}, byte {offset}")]
+    DictionaryFull { field: usize, offset: usize },
+
+    #[error("arrays and maps nest too deep in field {field} at byte {offset}")]
+    TooDeep { field: usize, offset: usize },
+
+    #[error("array or map length does not match its body in field {field} at byte {offset}")]
+    BadNesting { field: usize, offset: usize },
+
+    #[error("{count} trailing bytes after rowpack row at byte {offset}")]
+    TrailingBytes { offset: usize, count: usize },
+
+    #[error("length {len} does not fit a rowpack u32 length prefix")]
+    TooLong { len: usize },
+}
+
+impl RowpackError {
+    /// Moves the reported offset by `by`, for errors from a nested buffer.
+    pub(crate) fn shifted(mut self, by: usize) -> Self {
+        match &mut self {
+            RowpackError::Truncated { offset, .. }
+            | RowpackError::UnknownTag { offset, .. }
+            | RowpackError::InvalidUtf8 { offset, .. }
+            | RowpackError::UnknownStrId { offset, .. }
+            | RowpackError::NoDictionary { offset, .. }
+            | RowpackError::DictionaryFull { offset, .. }
+            | RowpackError::TooDeep { offset, .. }
+            | RowpackError::BadNesting { offset, .. }
+            | RowpackError::TrailingBytes { offset, .. } => *offset += by,
+            RowpackError::TooLong { .. } => {}
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SlotError {
+    #[error("expected {expected}, got {got}")]
+    TypeMismatch {
+        expected: &'static str,
+        got: &'static str,
+    },
+
+    #[error("{got} value does not fit {expected}")]
+    OutOfRange {
+        expected: &'static str,
+        got: &'static str,
+    },
+
+    #[error("str id {0} is not in the dictionary")]
+    UnknownStrId(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SchemaError {
+    #[error("unsupported ClickHouse type `{0}`")]
+    UnknownType(String),
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum ViewError {
@@ -8,13 +130,6 @@ pub enum ViewError {
     #[error("field count mismatch: expected {expected}, got {got}")]
     FieldCountMismatch { expected: usize, got: usize },
 
-    #[error("field name mismatch at index {index}: expected `{expected}`, got `{got}`")]
-    FieldNameMismatch {
-        index: usize,
-        expected: &'static str,
-        got: String,
-    },
-
     #[error("field `{field}` has incompatible type")]
     TypeMismatch { field: &'static str },
 
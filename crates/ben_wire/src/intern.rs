@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+/// Default cap on dictionary entries per stream, on both sides of the wire.
+pub const DEFAULT_DICT_LIMIT: usize = 1 << 16;
+
+/// Encoder half of a rowpack string dictionary.
+///
+/// Ids are handed out densely in first-use order, so the first time a string
+/// is seen the encoder writes its definition and every later occurrence is
+/// just the id. One interner belongs to exactly one stream; the decoding side
+/// must replay the same sequence of rows into a [`StrDict`].
+#[derive(Debug)]
+pub struct StrInterner {
+    ids: HashMap<Box<str>, u32>,
+    limit: usize,
+}
+
+impl Default for StrInterner {
+    fn default() -> Self {
+        Self::with_limit(DEFAULT_DICT_LIMIT)
+    }
+}
+
+impl StrInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            ids: HashMap::new(),
+            limit: limit.min(u32::MAX as usize),
+        }
+    }
+
+    /// Returns the id for `s` and whether this call defined it. `None` once
+    /// the dictionary is full; callers fall back to an inline string.
+    pub fn intern(&mut self, s: &str) -> Option<(u32, bool)> {
+        if let Some(&id) = self.ids.get(s) {
+            return Some((id, false));
+        }
+        if self.ids.len() >= self.limit {
+            return None;
+        }
+        let id = self.ids.len() as u32;
+        self.ids.insert(s.into(), id);
+        Some((id, true))
+    }
+
+    pub fn get(&self, s: &str) -> Option<u32> {
+        self.ids.get(s).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Forgets every entry. The peer's [`StrDict`] must be reset at the same
+    /// point in the stream.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    /// Forgets ids from `len` on, undoing a row that failed to encode.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len < self.ids.len() {
+            self.ids.retain(|_, id| (*id as usize) < len);
+        }
+    }
+}
+
+/// Decoder half of a rowpack string dictionary; resolves `SlotValue::StrId`.
+#[derive(Debug)]
+pub struct StrDict {
+    entries: Vec<Box<str>>,
+    limit: usize,
+}
+
+impl Default for StrDict {
+    fn default() -> Self {
+        Self::with_limit(DEFAULT_DICT_LIMIT)
+    }
+}
+
+impl StrDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            limit: limit.min(u32::MAX as usize),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.entries.get(id as usize).map(|s| &**s)
+    }
+
+    /// Id the next definition must carry.
+    #[inline]
+    pub fn next_id(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.limit
+    }
+
+    /// Appends a definition. Returns `None` when the dictionary is full.
+    pub fn define(&mut self, s: &str) -> Option<u32> {
+        if self.is_full() {
+            return None;
+        }
+        let id = self.next_id();
+        self.entries.push(s.into());
+        Some(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Drops definitions past the first `len`, undoing a rejected row.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+    }
+}
@@ -1,8 +1,14 @@
+pub mod batch;
+pub mod bits;
+pub mod ch_value;
 pub mod envelope;
 pub mod error;
+pub mod intern;
 pub mod rowbinary;
 pub mod rowpack;
 pub mod schema;
+pub mod schema_text;
+#[cfg(feature = "serde")]
 pub mod serde_bridge;
 pub mod slot;
 pub mod view;
@@ -10,7 +16,11 @@ pub mod view;
 pub use crate::envelope::{Encoding, Envelope};
 pub use crate::schema::Schema;
 
+/// Writers for ClickHouse `FORMAT RowBinary`. Everything the generated
+/// encoders emit goes through here so the layout lives in one place; the
+/// matching readers are on [`rowbinary::RowBinCursor`].
 pub mod ch_binary {
+    /// LEB128, as used for string, array and map lengths.
     #[inline]
     pub fn write_uvarint(mut x: usize, buf: &mut Vec<u8>) {
         while x >= 0x80 {
@@ -22,7 +32,66 @@ pub mod ch_binary {
 
     #[inline]
     pub fn write_str(s: &str, out: &mut Vec<u8>) {
-        write_uvarint(s.len(), out);
-        out.extend_from_slice(s.as_bytes());
+        write_bytes(s.as_bytes(), out);
+    }
+
+    /// `String` holding arbitrary bytes; same layout as `Array(UInt8)`.
+    #[inline]
+    pub fn write_bytes(b: &[u8], out: &mut Vec<u8>) {
+        write_uvarint(b.len(), out);
+        out.extend_from_slice(b);
+    }
+
+    /// `Nullable(T)` marker: 1 for NULL, 0 when a value follows.
+    #[inline]
+    pub fn write_null_marker(is_null: bool, out: &mut Vec<u8>) {
+        out.push(is_null as u8);
+    }
+
+    /// `IPv4` is the address as a little-endian u32.
+    #[inline]
+    pub fn write_ipv4(ip: u32, out: &mut Vec<u8>) {
+        out.extend_from_slice(&ip.to_le_bytes());
+    }
+
+    /// `IPv6` is 16 bytes in network order.
+    #[inline]
+    pub fn write_ipv6(ip: u128, out: &mut Vec<u8>) {
+        out.extend_from_slice(&ip.to_be_bytes());
+    }
+
+    /// An `IpAddr` column is `IPv6`, with IPv4 addresses IPv4-mapped.
+    #[inline]
+    pub fn write_ip(ip: std::net::IpAddr, out: &mut Vec<u8>) {
+        let v6 = match ip {
+            std::net::IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+            std::net::IpAddr::V6(v6) => v6,
+        };
+        write_ipv6(v6.into(), out);
+    }
+
+    /// `UUID` is two little-endian u64 halves; `uuid` is in canonical
+    /// (big-endian) byte order.
+    #[inline]
+    pub fn write_uuid(uuid: &[u8; 16], out: &mut Vec<u8>) {
+        let (hi, lo) = uuid.split_at(8);
+        out.extend(hi.iter().rev());
+        out.extend(lo.iter().rev());
+    }
+
+    /// `DateTime64(P)` is the tick count only; the precision lives in the type.
+    #[inline]
+    pub fn write_datetime64(ticks: i64, out: &mut Vec<u8>) {
+        out.extend_from_slice(&ticks.to_le_bytes());
+    }
+
+    /// `Enum8` when `bits` is 8, else `Enum16`: the value as an `Int8`/`Int16`.
+    #[inline]
+    pub fn write_enum(value: i16, bits: u8, out: &mut Vec<u8>) {
+        if bits == 8 {
+            out.push(value as i8 as u8);
+        } else {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
     }
 }
@@ -1,5 +1,11 @@
 use anyhow::{Result, ensure};
 
+use crate::{
+    bits::BitSet,
+    ch_binary,
+    schema::{INFRA_COLUMNS, Schema},
+};
+
 pub type RowBinaryResult = anyhow::Result<()>;
 
 pub struct RowBinCursor<'a> {
@@ -14,12 +20,12 @@ impl<'a> RowBinCursor<'a> {
     }
 
     #[inline]
-    fn remaining(&self) -> usize {
+    pub fn remaining(&self) -> usize {
         self.buf.len().saturating_sub(self.pos)
     }
 
     #[inline]
-    pub fn take(&mut self, n: usize) -> Result<&[u8]> {
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8]> {
         if self.remaining() < n {
             anyhow::bail!("buffer underflow");
         }
@@ -28,14 +34,38 @@ impl<'a> RowBinCursor<'a> {
         Ok(&self.buf[start..self.pos])
     }
 
+    #[inline]
+    pub fn read_u128(&mut self) -> Result<u128> {
+        Ok(u128::from_le_bytes(self.take(16)?.try_into()?))
+    }
     #[inline]
     pub fn read_u64(&mut self) -> Result<u64> {
         Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
     }
     #[inline]
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+    #[inline]
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+    #[inline]
     pub fn read_i64(&mut self) -> Result<i64> {
         Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
     }
+    #[inline]
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+    #[inline]
+    pub fn read_i16(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+    #[inline]
+    pub fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.read_u8()? as i8)
+    }
 
     #[inline]
     pub fn read_f64(&mut self) -> Result<f64> {
@@ -43,6 +73,12 @@ impl<'a> RowBinCursor<'a> {
         Ok(f64::from_le_bytes(bytes))
     }
 
+    #[inline]
+    pub fn read_f32(&mut self) -> Result<f32> {
+        let bytes: [u8; 4] = self.take(4)?.try_into()?;
+        Ok(f32::from_le_bytes(bytes))
+    }
+
     #[inline]
     pub fn read_u8(&mut self) -> Result<u8> {
         Ok(self.take(1)?[0])
@@ -53,21 +89,79 @@ impl<'a> RowBinCursor<'a> {
         Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
     }
 
+    /// LEB128 length prefix of strings, arrays and maps.
+    #[inline]
+    pub fn read_uvarint(&mut self) -> Result<u64> {
+        let mut x = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.read_u8()?;
+            x |= u64::from(b & 0x7F) << shift;
+            if b & 0x80 == 0 {
+                ensure!(shift < 63 || b <= 1, "varint overflows u64");
+                return Ok(x);
+            }
+        }
+        anyhow::bail!("varint longer than 10 bytes")
+    }
+
+    /// Element count of an array or map. Never larger than the bytes left,
+    /// so it is safe to preallocate with.
+    #[inline]
+    pub fn read_len(&mut self) -> Result<usize> {
+        let len = usize::try_from(self.read_uvarint()?)?;
+        ensure!(len <= self.remaining(), "buffer underflow");
+        Ok(len)
+    }
+
+    /// `Nullable(T)` marker: true when the value is NULL.
+    #[inline]
+    pub fn read_null_marker(&mut self) -> Result<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => anyhow::bail!("invalid Nullable marker {other}"),
+        }
+    }
+
     #[inline]
     pub fn read_ipv6(&mut self) -> Result<u128> {
-        Ok(u128::from_le_bytes(self.take(16)?.try_into()?))
+        Ok(u128::from_be_bytes(self.take(16)?.try_into()?))
     }
 
+    /// Inverse of [`ch_binary::write_ip`]: IPv4-mapped addresses come back as IPv4.
     #[inline]
-    pub fn read_datetime64(&mut self) -> Result<(i64, u32)> {
-        let epoch = i64::from_le_bytes(self.take(8)?.try_into()?);
-        let scale = u32::from_le_bytes(self.take(4)?.try_into()?);
-        Ok((epoch, scale))
+    pub fn read_ip(&mut self) -> Result<std::net::IpAddr> {
+        Ok(std::net::Ipv6Addr::from(self.read_ipv6()?).to_canonical())
     }
 
+    /// Tick count of a `DateTime64(P)`; the caller knows `P` from the schema.
+    #[inline]
+    pub fn read_datetime64(&mut self) -> Result<i64> {
+        self.read_i64()
+    }
+
+    /// UUID in canonical (big-endian) byte order.
     #[inline]
     pub fn read_uuid(&mut self) -> Result<[u8; 16]> {
-        Ok(self.take(16)?.try_into()?)
+        let raw = self.take(16)?;
+        let mut out = [0u8; 16];
+        for (dst, src) in out
+            .iter_mut()
+            .zip(raw[..8].iter().rev().chain(raw[8..].iter().rev()))
+        {
+            *dst = *src;
+        }
+        Ok(out)
+    }
+
+    /// Inverse of [`ch_binary::write_enum`].
+    #[inline]
+    pub fn read_enum(&mut self, bits: u8) -> Result<i16> {
+        if bits == 8 {
+            Ok(i16::from(self.read_u8()? as i8))
+        } else {
+            Ok(i16::from_le_bytes(self.take(2)?.try_into()?))
+        }
     }
 
     #[inline]
@@ -76,9 +170,20 @@ impl<'a> RowBinCursor<'a> {
     }
     #[inline]
     pub fn read_string(&mut self) -> Result<String> {
-        let len = u32::from_le_bytes(self.take(4)?.try_into()?) as usize;
-        let bytes = self.take(len)?;
-        Ok(String::from_utf8(bytes.to_vec())?)
+        Ok(self.read_str()?.to_owned())
+    }
+
+    /// Borrowing counterpart of [`RowBinCursor::read_string`].
+    #[inline]
+    pub fn read_str(&mut self) -> Result<&'a str> {
+        Ok(std::str::from_utf8(self.read_byte_array()?)?)
+    }
+
+    /// Borrows a `String` or `Array(UInt8)` column as raw bytes.
+    #[inline]
+    pub fn read_byte_array(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_len()?;
+        self.take(len)
     }
 }
 
@@ -93,16 +198,32 @@ pub trait RowBinaryDecode: Sized {
     fn from_rowbinary(cur: &mut RowBinCursor<'_>) -> Result<Self>;
 }
 
+/// Decoding into a view that borrows strings and byte arrays from the input
+/// buffer instead of allocating them.
+pub trait RowBinaryDecodeRef<'a>: Sized {
+    const EVT_HASH: [u8; 32];
+    const FIELD_COUNT: u16;
+
+    fn from_rowbinary_ref(cur: &mut RowBinCursor<'a>) -> Result<Self>;
+
+    /// Borrowed counterpart of [`DecodeQuic::decode_quic`].
+    fn decode_quic_ref(buf: &'a [u8]) -> Result<Self> {
+        let payload = quic_payload(buf, &Self::EVT_HASH, Self::FIELD_COUNT)?;
+        let mut cur = RowBinCursor::new(payload);
+        Self::from_rowbinary_ref(&mut cur)
+    }
+}
+
 pub trait EncodeQuic: RowBinaryEncode {
     const EVT_HASH: [u8; 32];
     const FIELD_COUNT: u16;
 
-    fn encode_quic(&self, out: &mut Vec<u8>) {
+    fn encode_quic(&self, out: &mut Vec<u8>) -> RowBinaryResult {
         out.extend_from_slice(&Self::EVT_HASH);
 
         out.extend_from_slice(&Self::FIELD_COUNT.to_le_bytes());
 
-        self.encode_rowbinary(out);
+        self.encode_rowbinary(out)
     }
 }
 
@@ -111,40 +232,193 @@ pub trait DecodeQuic: RowBinaryDecode {
     const FIELD_COUNT: u16;
 
     fn decode_quic(buf: &[u8]) -> Result<Self> {
-        if buf.len() < 34 {
-            anyhow::bail!("decode_quic: frame too small");
+        let payload = quic_payload(
+            buf,
+            &<Self as DecodeQuic>::EVT_HASH,
+            <Self as DecodeQuic>::FIELD_COUNT,
+        )?;
+        let mut cur = RowBinCursor::new(payload);
+        Self::from_rowbinary(&mut cur)
+    }
+}
+
+/// Column names and ClickHouse types in encode order, for inserts that use
+/// `FORMAT RowBinaryWithNamesAndTypes`.
+pub trait RowBinaryColumns {
+    const COLUMNS: &'static [(&'static str, &'static str)];
+}
+
+/// A `#[derive(BenSchema)]` struct stored inside another's row, either as a
+/// `Tuple` column or flattened into `parent_child` columns. Both are laid out
+/// as the child's own columns back to back.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a nested BenSchema struct",
+    note = "derive BenSchema on `{Self}`, with #[bschema(nested)] if it has no table of its own"
+)]
+pub trait RowBinaryNested: RowBinaryEncode + RowBinaryColumns + Sized {
+    /// `Tuple(name Type, ...)` over [`RowBinaryColumns::COLUMNS`].
+    const TUPLE_TYPE: &'static str;
+
+    fn decode_nested(cur: &mut RowBinCursor<'_>) -> Result<Self>;
+}
+
+/// Header of `RowBinaryWithNamesAndTypes`: a varint column count, then every
+/// column name, then every type name, all as RowBinary strings.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NamesAndTypes {
+    pub columns: Vec<(String, String)>,
+}
+
+impl NamesAndTypes {
+    pub fn of<T: RowBinaryColumns>() -> Self {
+        Self {
+            columns: T::COLUMNS
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.to_string()))
+                .collect(),
         }
+    }
 
-        let (hash_bytes, rest) = buf.split_at(32);
-        if hash_bytes != <Self as DecodeQuic>::EVT_HASH {
-            anyhow::bail!("decode_quic: wrong event hash");
+    pub fn from_schema(schema: &Schema) -> Self {
+        Self {
+            columns: schema
+                .fields
+                .iter()
+                .map(|f| (f.name.clone(), f.ch_type()))
+                .collect(),
         }
+    }
 
-        let (fc_bytes, payload) = rest.split_at(2);
-        let fc = u16::from_le_bytes(fc_bytes.try_into()?);
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
 
-        if fc != <Self as DecodeQuic>::FIELD_COUNT {
-            anyhow::bail!(
-                "decode_quic: field count mismatch (got {}, expected {})",
-                fc,
-                <Self as DecodeQuic>::FIELD_COUNT
-            );
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|(n, _)| n.as_str())
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        ch_binary::write_uvarint(self.columns.len(), out);
+        for (name, _) in &self.columns {
+            ch_binary::write_str(name, out);
+        }
+        for (_, ty) in &self.columns {
+            ch_binary::write_str(ty, out);
         }
+    }
 
-        let mut cur = RowBinCursor::new(payload);
-        Self::from_rowbinary(&mut cur)
+    pub fn read(cur: &mut RowBinCursor<'_>) -> Result<Self> {
+        // every column takes at least two bytes of header
+        let n = cur.read_uvarint()?;
+        ensure!(
+            n <= (cur.remaining() / 2) as u64,
+            "names and types header: {n} columns in {} bytes",
+            cur.remaining()
+        );
+
+        let mut names = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            names.push(cur.read_string()?);
+        }
+        let mut columns = Vec::with_capacity(n as usize);
+        for name in names {
+            columns.push((name, cur.read_string()?));
+        }
+        Ok(Self { columns })
+    }
+}
+
+/// Infra column values the pipeline appends to rows whose struct leaves them
+/// out. Unset values are left to the column default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InfraValues {
+    pub tenant_id: Option<[u8; 16]>,
+    /// Milliseconds since the epoch.
+    pub ingest_ts: Option<i64>,
+    /// Written as `UInt128`, word 0 holding the low bits.
+    pub system_mask: Option<BitSet>,
+    pub struct_mask: Option<BitSet>,
+    pub field_mask: Option<BitSet>,
+}
+
+impl InfraValues {
+    /// Header entries for the set values, in the order [`InfraValues::write`] emits them.
+    pub fn columns(&self) -> Vec<(String, String)> {
+        INFRA_COLUMNS
+            .iter()
+            .filter(|c| self.is_set(c.name))
+            .map(|c| (c.name.to_string(), c.ch_type.to_string()))
+            .collect()
+    }
+
+    /// Appends the set values after a row's own columns.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        if let Some(id) = &self.tenant_id {
+            ch_binary::write_uuid(id, out);
+        }
+        if let Some(ts) = self.ingest_ts {
+            ch_binary::write_datetime64(ts, out);
+        }
+        for mask in [self.system_mask, self.struct_mask, self.field_mask]
+            .into_iter()
+            .flatten()
+        {
+            for word in mask.words() {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
     }
+
+    fn is_set(&self, name: &str) -> bool {
+        match name {
+            "tenant_id" => self.tenant_id.is_some(),
+            "ingest_ts" => self.ingest_ts.is_some(),
+            "system_mask" => self.system_mask.is_some(),
+            "struct_mask" => self.struct_mask.is_some(),
+            "field_mask" => self.field_mask.is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// Checks a quic frame's hash and field count and returns its payload.
+fn quic_payload<'a>(buf: &'a [u8], evt_hash: &[u8; 32], field_count: u16) -> Result<&'a [u8]> {
+    if buf.len() < 34 {
+        anyhow::bail!("decode_quic: frame too small");
+    }
+
+    let (hash_bytes, rest) = buf.split_at(32);
+    if hash_bytes != evt_hash {
+        anyhow::bail!("decode_quic: wrong event hash");
+    }
+
+    let (fc_bytes, payload) = rest.split_at(2);
+    let fc = u16::from_le_bytes(fc_bytes.try_into()?);
+
+    if fc != field_count {
+        anyhow::bail!(
+            "decode_quic: field count mismatch (got {}, expected {})",
+            fc,
+            field_count
+        );
+    }
+
+    Ok(payload)
 }
 
 impl<T: RowBinaryEncode> RowBinaryEncode for Option<T> {
     fn encode_rowbinary(&self, buf: &mut Vec<u8>) -> Result<(), anyhow::Error> {
         match self {
             None => {
-                buf.push(1u8); // null marker
+                ch_binary::write_null_marker(true, buf);
                 Ok(())
             }
             Some(value) => {
-                buf.push(0u8); // not null
+                ch_binary::write_null_marker(false, buf);
                 value.encode_rowbinary(buf)
             }
         }
@@ -155,8 +429,7 @@ impl<T: RowBinaryDecode> RowBinaryDecode for Option<T> {
     const FIELD_COUNT: u16 = T::FIELD_COUNT;
 
     fn from_rowbinary(cur: &mut RowBinCursor<'_>) -> Result<Self, anyhow::Error> {
-        let is_null = cur.take(1)?[0] != 0;
-        if is_null {
+        if cur.read_null_marker()? {
             Ok(None)
         } else {
             Ok(Some(T::from_rowbinary(cur)?))
@@ -166,8 +439,7 @@ impl<T: RowBinaryDecode> RowBinaryDecode for Option<T> {
 
 impl<T: RowBinaryEncode> RowBinaryEncode for Vec<T> {
     fn encode_rowbinary(&self, buf: &mut Vec<u8>) -> Result<(), anyhow::Error> {
-        let len = self.len() as u64;
-        buf.extend_from_slice(&len.to_le_bytes());
+        ch_binary::write_uvarint(self.len(), buf);
 
         for item in self {
             item.encode_rowbinary(buf)?;
@@ -181,7 +453,7 @@ impl<T: RowBinaryDecode> RowBinaryDecode for Vec<T> {
     const FIELD_COUNT: u16 = T::FIELD_COUNT;
 
     fn from_rowbinary(cur: &mut RowBinCursor<'_>) -> Result<Self, anyhow::Error> {
-        let len = cur.read_u64()? as usize;
+        let len = cur.read_len()?;
         let mut out = Vec::with_capacity(len);
         for _ in 0..len {
             out.push(T::from_rowbinary(cur)?);
@@ -201,6 +473,6 @@ impl RowBinaryDecode for u64 {
     const FIELD_COUNT: u16 = 1;
 
     fn from_rowbinary(cur: &mut RowBinCursor<'_>) -> Result<Self, anyhow::Error> {
-        Ok(cur.read_u64()?)
+        cur.read_u64()
     }
 }
@@ -1,17 +1,80 @@
-use crate::{schema::Schema, slot::SlotValue};
+use crate::{
+    error::RowpackError,
+    intern::{StrDict, StrInterner},
+    slot::{SlotMap, SlotSeq, SlotValue},
+};
+
+/// How deep arrays and maps may nest inside one slot.
+pub const MAX_NESTING: usize = 32;
 
 #[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Tag {
     Missing = 0,
     U64 = 1,
     I64 = 2,
     F64 = 3,
     Bool = 4,
+    /// Interned string: u32 LE id. An id equal to the dictionary's next id is
+    /// a definition and is followed by the string in [`Tag::Str`] layout.
     StrId = 5,
     IPv4 = 6,
     IPv6 = 7,
     DT64 = 8,
     Uuid = 9,
+    /// Inline string: u32 LE length, then UTF-8 bytes.
+    Str = 10,
+    U32 = 11,
+    U16 = 12,
+    U8 = 13,
+    I32 = 14,
+    I16 = 15,
+    I8 = 16,
+    F32 = 17,
+    /// i128 LE value, then u32 LE scale.
+    Decimal = 18,
+    Date = 19,
+    Date32 = 20,
+    /// u32 LE length, then raw bytes.
+    Bytes = 21,
+    /// u32 LE element count, u32 LE byte length, then the elements as slots.
+    Array = 22,
+    /// u32 LE entry count, u32 LE byte length, then key and value slots.
+    Map = 23,
+}
+
+impl TryFrom<u8> for Tag {
+    type Error = u8;
+
+    fn try_from(tag: u8) -> Result<Self, u8> {
+        Ok(match tag {
+            0 => Tag::Missing,
+            1 => Tag::U64,
+            2 => Tag::I64,
+            3 => Tag::F64,
+            4 => Tag::Bool,
+            5 => Tag::StrId,
+            6 => Tag::IPv4,
+            7 => Tag::IPv6,
+            8 => Tag::DT64,
+            9 => Tag::Uuid,
+            10 => Tag::Str,
+            11 => Tag::U32,
+            12 => Tag::U16,
+            13 => Tag::U8,
+            14 => Tag::I32,
+            15 => Tag::I16,
+            16 => Tag::I8,
+            17 => Tag::F32,
+            18 => Tag::Decimal,
+            19 => Tag::Date,
+            20 => Tag::Date32,
+            21 => Tag::Bytes,
+            22 => Tag::Array,
+            23 => Tag::Map,
+            other => return Err(other),
+        })
+    }
 }
 
 pub trait BenEncodeRowpack {
@@ -20,73 +83,438 @@ pub trait BenEncodeRowpack {
 
     fn encode_rowpack_payload(&self, out: &mut Vec<u8>);
 
-    fn to_envelope_rowpack(&self, epoch: u64) -> Vec<u8> {
+    fn to_envelope_rowpack(&self, epoch: u64) -> anyhow::Result<Vec<u8>> {
         let mut out = Vec::with_capacity(64);
 
-        crate::envelope::Envelope::write_rowbinary_header(
+        crate::envelope::Envelope::write_header(
             &mut out,
             Self::EVT_HASH,
             epoch,
+            crate::envelope::Encoding::Rowpack,
             Self::FIELD_COUNT,
-        );
+        )?;
 
         self.encode_rowpack_payload(&mut out);
 
-        out
+        Ok(out)
+    }
+
+    /// Same payload as [`BenEncodeRowpack::to_envelope_rowpack`], framed with the v2 header.
+    fn to_frame_rowpack(
+        &self,
+        epoch: u64,
+        flags: crate::envelope::EnvelopeFlags,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut payload = Vec::with_capacity(64);
+        self.encode_rowpack_payload(&mut payload);
+
+        let mut out = Vec::with_capacity(crate::envelope::Envelope::V2_HEADER_LEN + payload.len());
+        crate::envelope::Envelope::write_frame(
+            &mut out,
+            Self::EVT_HASH,
+            epoch,
+            crate::envelope::Encoding::Rowpack,
+            Self::FIELD_COUNT,
+            flags,
+            &payload,
+        )?;
+
+        Ok(out)
     }
 }
 
-pub fn decode_rowpack(mut p: &[u8], fields: usize) -> anyhow::Result<Vec<SlotValue>> {
-    use Tag::*;
-    let mut row = vec![SlotValue::Missing; fields];
-    for id in 0..fields {
-        let tag = p[0];
-        p = &p[1..];
-        row[id] = match tag {
-            x if x == U64 as u8 => {
-                let (n, r) = p.split_at(8);
-                p = r;
-                SlotValue::U64(u64::from_le_bytes(n.try_into()?))
-            }
-            x if x == I64 as u8 => {
-                let (n, r) = p.split_at(8);
-                p = r;
-                SlotValue::I64(i64::from_le_bytes(n.try_into()?))
+fn len_prefix(len: usize) -> Result<[u8; 4], RowpackError> {
+    u32::try_from(len)
+        .map(u32::to_le_bytes)
+        .map_err(|_| RowpackError::TooLong { len })
+}
+
+/// Appends one tagged slot. Fails, writing nothing, when a length overflows
+/// its u32 prefix.
+pub fn encode_slot(slot: &SlotValue, out: &mut Vec<u8>) -> Result<(), RowpackError> {
+    match *slot {
+        SlotValue::Missing => out.push(Tag::Missing as u8),
+        SlotValue::U64(v) => {
+            out.push(Tag::U64 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::I64(v) => {
+            out.push(Tag::I64 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::F64(v) => {
+            out.push(Tag::F64 as u8);
+            out.extend_from_slice(&v.to_bits().to_le_bytes());
+        }
+        SlotValue::U32(v) => {
+            out.push(Tag::U32 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::U16(v) => {
+            out.push(Tag::U16 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::U8(v) => {
+            out.push(Tag::U8 as u8);
+            out.push(v);
+        }
+        SlotValue::I32(v) => {
+            out.push(Tag::I32 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::I16(v) => {
+            out.push(Tag::I16 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::I8(v) => {
+            out.push(Tag::I8 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::F32(v) => {
+            out.push(Tag::F32 as u8);
+            out.extend_from_slice(&v.to_bits().to_le_bytes());
+        }
+        SlotValue::Bool(v) => {
+            out.push(Tag::Bool as u8);
+            out.push(v as u8);
+        }
+        SlotValue::Decimal { value, scale } => {
+            out.push(Tag::Decimal as u8);
+            out.extend_from_slice(&value.to_le_bytes());
+            out.extend_from_slice(&scale.to_le_bytes());
+        }
+        SlotValue::Date(v) => {
+            out.push(Tag::Date as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::Date32(v) => {
+            out.push(Tag::Date32 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::Bytes(b) => {
+            let len = len_prefix(b.len())?;
+            out.push(Tag::Bytes as u8);
+            out.extend_from_slice(&len);
+            out.extend_from_slice(b);
+        }
+        SlotValue::Array(seq) => {
+            let (count, len) = (len_prefix(seq.len())?, len_prefix(seq.as_bytes().len())?);
+            out.push(Tag::Array as u8);
+            out.extend_from_slice(&count);
+            out.extend_from_slice(&len);
+            out.extend_from_slice(seq.as_bytes());
+        }
+        SlotValue::Map(map) => {
+            let (count, len) = (len_prefix(map.len())?, len_prefix(map.as_bytes().len())?);
+            out.push(Tag::Map as u8);
+            out.extend_from_slice(&count);
+            out.extend_from_slice(&len);
+            out.extend_from_slice(map.as_bytes());
+        }
+        SlotValue::Str(s) => {
+            let len = len_prefix(s.len())?;
+            out.push(Tag::Str as u8);
+            out.extend_from_slice(&len);
+            out.extend_from_slice(s.as_bytes());
+        }
+        SlotValue::StrId(id) => {
+            out.push(Tag::StrId as u8);
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        SlotValue::IPv4(v) => {
+            out.push(Tag::IPv4 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::IPv6(v) => {
+            out.push(Tag::IPv6 as u8);
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        SlotValue::DateTime64 { epoch, scale } => {
+            out.push(Tag::DT64 as u8);
+            out.extend_from_slice(&epoch.to_le_bytes());
+            out.extend_from_slice(&scale.to_le_bytes());
+        }
+        SlotValue::Uuid(bytes) => {
+            out.push(Tag::Uuid as u8);
+            out.extend_from_slice(&bytes);
+        }
+    }
+    Ok(())
+}
+
+/// Appends a whole row; the inverse of [`decode_rowpack`].
+pub fn encode_rowpack(row: &[SlotValue], out: &mut Vec<u8>) -> Result<(), RowpackError> {
+    for slot in row {
+        encode_slot(slot, out)?;
+    }
+    Ok(())
+}
+
+/// Like [`encode_slot`], but strings go through `interner`: the first use
+/// writes the definition, later uses only the id. Strings that no longer fit
+/// the dictionary are written inline.
+pub fn encode_slot_interned(
+    slot: &SlotValue,
+    interner: &mut StrInterner,
+    out: &mut Vec<u8>,
+) -> Result<(), RowpackError> {
+    let SlotValue::Str(s) = *slot else {
+        return encode_slot(slot, out);
+    };
+    // Checked before interning so a failed write never defines an id.
+    let len = len_prefix(s.len())?;
+    match interner.intern(s) {
+        Some((id, defined)) => {
+            out.push(Tag::StrId as u8);
+            out.extend_from_slice(&id.to_le_bytes());
+            if defined {
+                out.extend_from_slice(&len);
+                out.extend_from_slice(s.as_bytes());
             }
-            x if x == F64 as u8 => {
-                let (n, r) = p.split_at(8);
-                p = r;
-                SlotValue::F64(f64::from_bits(u64::from_le_bytes(n.try_into()?)))
+            Ok(())
+        }
+        None => encode_slot(slot, out),
+    }
+}
+
+/// Interned counterpart of [`encode_rowpack`]; decode with [`decode_rowpack_interned`].
+/// A row that fails to encode forgets the ids it defined, so a later row
+/// using the same strings defines them again.
+pub fn encode_rowpack_interned(
+    row: &[SlotValue],
+    interner: &mut StrInterner,
+    out: &mut Vec<u8>,
+) -> Result<(), RowpackError> {
+    let committed = interner.len();
+    let encoded = row
+        .iter()
+        .try_for_each(|slot| encode_slot_interned(slot, interner, out));
+    if encoded.is_err() {
+        interner.truncate(committed);
+    }
+    encoded
+}
+
+/// Bounds-checked reader over a rowpack payload. Every read reports the field
+/// index and byte offset it failed at instead of panicking.
+pub struct RowpackCursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RowpackCursor<'a> {
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    #[inline]
+    fn take(&mut self, field: usize, n: usize) -> Result<&'a [u8], RowpackError> {
+        if self.remaining() < n {
+            return Err(RowpackError::Truncated {
+                field,
+                offset: self.pos,
+                needed: n,
+                remaining: self.remaining(),
+            });
+        }
+        let start = self.pos;
+        self.pos += n;
+        Ok(&self.buf[start..self.pos])
+    }
+
+    #[inline]
+    fn take_array<const N: usize>(&mut self, field: usize) -> Result<[u8; N], RowpackError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(field, N)?);
+        Ok(out)
+    }
+
+    /// `offset` is the start of the slot, for error reporting.
+    fn take_str(&mut self, field: usize, offset: usize) -> Result<&'a str, RowpackError> {
+        let len = u32::from_le_bytes(self.take_array(field)?) as usize;
+        let bytes = self.take(field, len)?;
+        std::str::from_utf8(bytes).map_err(|_| RowpackError::InvalidUtf8 { field, offset })
+    }
+
+    /// Reads the slot for `field`; the index is only used for error reporting.
+    /// Interned strings are an error here, use [`RowpackCursor::read_slot_interned`].
+    pub fn read_slot(&mut self, field: usize) -> Result<SlotValue<'a>, RowpackError> {
+        self.read(field, None, 0)
+    }
+
+    /// Reads the slot for `field`, recording string definitions in `dict`.
+    /// Interned strings come back as [`SlotValue::StrId`]. Definitions stay
+    /// even if the row later fails; [`decode_rowpack_interned`] undoes them.
+    pub fn read_slot_interned(
+        &mut self,
+        field: usize,
+        dict: &mut StrDict,
+    ) -> Result<SlotValue<'a>, RowpackError> {
+        self.read(field, Some(dict), 0)
+    }
+
+    fn read(
+        &mut self,
+        field: usize,
+        dict: Option<&mut StrDict>,
+        depth: usize,
+    ) -> Result<SlotValue<'a>, RowpackError> {
+        let offset = self.pos;
+        let raw = self.take_array::<1>(field)?[0];
+        let tag =
+            Tag::try_from(raw).map_err(|tag| RowpackError::UnknownTag { field, offset, tag })?;
+
+        Ok(match tag {
+            Tag::Missing => SlotValue::Missing,
+            Tag::U64 => SlotValue::U64(u64::from_le_bytes(self.take_array(field)?)),
+            Tag::I64 => SlotValue::I64(i64::from_le_bytes(self.take_array(field)?)),
+            Tag::F64 => SlotValue::F64(f64::from_bits(u64::from_le_bytes(self.take_array(field)?))),
+            Tag::U32 => SlotValue::U32(u32::from_le_bytes(self.take_array(field)?)),
+            Tag::U16 => SlotValue::U16(u16::from_le_bytes(self.take_array(field)?)),
+            Tag::U8 => SlotValue::U8(self.take_array::<1>(field)?[0]),
+            Tag::I32 => SlotValue::I32(i32::from_le_bytes(self.take_array(field)?)),
+            Tag::I16 => SlotValue::I16(i16::from_le_bytes(self.take_array(field)?)),
+            Tag::I8 => SlotValue::I8(i8::from_le_bytes(self.take_array(field)?)),
+            Tag::F32 => SlotValue::F32(f32::from_bits(u32::from_le_bytes(self.take_array(field)?))),
+            Tag::Bool => SlotValue::Bool(self.take_array::<1>(field)?[0] != 0),
+            Tag::Decimal => {
+                let value = i128::from_le_bytes(self.take_array(field)?);
+                let scale = u32::from_le_bytes(self.take_array(field)?);
+                SlotValue::Decimal { value, scale }
             }
-            x if x == Bool as u8 => {
-                let b = p[0];
-                p = &p[1..];
-                SlotValue::Bool(b != 0)
+            Tag::Date => SlotValue::Date(u16::from_le_bytes(self.take_array(field)?)),
+            Tag::Date32 => SlotValue::Date32(i32::from_le_bytes(self.take_array(field)?)),
+            Tag::Bytes => {
+                let len = u32::from_le_bytes(self.take_array(field)?) as usize;
+                SlotValue::Bytes(self.take(field, len)?)
             }
-            x if x == IPv4 as u8 => {
-                let (n, r) = p.split_at(4);
-                p = r;
-                SlotValue::IPv4(u32::from_le_bytes(n.try_into()?))
+            Tag::Array => {
+                let (len, bytes) = self.take_nested(field, offset, depth, 1)?;
+                SlotValue::Array(SlotSeq::new(len, bytes))
             }
-            x if x == IPv6 as u8 => {
-                let (n, r) = p.split_at(16);
-                p = r;
-                SlotValue::IPv6(u128::from_le_bytes(n.try_into()?))
+            Tag::Map => {
+                let (len, bytes) = self.take_nested(field, offset, depth, 2)?;
+                SlotValue::Map(SlotMap::new(len, bytes))
             }
-            x if x == DT64 as u8 => {
-                let (n, r) = p.split_at(12);
-                p = r;
-                let epoch = i64::from_le_bytes(n[0..8].try_into()?);
-                let scale = u32::from_le_bytes(n[8..12].try_into()?);
+            Tag::IPv4 => SlotValue::IPv4(u32::from_le_bytes(self.take_array(field)?)),
+            Tag::IPv6 => SlotValue::IPv6(u128::from_le_bytes(self.take_array(field)?)),
+            Tag::DT64 => {
+                let epoch = i64::from_le_bytes(self.take_array(field)?);
+                let scale = u32::from_le_bytes(self.take_array(field)?);
                 SlotValue::DateTime64 { epoch, scale }
             }
-            x if x == Uuid as u8 => {
-                let (n, r) = p.split_at(16);
-                p = r;
-                SlotValue::Uuid(n.try_into()?)
+            Tag::Uuid => SlotValue::Uuid(self.take_array(field)?),
+            Tag::Str => SlotValue::Str(self.take_str(field, offset)?),
+            Tag::StrId => {
+                let dict = dict.ok_or(RowpackError::NoDictionary { field, offset })?;
+                let id = u32::from_le_bytes(self.take_array(field)?);
+                let defined = dict.next_id();
+                if id == defined {
+                    let s = self.take_str(field, offset)?;
+                    dict.define(s)
+                        .ok_or(RowpackError::DictionaryFull { field, offset })?;
+                } else if id > defined {
+                    return Err(RowpackError::UnknownStrId {
+                        field,
+                        offset,
+                        id,
+                        defined,
+                    });
+                }
+                SlotValue::StrId(id)
             }
-            _ => SlotValue::Missing,
-        };
+        })
+    }
+
+    /// Reads the count and body of an array or map and checks that the body
+    /// holds exactly `len * per_entry` well-formed slots.
+    fn take_nested(
+        &mut self,
+        field: usize,
+        offset: usize,
+        depth: usize,
+        per_entry: u64,
+    ) -> Result<(u32, &'a [u8]), RowpackError> {
+        if depth >= MAX_NESTING {
+            return Err(RowpackError::TooDeep { field, offset });
+        }
+        let len = u32::from_le_bytes(self.take_array(field)?);
+        let byte_len = u32::from_le_bytes(self.take_array(field)?) as usize;
+
+        // every slot takes at least its tag byte
+        let slots = len as u64 * per_entry;
+        if slots > byte_len as u64 {
+            return Err(RowpackError::BadNesting { field, offset });
+        }
+
+        let start = self.pos;
+        let body = self.take(field, byte_len)?;
+        let mut inner = RowpackCursor::new(body);
+        for _ in 0..slots {
+            inner
+                .read(field, None, depth + 1)
+                .map_err(|e| e.shifted(start))?;
+        }
+        if inner.remaining() != 0 {
+            return Err(RowpackError::BadNesting { field, offset });
+        }
+        Ok((len, body))
+    }
+
+    /// Errors unless every byte of the payload has been consumed.
+    pub fn finish(&self) -> Result<(), RowpackError> {
+        match self.remaining() {
+            0 => Ok(()),
+            count => Err(RowpackError::TrailingBytes {
+                offset: self.pos,
+                count,
+            }),
+        }
+    }
+}
+
+/// Decodes exactly `fields` slots and rejects any bytes left over.
+pub fn decode_rowpack(p: &[u8], fields: usize) -> Result<Vec<SlotValue<'_>>, RowpackError> {
+    let mut cur = RowpackCursor::new(p);
+    let mut row = Vec::with_capacity(fields);
+    for field in 0..fields {
+        row.push(cur.read_slot(field)?);
     }
+    cur.finish()?;
     Ok(row)
 }
+
+/// Like [`decode_rowpack`], resolving string definitions through the stream's `dict`.
+/// The row's definitions are only kept once the whole row has decoded, so a
+/// rejected row leaves `dict` as it was.
+pub fn decode_rowpack_interned<'a>(
+    p: &'a [u8],
+    fields: usize,
+    dict: &mut StrDict,
+) -> Result<Vec<SlotValue<'a>>, RowpackError> {
+    let committed = dict.len();
+    let decoded = (|| {
+        let mut cur = RowpackCursor::new(p);
+        let mut row = Vec::with_capacity(fields);
+        for field in 0..fields {
+            row.push(cur.read_slot_interned(field, dict)?);
+        }
+        cur.finish()?;
+        Ok(row)
+    })();
+    if decoded.is_err() {
+        dict.truncate(committed);
+    }
+    decoded
+}
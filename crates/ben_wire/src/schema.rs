@@ -1,4 +1,9 @@
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+use crate::{
+    bits::{BitSet, DEFAULT_WORDS},
+    error::SchemaError,
+};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Schema {
     pub event: String,
     pub version: u32,
@@ -6,25 +11,344 @@ pub struct Schema {
     pub fields: Vec<Field>,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Field {
     pub name: String,
     pub ty: FieldType,
     pub nullable: bool,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum FieldType {
+    UInt128,
     UInt64,
+    UInt32,
+    UInt16,
+    UInt8,
     Int64,
+    Int32,
+    Int16,
+    Int8,
     Float64,
+    Float32,
+    Decimal {
+        precision: u8,
+        scale: u8,
+    },
     Bool,
     String,
+    /// A `String` column read and written as raw bytes.
+    Bytes,
     IPv4,
     IPv6,
-    DateTime64 { scale: u32 },
+    Date,
+    Date32,
+    DateTime64 {
+        scale: u32,
+    },
     Uuid,
-    Str,
+    /// `(label, value)` in declaration order; stored as an `Int8`.
+    Enum8(Vec<(String, i8)>),
+    /// `(label, value)` in declaration order; stored as an `Int16`.
+    Enum16(Vec<(String, i16)>),
+    LowCardinality(Box<FieldType>),
+    /// Only for elements of arrays and maps; top-level columns use
+    /// [`Field::nullable`].
+    Nullable(Box<FieldType>),
+    Array(Box<FieldType>),
+    Map(Box<FieldType>, Box<FieldType>),
+    /// Element names are empty for an unnamed `Tuple(T1, T2)`.
+    Tuple(Vec<(String, FieldType)>),
+}
+
+impl FieldType {
+    /// ClickHouse type name, without `Nullable`.
+    pub fn ch_type(&self) -> String {
+        match self {
+            FieldType::UInt128 => "UInt128".into(),
+            FieldType::UInt64 => "UInt64".into(),
+            FieldType::UInt32 => "UInt32".into(),
+            FieldType::UInt16 => "UInt16".into(),
+            FieldType::UInt8 => "UInt8".into(),
+            FieldType::Int64 => "Int64".into(),
+            FieldType::Int32 => "Int32".into(),
+            FieldType::Int16 => "Int16".into(),
+            FieldType::Int8 => "Int8".into(),
+            FieldType::Float64 => "Float64".into(),
+            FieldType::Float32 => "Float32".into(),
+            FieldType::Decimal { precision, scale } => format!("Decimal({precision}, {scale})"),
+            FieldType::Bool => "Bool".into(),
+            FieldType::String | FieldType::Bytes => "String".into(),
+            FieldType::IPv4 => "IPv4".into(),
+            FieldType::IPv6 => "IPv6".into(),
+            FieldType::Date => "Date".into(),
+            FieldType::Date32 => "Date32".into(),
+            FieldType::DateTime64 { scale } => format!("DateTime64({scale})"),
+            FieldType::Uuid => "UUID".into(),
+            FieldType::Enum8(variants) => enum_ch_type("Enum8", variants),
+            FieldType::Enum16(variants) => enum_ch_type("Enum16", variants),
+            FieldType::LowCardinality(inner) => format!("LowCardinality({})", inner.ch_type()),
+            FieldType::Nullable(inner) => format!("Nullable({})", inner.ch_type()),
+            FieldType::Array(inner) => format!("Array({})", inner.ch_type()),
+            FieldType::Map(k, v) => format!("Map({}, {})", k.ch_type(), v.ch_type()),
+            FieldType::Tuple(elems) => {
+                let elems: Vec<String> = elems
+                    .iter()
+                    .map(|(name, ty)| {
+                        if name.is_empty() {
+                            ty.ch_type()
+                        } else {
+                            format!("{name} {}", ty.ch_type())
+                        }
+                    })
+                    .collect();
+                format!("Tuple({})", elems.join(", "))
+            }
+        }
+    }
+
+    /// The type values are stored as: `LowCardinality` is only a column hint.
+    pub fn storage(&self) -> &FieldType {
+        match self {
+            FieldType::LowCardinality(inner) => inner.storage(),
+            other => other,
+        }
+    }
+}
+
+impl std::str::FromStr for FieldType {
+    type Err = SchemaError;
+
+    /// Inverse of [`FieldType::ch_type`]. `Nullable` is accepted so array and
+    /// map elements round-trip; columns go through [`Field::from_ch_type`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || SchemaError::UnknownType(s.to_string());
+        let s = s.trim();
+        let (name, args) = match s.find('(') {
+            Some(open) if s.ends_with(')') => {
+                (s[..open].trim(), split_args(&s[open + 1..s.len() - 1]))
+            }
+            Some(_) => return Err(unknown()),
+            None => (s, Vec::new()),
+        };
+
+        let ty = match (name, args.as_slice()) {
+            ("UInt128", []) => FieldType::UInt128,
+            ("UInt64", []) => FieldType::UInt64,
+            ("UInt32", []) => FieldType::UInt32,
+            ("UInt16", []) => FieldType::UInt16,
+            ("UInt8", []) => FieldType::UInt8,
+            ("Int64", []) => FieldType::Int64,
+            ("Int32", []) => FieldType::Int32,
+            ("Int16", []) => FieldType::Int16,
+            ("Int8", []) => FieldType::Int8,
+            ("Float64", []) => FieldType::Float64,
+            ("Float32", []) => FieldType::Float32,
+            ("Decimal", [precision, scale]) => FieldType::Decimal {
+                precision: precision.parse().map_err(|_| unknown())?,
+                scale: scale.parse().map_err(|_| unknown())?,
+            },
+            ("Bool", []) => FieldType::Bool,
+            ("String", []) => FieldType::String,
+            ("IPv4", []) => FieldType::IPv4,
+            ("IPv6", []) => FieldType::IPv6,
+            ("Date", []) => FieldType::Date,
+            ("Date32", []) => FieldType::Date32,
+            // The optional timezone does not change the stored value.
+            ("DateTime64", [scale] | [scale, _]) => FieldType::DateTime64 {
+                scale: scale.parse().map_err(|_| unknown())?,
+            },
+            ("UUID", []) => FieldType::Uuid,
+            ("Enum8", variants) if !variants.is_empty() => {
+                FieldType::Enum8(enum_variants(variants)?)
+            }
+            ("Enum16", variants) if !variants.is_empty() => {
+                FieldType::Enum16(enum_variants(variants)?)
+            }
+            ("LowCardinality", [inner]) => FieldType::LowCardinality(Box::new(inner.parse()?)),
+            ("Nullable", [inner]) => FieldType::Nullable(Box::new(inner.parse()?)),
+            ("Array", [inner]) => FieldType::Array(Box::new(inner.parse()?)),
+            ("Map", [k, v]) => FieldType::Map(Box::new(k.parse()?), Box::new(v.parse()?)),
+            ("Tuple", elems) if !elems.is_empty() => FieldType::Tuple(
+                elems
+                    .iter()
+                    .copied()
+                    .map(tuple_element)
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(unknown()),
+        };
+        Ok(ty)
+    }
+}
+
+/// `name Type` or a bare `Type`.
+fn tuple_element(s: &str) -> Result<(String, FieldType), SchemaError> {
+    if let Ok(ty) = s.parse() {
+        return Ok((String::new(), ty));
+    }
+    let (name, ty) = s
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| SchemaError::UnknownType(s.to_string()))?;
+    Ok((name.trim_matches('`').to_string(), ty.parse()?))
+}
+
+/// `Enum8('a' = 1, 'it\'s' = 2)`: labels are quoted, with `\` escaping
+/// `'` and `\`.
+fn enum_ch_type<T: std::fmt::Display>(name: &str, variants: &[(String, T)]) -> String {
+    let variants: Vec<String> = variants
+        .iter()
+        .map(|(label, value)| {
+            let label = label.replace('\\', "\\\\").replace('\'', "\\'");
+            format!("'{label}' = {value}")
+        })
+        .collect();
+    format!("{name}({})", variants.join(", "))
+}
+
+/// The `'label' = value` arguments of an `Enum8`/`Enum16`.
+fn enum_variants<T: std::str::FromStr>(args: &[&str]) -> Result<Vec<(String, T)>, SchemaError> {
+    args.iter()
+        .map(|arg| {
+            let unknown = || SchemaError::UnknownType(arg.to_string());
+            let quoted = arg.strip_prefix('\'').ok_or_else(unknown)?;
+            let (mut label, mut escaped) = (String::new(), false);
+            let mut rest = None;
+            for (i, c) in quoted.char_indices() {
+                match c {
+                    _ if escaped => {
+                        label.push(c);
+                        escaped = false;
+                    }
+                    '\\' => escaped = true,
+                    '\'' => {
+                        rest = Some(&quoted[i + 1..]);
+                        break;
+                    }
+                    _ => label.push(c),
+                }
+            }
+            let value = rest
+                .and_then(|r| r.trim_start().strip_prefix('='))
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or_else(unknown)?;
+            Ok((label, value))
+        })
+        .collect()
+}
+
+/// Splits `a, Map(b, c), 'x,y'` at its top-level commas.
+fn split_args(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut quoted, mut escaped, mut start) = (0usize, false, false, 0);
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                out.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(s[start..].trim());
+    out
+}
+
+impl Field {
+    /// Column from its ClickHouse type; a top-level `Nullable` sets [`Field::nullable`].
+    pub fn from_ch_type(name: impl Into<String>, ch_type: &str) -> Result<Self, SchemaError> {
+        let (ty, nullable) = match ch_type.parse()? {
+            FieldType::Nullable(inner) => (*inner, true),
+            FieldType::LowCardinality(inner) => match *inner {
+                FieldType::Nullable(inner) => (FieldType::LowCardinality(inner), true),
+                inner => (FieldType::LowCardinality(Box::new(inner)), false),
+            },
+            ty => (ty, false),
+        };
+        Ok(Field {
+            name: name.into(),
+            ty,
+            nullable,
+        })
+    }
+
+    pub fn ch_type(&self) -> String {
+        if let (true, FieldType::LowCardinality(inner)) = (self.nullable, &self.ty) {
+            // ClickHouse only accepts `Nullable` inside `LowCardinality`.
+            format!("LowCardinality(Nullable({}))", inner.ch_type())
+        } else if self.nullable {
+            format!("Nullable({})", self.ty.ch_type())
+        } else {
+            self.ty.ch_type()
+        }
+    }
+}
+
+/// A column every stored table carries besides the struct's own fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfraColumn {
+    pub name: &'static str,
+    pub ch_type: &'static str,
+    /// `{version}` stands for the table's schema version.
+    pub default: &'static str,
+}
+
+impl InfraColumn {
+    pub fn default_expr(&self, version: u32) -> String {
+        self.default.replace("{version}", &version.to_string())
+    }
+}
+
+/// ClickHouse type of the mask columns: one default-width [`BitSet`].
+pub const MASK_CH_TYPE: &str = "UInt128";
+const _: () = assert!(BitSet::<DEFAULT_WORDS>::WIDTH == 128);
+
+/// Infra columns in table order. A struct may declare a field with the same
+/// name to fill one in itself; otherwise the column takes its default unless
+/// the pipeline supplies a value, see [`crate::rowbinary::InfraValues`].
+pub const INFRA_COLUMNS: &[InfraColumn] = &[
+    InfraColumn {
+        name: "tenant_id",
+        ch_type: "UUID",
+        default: "toUUID('00000000-0000-0000-0000-000000000000')",
+    },
+    InfraColumn {
+        name: "ingest_ts",
+        ch_type: "DateTime64(3)",
+        default: "now64(3)",
+    },
+    InfraColumn {
+        name: "schema_version",
+        ch_type: "UInt32",
+        default: "{version}",
+    },
+    InfraColumn {
+        name: "system_mask",
+        ch_type: MASK_CH_TYPE,
+        default: "0",
+    },
+    InfraColumn {
+        name: "struct_mask",
+        ch_type: MASK_CH_TYPE,
+        default: "0",
+    },
+    InfraColumn {
+        name: "field_mask",
+        ch_type: MASK_CH_TYPE,
+        default: "0",
+    },
+];
+
+pub fn infra_column(name: &str) -> Option<&'static InfraColumn> {
+    INFRA_COLUMNS.iter().find(|c| c.name == name)
 }
 
 impl Schema {
@@ -0,0 +1,484 @@
+//! Compile-time assembly of schema text. A `#[derive(BenSchema)]` struct
+//! with nested columns cannot see its children's fields, so its DDL, manifest
+//! and hashes are built here in const context from the children's
+//! [`RowBinaryColumns::COLUMNS`](crate::rowbinary::RowBinaryColumns::COLUMNS).
+//!
+//! Every text comes in two steps, because the buffer length has to be a const
+//! before the buffer can be filled:
+//!
+//! ```ignore
+//! const PIECES: &[Piece] = &[Piece::Str("Array("), Piece::Str(Child::TUPLE_TYPE), Piece::Str(")")];
+//! const BUF: [u8; text_len(PIECES)] = text(PIECES);
+//! const TEXT: &str = as_str(&BUF);
+//! ```
+
+use crate::schema::INFRA_COLUMNS;
+
+/// One span of a text.
+#[derive(Debug, Clone, Copy)]
+pub enum Piece {
+    /// Copied as is.
+    Str(&'static str),
+    /// Written as a quoted JSON string.
+    JsonStr(&'static str),
+    /// The manifest `enum_map` of a column type, see [`Buf::push_enum_map`].
+    EnumMap(&'static str),
+    /// Every column, with `prefix` before its name, in the given layout.
+    Columns {
+        prefix: &'static str,
+        columns: &'static [(&'static str, &'static str)],
+        layout: Layout,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Layout {
+    /// `name Type, ...`, the element list of a named `Tuple`.
+    Tuple,
+    /// One `CREATE TABLE` line per column, with `suffix` after the type.
+    Ddl { suffix: &'static str },
+    /// One manifest column object per column.
+    Json,
+    /// Names back to back, for [`columns`].
+    Names,
+}
+
+/// A group of `(name, type)` columns in a `COLUMNS` list.
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnGroup {
+    One(&'static str, &'static str),
+    /// A flattened child: its columns, named `prefix` + child column name.
+    Prefixed {
+        prefix: &'static str,
+        columns: &'static [(&'static str, &'static str)],
+    },
+}
+
+struct Buf<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl Buf<'_> {
+    /// Counts only while `out` is empty.
+    const fn push(&mut self, s: &[u8]) {
+        if !self.out.is_empty() {
+            let mut i = 0;
+            while i < s.len() {
+                self.out[self.len + i] = s[i];
+                i += 1;
+            }
+        }
+        self.len += s.len();
+    }
+
+    const fn push_json(&mut self, s: &str) {
+        let s = s.as_bytes();
+        self.push(b"\"");
+        let mut i = 0;
+        while i < s.len() {
+            if s[i] == b'"' || s[i] == b'\\' {
+                self.push(b"\\");
+            }
+            self.push(std::slice::from_ref(&s[i]));
+            i += 1;
+        }
+        self.push(b"\"");
+    }
+
+    /// `{"label": value, ...}` for the `Enum8`/`Enum16` in column type `ty`,
+    /// or `null`. An enum inside a `Tuple` belongs to the element, not the column.
+    const fn push_enum_map(&mut self, ty: &str) {
+        let ty = ty.as_bytes();
+        let Some(mut i) = enum_args(ty) else {
+            self.push(b"null");
+            return;
+        };
+        self.push(b"{");
+        while ty[i] != b')' {
+            if ty[i] == b',' {
+                self.push(b", ");
+            }
+            while ty[i] != b'\'' {
+                i += 1;
+            }
+            // `'label'`, unescaping `\'` and `\\`.
+            i += 1;
+            self.push(b"\"");
+            while ty[i] != b'\'' {
+                if ty[i] == b'\\' {
+                    i += 1;
+                }
+                if ty[i] == b'"' || ty[i] == b'\\' {
+                    self.push(b"\\");
+                }
+                self.push(std::slice::from_ref(&ty[i]));
+                i += 1;
+            }
+            self.push(b"\": ");
+            // ` = value`
+            while ty[i] != b'-' && !ty[i].is_ascii_digit() {
+                i += 1;
+            }
+            let start = i;
+            while ty[i] == b'-' || ty[i].is_ascii_digit() {
+                i += 1;
+            }
+            self.push(ty.split_at(i).0.split_at(start).1);
+        }
+        self.push(b"}");
+    }
+
+    const fn push_columns(&mut self, prefix: &str, columns: &[(&str, &str)], layout: Layout) {
+        let mut i = 0;
+        while i < columns.len() {
+            let (name, ty) = columns[i];
+            match layout {
+                Layout::Tuple => {
+                    if i > 0 {
+                        self.push(b", ");
+                    }
+                    self.push(prefix.as_bytes());
+                    self.push(name.as_bytes());
+                    self.push(b" ");
+                    self.push(ty.as_bytes());
+                }
+                Layout::Ddl { suffix } => {
+                    if i > 0 {
+                        self.push(b",\n");
+                    }
+                    self.push(b"  `");
+                    self.push(prefix.as_bytes());
+                    self.push(name.as_bytes());
+                    self.push(b"` ");
+                    self.push(ty.as_bytes());
+                    self.push(suffix.as_bytes());
+                }
+                Layout::Json => {
+                    if i > 0 {
+                        self.push(b",\n    ");
+                    }
+                    // Keys in the order serde_json writes the other columns.
+                    self.push(b"{\"ben_meta\": false, \"ch_type\": ");
+                    self.push_json(ty);
+                    self.push(b", \"default\": null, \"enum_map\": ");
+                    self.push_enum_map(ty);
+                    self.push(b", \"infra\": false, ");
+                    self.push(b"\"key\": false, \"name\": \"");
+                    self.push(prefix.as_bytes());
+                    self.push(name.as_bytes());
+                    self.push(b"\", \"nullable\": ");
+                    self.push(if is_nullable(ty) { b"true" } else { b"false" });
+                    self.push(b", \"rust_type\": null, \"tags\": false, \"ttl\": null, ");
+                    self.push(b"\"unique\": false}");
+                }
+                Layout::Names => {
+                    self.push(prefix.as_bytes());
+                    self.push(name.as_bytes());
+                }
+            }
+            i += 1;
+        }
+    }
+
+    const fn push_pieces(&mut self, pieces: &[Piece]) {
+        let mut i = 0;
+        while i < pieces.len() {
+            match pieces[i] {
+                Piece::Str(s) => self.push(s.as_bytes()),
+                Piece::JsonStr(s) => self.push_json(s),
+                Piece::EnumMap(ty) => self.push_enum_map(ty),
+                Piece::Columns {
+                    prefix,
+                    columns,
+                    layout,
+                } => self.push_columns(prefix, columns, layout),
+            }
+            i += 1;
+        }
+    }
+}
+
+const fn starts_with(s: &[u8], prefix: &[u8]) -> bool {
+    if s.len() < prefix.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if s[i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Where the `'label' = value` list of the first `Enum8`/`Enum16` in `ty`
+/// starts, unless a `Tuple` comes first.
+const fn enum_args(ty: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i < ty.len() {
+        let rest = ty.split_at(i).1;
+        if starts_with(rest, b"Tuple(") {
+            return None;
+        }
+        if starts_with(rest, b"Enum8(") {
+            return Some(i + 6);
+        }
+        if starts_with(rest, b"Enum16(") {
+            return Some(i + 7);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// `Nullable(T)`, also inside `LowCardinality`.
+pub const fn is_nullable(ty: &str) -> bool {
+    let ty = ty.as_bytes();
+    starts_with(ty, b"Nullable(") || starts_with(ty, b"LowCardinality(Nullable(")
+}
+
+pub const fn text_len(pieces: &[Piece]) -> usize {
+    let mut buf = Buf {
+        out: &mut [],
+        len: 0,
+    };
+    buf.push_pieces(pieces);
+    buf.len
+}
+
+pub const fn text<const N: usize>(pieces: &[Piece]) -> [u8; N] {
+    let mut out = [0u8; N];
+    let mut buf = Buf {
+        out: &mut out,
+        len: 0,
+    };
+    buf.push_pieces(pieces);
+    assert!(buf.len == N, "schema text length mismatch");
+    out
+}
+
+pub const fn as_str(bytes: &'static [u8]) -> &'static str {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => panic!("schema text is not UTF-8"),
+    }
+}
+
+pub const fn columns_len(groups: &[ColumnGroup]) -> usize {
+    let mut n = 0;
+    let mut i = 0;
+    while i < groups.len() {
+        n += match groups[i] {
+            ColumnGroup::One(..) => 1,
+            ColumnGroup::Prefixed { columns, .. } => columns.len(),
+        };
+        i += 1;
+    }
+    n
+}
+
+/// The [`Layout::Names`] pieces of every prefixed group, for [`columns`].
+pub const fn names_len(groups: &[ColumnGroup]) -> usize {
+    let mut buf = Buf {
+        out: &mut [],
+        len: 0,
+    };
+    push_names(&mut buf, groups);
+    buf.len
+}
+
+pub const fn names<const N: usize>(groups: &[ColumnGroup]) -> [u8; N] {
+    let mut out = [0u8; N];
+    let mut buf = Buf {
+        out: &mut out,
+        len: 0,
+    };
+    push_names(&mut buf, groups);
+    out
+}
+
+const fn push_names(buf: &mut Buf<'_>, groups: &[ColumnGroup]) {
+    let mut i = 0;
+    while i < groups.len() {
+        if let ColumnGroup::Prefixed { prefix, columns } = groups[i] {
+            buf.push_columns(prefix, columns, Layout::Names);
+        }
+        i += 1;
+    }
+}
+
+/// The flat `COLUMNS` list; prefixed names are sliced out of `names`, the
+/// output of [`names`] for the same groups.
+pub const fn columns<const N: usize>(
+    groups: &[ColumnGroup],
+    names: &'static str,
+) -> [(&'static str, &'static str); N] {
+    let mut out = [("", ""); N];
+    let (mut n, mut rest) = (0, names);
+    let mut i = 0;
+    while i < groups.len() {
+        match groups[i] {
+            ColumnGroup::One(name, ty) => {
+                out[n] = (name, ty);
+                n += 1;
+            }
+            ColumnGroup::Prefixed { prefix, columns } => {
+                let mut j = 0;
+                while j < columns.len() {
+                    let (name, tail) = rest.split_at(prefix.len() + columns[j].0.len());
+                    let mut k = 0;
+                    while k < INFRA_COLUMNS.len() {
+                        assert!(
+                            !eq(name, INFRA_COLUMNS[k].name),
+                            "flattened column name taken by an infra column"
+                        );
+                        k += 1;
+                    }
+                    out[n] = (name, columns[j].1);
+                    rest = tail;
+                    n += 1;
+                    j += 1;
+                }
+            }
+        }
+        i += 1;
+    }
+    assert!(n == N, "schema column count mismatch");
+
+    // A flattened name can collide with a sibling.
+    let mut i = 0;
+    while i < N {
+        let mut j = i + 1;
+        while j < N {
+            assert!(!eq(out[i].0, out[j].0), "duplicate column name");
+            j += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+const fn eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && starts_with(a.as_bytes(), b.as_bytes())
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256, the same digest `sha2` gives at runtime.
+pub const fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // Message, 0x80, zero padding, then the bit length, in 64-byte blocks.
+    let blocks = (data.len() + 9).div_ceil(64);
+    let bit_len = (data.len() as u64).wrapping_mul(8).to_be_bytes();
+    let mut b = 0;
+    while b < blocks {
+        let mut w = [0u32; 64];
+        let mut t = 0;
+        while t < 16 {
+            let mut word = 0u32;
+            let mut k = 0;
+            while k < 4 {
+                let i = b * 64 + t * 4 + k;
+                let byte = if i < data.len() {
+                    data[i]
+                } else if i == data.len() {
+                    0x80
+                } else if i >= blocks * 64 - 8 {
+                    bit_len[i - (blocks * 64 - 8)]
+                } else {
+                    0
+                };
+                word = (word << 8) | byte as u32;
+                k += 1;
+            }
+            w[t] = word;
+            t += 1;
+        }
+        while t < 64 {
+            let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+            let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+            w[t] = w[t - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[t - 7])
+                .wrapping_add(s1);
+            t += 1;
+        }
+
+        let [mut a, mut bb, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        t = 0;
+        while t < 64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[t])
+                .wrapping_add(w[t]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & bb) ^ (a & c) ^ (bb & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = bb;
+            bb = a;
+            a = t1.wrapping_add(t2);
+            t += 1;
+        }
+        let add = [a, bb, c, d, e, f, g, hh];
+        let mut i = 0;
+        while i < 8 {
+            h[i] = h[i].wrapping_add(add[i]);
+            i += 1;
+        }
+        b += 1;
+    }
+
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 8 {
+        let bytes = h[i].to_be_bytes();
+        let mut k = 0;
+        while k < 4 {
+            out[i * 4 + k] = bytes[k];
+            k += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `sha256:` and the lowercase hex digest, as in `__BEN_SCHEMA_FINGERPRINT`.
+pub const fn fingerprint(digest: &[u8; 32]) -> [u8; 71] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = [0u8; 71];
+    let prefix = b"sha256:";
+    let mut i = 0;
+    while i < prefix.len() {
+        out[i] = prefix[i];
+        i += 1;
+    }
+    i = 0;
+    while i < 32 {
+        out[7 + i * 2] = HEX[(digest[i] >> 4) as usize];
+        out[8 + i * 2] = HEX[(digest[i] & 0xf) as usize];
+        i += 1;
+    }
+    out
+}
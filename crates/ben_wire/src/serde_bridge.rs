@@ -1,189 +1,1099 @@
-// use crate::slot::{SlotValue, conv};
-// use serde::de::{self, IntoDeserializer};
-
-// macro_rules! visit_num {
-//     ($meth:ident, $ty:ty, $visit:ident, $conv:ident) => {
-//         fn $meth<V>(self, v: V) -> Result<V::Value, Self::Error>
-//         where
-//             V: serde::de::Visitor<'de>,
-//         {
-//             let x: $ty = conv::$conv(self.0)?;
-//             v.$visit(x)
-//         }
-//     };
-// }
-
-// pub struct SlotValueDeserializer(pub SlotValue);
-// impl<'de> serde::de::Deserializer<'de> for SlotValueDeserializer {
-//     type Error = de::value::Error;
-//     visit_num!(deserialize_u64, u64, visit_u64, to_u64);
-//     visit_num!(deserialize_i64, i64, visit_i64, to_i64);
-//     visit_num!(deserialize_f64, f64, visit_f64, to_f64);
-
-//     fn deserialize_bool<V>(self, v: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         match self.0 {
-//             SlotValue::Bool(b) => v.visit_bool(b),
-//             _ => Err(de::Error::custom("bool")),
-//         }
-//     }
-
-//     fn deserialize_enum<V>(
-//         self,
-//         _name: &str,
-//         _variants: &'static [&'static str],
-//         visitor: V,
-//     ) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         use SlotValue::*;
-//         match self.0 {
-//             Str(s) => visitor.visit_enum(s.into_deserializer()),
-//             U64(x) => {
-//                 let v: u32 = x
-//                     .try_into()
-//                     .map_err(|_| de::Error::custom("enum index out of range (u64->u32)"))?;
-//                 visitor.visit_enum(v.into_deserializer())
-//             }
-//             I64(x) => {
-//                 let v: i32 = x
-//                     .try_into()
-//                     .map_err(|_| de::Error::custom("enum index out of range (i64->i32)"))?;
-//                 let as_u32: u32 = v
-//                     .try_into()
-//                     .map_err(|_| de::Error::custom("enum index out of range (i32u32)"))?;
-//                 visitor.visit_enum(as_u32.into_deserializer())
-//             }
-//             _ => Err(de::Error::custom("enum from non-scalar SlotValue")),
-//         }
-//     }
-
-//     fn deserialize_string<V>(self, v: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         match self.0 {
-//             SlotValue::Str(s) => v.visit_string(s.to_owned()),
-//             SlotValue::StrId(_) => Err(de::Error::custom("interner not wired")),
-//             _ => Err(de::Error::custom("string")),
-//         }
-//     }
-//     fn deserialize_str<V>(self, v: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         match self.0 {
-//             SlotValue::Str(s) => v.visit_borrowed_str(s),
-//             _ => Err(de::Error::custom("&str")),
-//         }
-//     }
-//     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         match self.0 {
-//             SlotValue::Missing => visitor.visit_none(),
-//             _ => visitor.visit_some(SlotValueDeserializer(self.0)),
-//         }
-//     }
-
-//     serde::forward_to_deserialize_any! {
-//         i8 i16 i32 u8 u16 u32 f32 char bytes byte_buf
-//         unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier ignored_any
-//     }
-
-//     fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: de::Visitor<'de>,
-//     {
-//         let _ = visitor;
-//         Err(de::Error::custom("i128 is not supported"))
-//     }
-
-//     fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: de::Visitor<'de>,
-//     {
-//         let _ = visitor;
-//         Err(de::Error::custom("u128 is not supported"))
-//     }
-
-//     fn is_human_readable(&self) -> bool {
-//         true
-//     }
-
-//     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: de::Visitor<'de>,
-//     {
-//         use SlotValue::*;
-//         match self.0 {
-//             U64(x) => visitor.visit_u64(x),
-//             I64(x) => visitor.visit_i64(x),
-//             F64(x) => visitor.visit_f64(x),
-//             Bool(b) => visitor.visit_bool(b),
-//             Str(s) => visitor.visit_str(s),
-//             Missing => visitor.visit_unit(),
-//             StrId(_) => Err(de::Error::custom("StrId requires interner")),
-//             IPv4(ip) => visitor.visit_u32(ip),
-//             IPv6(ip) => visitor.visit_u128(ip),
-//             DateTime64 { epoch, .. } => visitor.visit_i64(epoch),
-//             Uuid(bytes) => visitor.visit_bytes(&bytes),
-//         }
-//     }
-// }
-
-// impl<'de> IntoDeserializer<'de, de::value::Error> for SlotValueDeserializer {
-//     type Deserializer = Self;
-//     fn into_deserializer(self) -> Self::Deserializer {
-//         self
-//     }
-// }
-
-// pub struct RowpackStructDe<'a> {
-//     pub row: &'a [SlotValue],
-//     pub field_names: &'a [&'a str],
-// }
-
-// impl<'de, 'a> serde::de::Deserializer<'de> for RowpackStructDe<'a> {
-//     type Error = de::value::Error;
-//     fn deserialize_struct<V>(
-//         self,
-//         _: &'static str,
-//         _: &'static [&'static str],
-//         visitor: V,
-//     ) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         let iter = (0..self.field_names.len()).map(|i| {
-//             (
-//                 self.field_names[i].into_deserializer(),
-//                 SlotValueDeserializer(self.row[i]),
-//             )
-//         });
-//         serde::de::value::MapDeserializer::new(iter).deserialize_any(visitor)
-//     }
-//     fn deserialize_map<V>(self, v: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: serde::de::Visitor<'de>,
-//     {
-//         self.deserialize_struct("", &[], v)
-//     }
-
-//     serde::forward_to_deserialize_any! {
-//        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
-//         option unit unit_struct newtype_struct seq tuple tuple_struct enum identifier ignored_any
-//     }
-
-//     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-//     where
-//         V: de::Visitor<'de>,
-//     {
-//         let _ = visitor;
-//         Err(de::Error::custom("any is not supported"))
-//     }
-// }
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use serde::{
+    Deserialize, Serialize,
+    de::{
+        self, IntoDeserializer, Unexpected,
+        value::{MapDeserializer, SeqDeserializer},
+    },
+    ser::{self, Impossible},
+};
+
+use crate::{
+    ch_value::nanos_to_ticks,
+    intern::StrDict,
+    rowpack::{Tag, decode_rowpack, encode_slot},
+    schema::{Field, FieldType, Schema},
+    slot::{Coercion, SlotMap, SlotSeq, SlotValue},
+};
+
+pub type Error = de::value::Error;
+
+/// Deserializes `row` (laid out as `schema.fields`) into any serde type.
+pub fn from_row<'de, T: Deserialize<'de>>(
+    schema: &'de Schema,
+    row: &'de [SlotValue<'de>],
+) -> Result<T, Error> {
+    T::deserialize(RowpackStructDe::new(schema, row))
+}
+
+/// Serializes a struct into a rowpack payload in `schema` field order.
+/// Fields the struct does not serialize are written as missing.
+pub fn to_rowpack<T: Serialize + ?Sized>(
+    value: &T,
+    schema: &Schema,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    value.serialize(RowSerializer { schema, out })
+}
+
+/// Serializes a struct into a slot row. `buf` is cleared and then holds the
+/// rowpack payload the returned slots borrow from.
+pub fn to_row<'b, T: Serialize + ?Sized>(
+    value: &T,
+    schema: &Schema,
+    buf: &'b mut Vec<u8>,
+) -> Result<Vec<SlotValue<'b>>, Error> {
+    buf.clear();
+    to_rowpack(value, schema, buf)?;
+    decode_rowpack(buf, schema.fields_len()).map_err(de::Error::custom)
+}
+
+/// Deserializer for a single slot. Numeric slots are handed to the visitor as
+/// their wire type, so serde's own range checks apply (`U64(7)` into `u32` is
+/// fine, `U64(1 << 40)` is an error). `DateTime64` is the exception: it reads
+/// as nanoseconds since the epoch whatever its scale, as in [`crate::view`].
+pub struct SlotValueDeserializer<'de>(pub SlotValue<'de>);
+
+impl<'de> de::Deserializer<'de> for SlotValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        use SlotValue::*;
+        match self.0 {
+            Missing => visitor.visit_unit(),
+            U64(x) => visitor.visit_u64(x),
+            I64(x) => visitor.visit_i64(x),
+            F64(x) => visitor.visit_f64(x),
+            U32(x) => visitor.visit_u32(x),
+            U16(x) => visitor.visit_u16(x),
+            U8(x) => visitor.visit_u8(x),
+            I32(x) => visitor.visit_i32(x),
+            I16(x) => visitor.visit_i16(x),
+            I8(x) => visitor.visit_i8(x),
+            F32(x) => visitor.visit_f32(x),
+            Bool(b) => visitor.visit_bool(b),
+            Decimal { value, scale } => visitor.visit_string(format_decimal(value, scale)),
+            Date(days) => visitor.visit_u16(days),
+            Date32(days) => visitor.visit_i32(days),
+            Str(s) => visitor.visit_borrowed_str(s),
+            StrId(_) => Err(de::Error::custom("str id needs a string dictionary")),
+            Bytes(b) => visitor.visit_borrowed_bytes(b),
+            IPv4(ip) => visitor.visit_u32(ip),
+            IPv6(ip) => visitor.visit_u128(ip),
+            DateTime64 { .. } => {
+                let nanos = self.0.coerce_epoch_nanos(Coercion::Strict);
+                visitor.visit_i64(nanos.map_err(de::Error::custom)?)
+            }
+            Uuid(bytes) => visitor.visit_bytes(&bytes),
+            Array(seq) => {
+                let items = seq
+                    .iter()
+                    .map(|slot| slot.map(SlotValueDeserializer))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(de::Error::custom)?;
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Map(map) => {
+                let entries = map
+                    .iter()
+                    .map(|entry| {
+                        entry.map(|(k, v)| (SlotValueDeserializer(k), SlotValueDeserializer(v)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(de::Error::custom)?;
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Decimals are text in `deserialize_any`; a float target gets the
+    /// nearest float.
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::Decimal { value, scale } => {
+                visitor.visit_f64(value as f64 / 10f64.powi(scale as i32))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_f64(visitor)
+    }
+
+    /// `Vec<u8>` asks for a sequence; hand it the bytes one by one.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::Bytes(b) => {
+                let mut seq = SeqDeserializer::new(b.iter().copied());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::Missing => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::Missing => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /// Addresses and UUIDs render to their text form, which is what
+    /// `std::net` and `uuid` ask for from a human-readable format.
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::IPv4(ip) => visitor.visit_string(Ipv4Addr::from(ip).to_string()),
+            SlotValue::IPv6(ip) => visitor.visit_string(Ipv6Addr::from(ip).to_string()),
+            SlotValue::Uuid(bytes) => visitor.visit_string(format_uuid(&bytes)),
+            SlotValue::Bytes(b) => match std::str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            SlotValue::Str(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants by name (`Str`) or by index (`U64`/`I64`).
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let index = match self.0 {
+            SlotValue::Str(s) => return visitor.visit_enum(s.into_deserializer()),
+            SlotValue::U64(x) => u32::try_from(x).ok(),
+            SlotValue::I64(x) => u32::try_from(x).ok(),
+            SlotValue::U32(x) => Some(x),
+            SlotValue::U16(x) => Some(x.into()),
+            SlotValue::U8(x) => Some(x.into()),
+            SlotValue::I32(x) => u32::try_from(x).ok(),
+            SlotValue::I16(x) => u32::try_from(x).ok(),
+            SlotValue::I8(x) => u32::try_from(x).ok(),
+            other => return Err(de::Error::invalid_type(unexpected(&other), &visitor)),
+        };
+        let index = index.ok_or_else(|| de::Error::custom("enum index out of range"))?;
+        visitor.visit_enum(index.into_deserializer())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char
+        unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for SlotValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// Deserializer for a whole row, keyed by the schema's field names.
+///
+/// Structs see one map entry per schema field, so columns the target type
+/// does not know about are ignored and absent `Option` fields become `None`.
+/// Sequences and tuples see the slots in order.
+pub struct RowpackStructDe<'de> {
+    pub row: &'de [SlotValue<'de>],
+    pub schema: &'de Schema,
+    pub dict: Option<&'de StrDict>,
+}
+
+impl<'de> RowpackStructDe<'de> {
+    pub fn new(schema: &'de Schema, row: &'de [SlotValue<'de>]) -> Self {
+        Self {
+            row,
+            schema,
+            dict: None,
+        }
+    }
+
+    /// Resolves `SlotValue::StrId` slots through `dict`.
+    pub fn with_dict(mut self, dict: &'de StrDict) -> Self {
+        self.dict = Some(dict);
+        self
+    }
+
+    fn slots(&self) -> Result<Vec<SlotValue<'de>>, Error> {
+        if self.row.len() != self.schema.fields_len() {
+            return Err(de::Error::invalid_length(
+                self.row.len(),
+                &&*format!("{} fields", self.schema.fields_len()),
+            ));
+        }
+
+        self.row
+            .iter()
+            .map(|slot| match *slot {
+                SlotValue::StrId(id) => self
+                    .dict
+                    .and_then(|dict| dict.get(id))
+                    .map(SlotValue::Str)
+                    .ok_or_else(|| de::Error::custom(format!("unresolved str id {id}"))),
+                other => Ok(other),
+            })
+            .collect()
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RowpackStructDe<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let slots = self.slots()?;
+        let names = self.schema.fields.iter().map(|f| f.name.as_str());
+        let mut map = MapDeserializer::new(names.zip(slots.into_iter().map(SlotValueDeserializer)));
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let slots = self.slots()?;
+        let mut seq = SeqDeserializer::new(slots.into_iter().map(SlotValueDeserializer));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// Serializer for a whole row. Only structs (and newtypes around them) are
+/// rows; fields are matched to the schema by name.
+struct RowSerializer<'s> {
+    schema: &'s Schema,
+    out: &'s mut Vec<u8>,
+}
+
+macro_rules! not_a_row {
+    ($($meth:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $meth(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(ser::Error::custom("a row must serialize as a struct"))
+            }
+        )*
+    };
+}
+
+impl<'s> ser::Serializer for RowSerializer<'s> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = RowStructSerializer<'s>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    not_a_row! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Ok(RowStructSerializer {
+            slots: vec![None; self.schema.fields_len()],
+            schema: self.schema,
+            out: self.out,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(ser::Error::custom("a row must serialize as a struct"))
+    }
+}
+
+/// Buffers each encoded field so the row comes out in schema order whatever
+/// order the struct declares its fields in.
+struct RowStructSerializer<'s> {
+    schema: &'s Schema,
+    out: &'s mut Vec<u8>,
+    slots: Vec<Option<Vec<u8>>>,
+}
+
+impl ser::SerializeStruct for RowStructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let idx = self
+            .schema
+            .index_of(key)
+            .ok_or_else(|| ser::Error::custom(format!("field `{key}` is not in the schema")))?;
+
+        let mut buf = Vec::new();
+        value.serialize(SlotSerializer::field(&self.schema.fields[idx], &mut buf))?;
+        self.slots[idx] = Some(buf);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        for (field, slot) in self.schema.fields.iter().zip(&self.slots) {
+            if slot.is_none() && !field.nullable {
+                return Err(not_nullable(&field.name));
+            }
+        }
+        for slot in self.slots {
+            match slot {
+                Some(bytes) => self.out.extend_from_slice(&bytes),
+                None => self.out.push(Tag::Missing as u8),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serializer for one field or one array/map element; the schema's
+/// `FieldType` picks the slot.
+struct SlotSerializer<'s> {
+    name: &'s str,
+    ty: &'s FieldType,
+    nullable: bool,
+    out: &'s mut Vec<u8>,
+}
+
+static UINT8: FieldType = FieldType::UInt8;
+
+impl<'s> SlotSerializer<'s> {
+    fn field(field: &'s Field, out: &'s mut Vec<u8>) -> Self {
+        Self {
+            name: &field.name,
+            ty: &field.ty,
+            nullable: field.nullable,
+            out,
+        }
+    }
+
+    fn element(name: &'s str, ty: &'s FieldType, out: &'s mut Vec<u8>) -> Self {
+        match ty {
+            FieldType::Nullable(inner) => Self {
+                name,
+                ty: inner,
+                nullable: true,
+                out,
+            },
+            ty => Self {
+                name,
+                ty,
+                nullable: false,
+                out,
+            },
+        }
+    }
+
+    fn ty(&self) -> &'s FieldType {
+        self.ty.storage()
+    }
+
+    fn put(self, slot: SlotValue) -> Result<(), Error> {
+        if matches!(slot, SlotValue::Missing) && !self.nullable {
+            return Err(not_nullable(self.name));
+        }
+        encode_slot(&slot, self.out).map_err(ser::Error::custom)
+    }
+
+    fn mismatch(&self, what: &str) -> Error {
+        ser::Error::custom(format!(
+            "field `{}`: cannot store {what} as {:?}",
+            self.name, self.ty
+        ))
+    }
+
+    fn put_int(self, v: i128) -> Result<(), Error> {
+        let slot = match *self.ty() {
+            FieldType::UInt64 => u64::try_from(v).ok().map(SlotValue::U64),
+            FieldType::UInt32 => u32::try_from(v).ok().map(SlotValue::U32),
+            FieldType::UInt16 => u16::try_from(v).ok().map(SlotValue::U16),
+            FieldType::UInt8 => u8::try_from(v).ok().map(SlotValue::U8),
+            FieldType::Int64 => i64::try_from(v).ok().map(SlotValue::I64),
+            FieldType::Int32 => i32::try_from(v).ok().map(SlotValue::I32),
+            FieldType::Int16 => i16::try_from(v).ok().map(SlotValue::I16),
+            FieldType::Int8 => i8::try_from(v).ok().map(SlotValue::I8),
+            FieldType::Float64 => exact_f64(v).map(SlotValue::F64),
+            FieldType::Float32 => exact_f32(v).map(SlotValue::F32),
+            FieldType::Decimal { precision, scale } => 10i128
+                .checked_pow(scale.into())
+                .and_then(|m| v.checked_mul(m))
+                .and_then(|value| decimal(value, precision, scale)),
+            FieldType::IPv4 => u32::try_from(v).ok().map(SlotValue::IPv4),
+            FieldType::IPv6 => u128::try_from(v).ok().map(SlotValue::IPv6),
+            FieldType::Date => u16::try_from(v).ok().map(SlotValue::Date),
+            FieldType::Date32 => i32::try_from(v).ok().map(SlotValue::Date32),
+            FieldType::DateTime64 { scale } => {
+                i64::try_from(v).ok().map(|nanos| SlotValue::DateTime64 {
+                    epoch: nanos_to_ticks(nanos.into(), scale),
+                    scale,
+                })
+            }
+            FieldType::Enum8(ref variants) => i8::try_from(v)
+                .ok()
+                .filter(|v| variants.iter().any(|(_, value)| value == v))
+                .map(SlotValue::I8),
+            FieldType::Enum16(ref variants) => i16::try_from(v)
+                .ok()
+                .filter(|v| variants.iter().any(|(_, value)| value == v))
+                .map(SlotValue::I16),
+            _ => None,
+        };
+        match slot {
+            Some(slot) => self.put(slot),
+            None => Err(self.mismatch(&format!("integer {v}"))),
+        }
+    }
+}
+
+impl<'s> ser::Serializer for SlotSerializer<'s> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSlotSerializer<'s>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = MapSlotSerializer<'s>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        match self.ty() {
+            FieldType::Bool => self.put(SlotValue::Bool(v)),
+            _ => Err(self.mismatch("bool")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.put_int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.put_int(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        match (self.ty(), i128::try_from(v)) {
+            (FieldType::IPv6, _) => self.put(SlotValue::IPv6(v)),
+            (_, Ok(v)) => self.put_int(v),
+            _ => Err(self.mismatch("u128")),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        match self.ty() {
+            FieldType::Float32 => self.put(SlotValue::F32(v)),
+            _ => self.serialize_f64(v.into()),
+        }
+    }
+
+    /// Floats only narrow to `Float32` when the value survives the trip.
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        match self.ty() {
+            FieldType::Float64 => self.put(SlotValue::F64(v)),
+            FieldType::Float32 if v.is_nan() || (v as f32) as f64 == v => {
+                self.put(SlotValue::F32(v as f32))
+            }
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(v.encode_utf8(&mut [0u8; 4]))
+    }
+
+    /// Text goes into string columns as is; address, UUID and decimal
+    /// columns parse it, and enum columns look up the label.
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        let slot = match *self.ty() {
+            FieldType::String => Some(SlotValue::Str(v)),
+            FieldType::Bytes => Some(SlotValue::Bytes(v.as_bytes())),
+            FieldType::Decimal { precision, scale } => {
+                parse_decimal(v, scale).and_then(|value| decimal(value, precision, scale))
+            }
+            FieldType::IPv4 => v
+                .parse::<Ipv4Addr>()
+                .ok()
+                .map(|ip| SlotValue::IPv4(ip.into())),
+            FieldType::IPv6 => v
+                .parse::<Ipv6Addr>()
+                .ok()
+                .map(|ip| SlotValue::IPv6(ip.into())),
+            FieldType::Uuid => parse_uuid(v).map(SlotValue::Uuid),
+            FieldType::Enum8(ref variants) => variants
+                .iter()
+                .find(|(label, _)| label == v)
+                .map(|&(_, value)| SlotValue::I8(value)),
+            FieldType::Enum16(ref variants) => variants
+                .iter()
+                .find(|(label, _)| label == v)
+                .map(|&(_, value)| SlotValue::I16(value)),
+            _ => None,
+        };
+        match slot {
+            Some(slot) => self.put(slot),
+            None => Err(self.mismatch(&format!("{v:?}"))),
+        }
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        match (self.ty(), <[u8; 16]>::try_from(v)) {
+            (FieldType::Bytes, _) => self.put(SlotValue::Bytes(v)),
+            (FieldType::String, _) => match std::str::from_utf8(v) {
+                Ok(s) => self.put(SlotValue::Str(s)),
+                Err(_) => Err(self.mismatch("non-UTF-8 bytes")),
+            },
+            (FieldType::Uuid, Ok(bytes)) => self.put(SlotValue::Uuid(bytes)),
+            _ => Err(self.mismatch("bytes")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.put(SlotValue::Missing)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.put(SlotValue::Missing)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.put(SlotValue::Missing)
+    }
+
+    /// Unit enums go by variant name, the same form `deserialize_enum` reads.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(self.mismatch("enum with data"))
+    }
+
+    /// Sequences fill `Array` columns, and `Bytes` columns when the items
+    /// are `u8`s.
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        let (elem, bytes) = match self.ty() {
+            FieldType::Array(elem) => (&**elem, false),
+            FieldType::Bytes => (&UINT8, true),
+            _ => return Err(self.mismatch("sequence")),
+        };
+        Ok(SeqSlotSerializer {
+            name: self.name,
+            elem,
+            bytes,
+            out: self.out,
+            buf: Vec::new(),
+            len: 0,
+        })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(self.mismatch("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(self.mismatch("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(self.mismatch("enum with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        match self.ty() {
+            FieldType::Map(key, value) => Ok(MapSlotSerializer {
+                name: self.name,
+                key,
+                value,
+                out: self.out,
+                buf: Vec::new(),
+                len: 0,
+            }),
+            _ => Err(self.mismatch("map")),
+        }
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(self.mismatch("nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(self.mismatch("enum with data"))
+    }
+}
+
+/// Encodes `Array` elements into a side buffer, then writes the slot.
+struct SeqSlotSerializer<'s> {
+    name: &'s str,
+    elem: &'s FieldType,
+    bytes: bool,
+    out: &'s mut Vec<u8>,
+    buf: Vec<u8>,
+    len: u32,
+}
+
+impl ser::SerializeSeq for SeqSlotSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(SlotSerializer::element(self.name, self.elem, &mut self.buf))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        if !self.bytes {
+            return encode_slot(
+                &SlotValue::Array(SlotSeq::new(self.len, &self.buf)),
+                self.out,
+            )
+            .map_err(ser::Error::custom);
+        }
+
+        let raw = decode_rowpack(&self.buf, self.len as usize)
+            .map_err(ser::Error::custom)?
+            .into_iter()
+            .map(|slot| match slot {
+                SlotValue::U8(b) => b,
+                _ => unreachable!("elements were serialized as UInt8"),
+            })
+            .collect::<Vec<u8>>();
+        encode_slot(&SlotValue::Bytes(&raw), self.out).map_err(ser::Error::custom)
+    }
+}
+
+/// Encodes `Map` keys and values into a side buffer, then writes the slot.
+struct MapSlotSerializer<'s> {
+    name: &'s str,
+    key: &'s FieldType,
+    value: &'s FieldType,
+    out: &'s mut Vec<u8>,
+    buf: Vec<u8>,
+    len: u32,
+}
+
+impl ser::SerializeMap for MapSlotSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(SlotSerializer::element(self.name, self.key, &mut self.buf))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(SlotSerializer::element(
+            self.name,
+            self.value,
+            &mut self.buf,
+        ))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        encode_slot(&SlotValue::Map(SlotMap::new(self.len, &self.buf)), self.out)
+            .map_err(ser::Error::custom)
+    }
+}
+
+fn not_nullable(name: &str) -> Error {
+    ser::Error::custom(format!("field `{name}` is missing but not nullable"))
+}
+
+/// Integers only become floats when the value survives the trip.
+fn exact_f64(v: i128) -> Option<f64> {
+    const LIMIT: i128 = 1 << f64::MANTISSA_DIGITS;
+    (-LIMIT..=LIMIT).contains(&v).then_some(v as f64)
+}
+
+fn exact_f32(v: i128) -> Option<f32> {
+    const LIMIT: i128 = 1 << f32::MANTISSA_DIGITS;
+    (-LIMIT..=LIMIT).contains(&v).then_some(v as f32)
+}
+
+/// `value` is already scaled; it must fit in `precision` digits.
+fn decimal<'a>(value: i128, precision: u8, scale: u8) -> Option<SlotValue<'a>> {
+    let limit = 10u128.checked_pow(precision.into())?;
+    (value.unsigned_abs() < limit).then_some(SlotValue::Decimal {
+        value,
+        scale: scale.into(),
+    })
+}
+
+/// Parses `-12.345` into a value scaled by `10^scale`. More fractional digits
+/// than `scale` is an error, not a rounding.
+fn parse_decimal(s: &str, scale: u8) -> Option<i128> {
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() && frac.is_empty()
+        || frac.len() > scale as usize
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut value: i128 = 0;
+    for b in int.bytes().chain(frac.bytes()) {
+        value = value.checked_mul(10)?.checked_add((b - b'0').into())?;
+    }
+    value = value.checked_mul(10i128.checked_pow((scale as usize - frac.len()) as u32)?)?;
+    Some(if neg { -value } else { value })
+}
+
+fn format_decimal(value: i128, scale: u32) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let sign = if value < 0 { "-" } else { "" };
+    let scale = scale as usize;
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    format!("{sign}{int}.{frac}")
+}
+
+fn unexpected<'a>(slot: &'a SlotValue<'a>) -> Unexpected<'a> {
+    match *slot {
+        SlotValue::Missing => Unexpected::Unit,
+        SlotValue::U64(x) => Unexpected::Unsigned(x),
+        SlotValue::I64(x) => Unexpected::Signed(x),
+        SlotValue::F64(x) => Unexpected::Float(x),
+        SlotValue::U32(x) => Unexpected::Unsigned(x.into()),
+        SlotValue::U16(x) => Unexpected::Unsigned(x.into()),
+        SlotValue::U8(x) => Unexpected::Unsigned(x.into()),
+        SlotValue::I32(x) => Unexpected::Signed(x.into()),
+        SlotValue::I16(x) => Unexpected::Signed(x.into()),
+        SlotValue::I8(x) => Unexpected::Signed(x.into()),
+        SlotValue::F32(x) => Unexpected::Float(x.into()),
+        SlotValue::Bool(b) => Unexpected::Bool(b),
+        SlotValue::Decimal { .. } => Unexpected::Other("decimal"),
+        SlotValue::Date(_) | SlotValue::Date32(_) => Unexpected::Other("date"),
+        SlotValue::Str(s) => Unexpected::Str(s),
+        SlotValue::Bytes(b) => Unexpected::Bytes(b),
+        SlotValue::Array(_) => Unexpected::Seq,
+        SlotValue::Map(_) => Unexpected::Map,
+        SlotValue::StrId(_) => Unexpected::Other("str id"),
+        SlotValue::IPv4(_) | SlotValue::IPv6(_) => Unexpected::Other("ip address"),
+        SlotValue::DateTime64 { .. } => Unexpected::Other("datetime64"),
+        SlotValue::Uuid(_) => Unexpected::Other("uuid"),
+    }
+}
+
+fn format_uuid(b: &[u8; 16]) -> String {
+    let hex: String = b.iter().map(|x| format!("{x:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    let hex: Vec<u8> = s.bytes().filter(|&b| b != b'-').collect();
+    if hex.len() != 32 || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let mut out = [0u8; 16];
+    for (byte, pair) in out.iter_mut().zip(hex.chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(out)
+}
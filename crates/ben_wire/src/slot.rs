@@ -1,53 +1,388 @@
+use crate::{
+    error::{RowpackError, SlotError},
+    intern::StrDict,
+    rowpack::RowpackCursor,
+};
+
 #[derive(Clone, Copy, Debug)]
 pub enum SlotValue<'a> {
     Missing,
     U64(u64),
     I64(i64),
     F64(f64),
+    U32(u32),
+    U16(u16),
+    U8(u8),
+    I32(i32),
+    I16(i16),
+    I8(i8),
+    F32(f32),
     Bool(bool),
+    /// `value / 10^scale`; the precision lives in the schema.
+    Decimal {
+        value: i128,
+        scale: u32,
+    },
+    /// Days since 1970-01-01.
+    Date(u16),
+    /// Days since 1970-01-01, may be negative.
+    Date32(i32),
     Str(&'a str),
+    /// Raw bytes; a `String` column that need not be UTF-8.
+    Bytes(&'a [u8]),
+    /// Interned string; resolve with [`SlotValue::as_str_in`].
+    StrId(u32),
     IPv4(u32),
     IPv6(u128),
-    DateTime64 { epoch: i64, scale: u32 },
+    DateTime64 {
+        epoch: i64,
+        scale: u32,
+    },
     Uuid([u8; 16]),
+    Array(SlotSeq<'a>),
+    Map(SlotMap<'a>),
+}
+
+/// Elements of an `Array` slot, kept in rowpack encoding and decoded on
+/// demand. Strings inside are always inline, never interned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlotSeq<'a> {
+    len: u32,
+    bytes: &'a [u8],
+}
+
+impl<'a> SlotSeq<'a> {
+    /// `bytes` holds `len` encoded slots; errors surface when iterating.
+    pub fn new(len: u32, bytes: &'a [u8]) -> Self {
+        Self { len, bytes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn iter(&self) -> SlotIter<'a> {
+        SlotIter {
+            cur: RowpackCursor::new(self.bytes),
+            remaining: self.len as usize,
+        }
+    }
+}
+
+/// Entries of a `Map` slot, encoded as key, value, key, value, ...
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlotMap<'a> {
+    len: u32,
+    bytes: &'a [u8],
+}
+
+impl<'a> SlotMap<'a> {
+    /// `bytes` holds `len` key/value pairs; errors surface when iterating.
+    pub fn new(len: u32, bytes: &'a [u8]) -> Self {
+        Self { len, bytes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = Result<(SlotValue<'a>, SlotValue<'a>), RowpackError>> {
+        let mut slots = SlotIter {
+            cur: RowpackCursor::new(self.bytes),
+            remaining: self.len as usize * 2,
+        };
+        std::iter::from_fn(move || {
+            let entry = match slots.next()? {
+                Ok(key) => slots.next()?.map(|value| (key, value)),
+                Err(e) => Err(e),
+            };
+            Some(entry)
+        })
+    }
+}
+
+/// Decodes the slots of a [`SlotSeq`] or [`SlotMap`]. Stops after the first
+/// error.
+pub struct SlotIter<'a> {
+    cur: RowpackCursor<'a>,
+    remaining: usize,
 }
 
+impl<'a> Iterator for SlotIter<'a> {
+    type Item = Result<SlotValue<'a>, RowpackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let slot = self.cur.read_slot(0);
+        if slot.is_err() {
+            self.remaining = 0;
+        }
+        Some(slot)
+    }
+}
+
+/// How far the `coerce_*` accessors may stretch a slot to fit the requested
+/// type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Coercion {
+    /// Only conversions that keep the exact value: narrow to wide integers,
+    /// integers to `f64` up to 2^53, IPv4 to IPv4-mapped IPv6.
+    #[default]
+    Strict,
+    /// Also conversions that may round or reinterpret: any integer or
+    /// decimal to `f64`, integral floats to integers, `0`/`1` to bool and
+    /// numbers or bools spelled out in strings.
+    Lenient,
+}
+
+/// 2^53; every integer up to here is exact in an `f64`.
+const F64_EXACT: i128 = 1 << f64::MANTISSA_DIGITS;
+
 impl<'a> SlotValue<'a> {
-    #[inline]
-    pub fn as_u64(&self) -> Result<&u64, String> {
-        match &self {
-            SlotValue::U64(x) => Ok(&x),
-            _ => Err("u64".into()),
+    /// Variant name, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SlotValue::Missing => "missing",
+            SlotValue::U64(_) => "u64",
+            SlotValue::I64(_) => "i64",
+            SlotValue::F64(_) => "f64",
+            SlotValue::U32(_) => "u32",
+            SlotValue::U16(_) => "u16",
+            SlotValue::U8(_) => "u8",
+            SlotValue::I32(_) => "i32",
+            SlotValue::I16(_) => "i16",
+            SlotValue::I8(_) => "i8",
+            SlotValue::F32(_) => "f32",
+            SlotValue::Bool(_) => "bool",
+            SlotValue::Decimal { .. } => "decimal",
+            SlotValue::Date(_) => "date",
+            SlotValue::Date32(_) => "date32",
+            SlotValue::Str(_) => "str",
+            SlotValue::Bytes(_) => "bytes",
+            SlotValue::StrId(_) => "str id",
+            SlotValue::IPv4(_) => "ipv4",
+            SlotValue::IPv6(_) => "ipv6",
+            SlotValue::DateTime64 { .. } => "datetime64",
+            SlotValue::Uuid(_) => "uuid",
+            SlotValue::Array(_) => "array",
+            SlotValue::Map(_) => "map",
         }
     }
-    #[inline]
-    pub fn as_i64(&self) -> Result<&i64, String> {
-        match &self {
-            SlotValue::I64(x) => Ok(&x),
-            _ => Err("i64".into()),
+
+    fn mismatch(&self, expected: &'static str) -> SlotError {
+        SlotError::TypeMismatch {
+            expected,
+            got: self.kind(),
         }
     }
-    #[inline]
-    pub fn as_f64(&self) -> Result<&f64, String> {
-        match &self {
-            SlotValue::F64(x) => Ok(&x),
-            _ => Err("f64".into()),
+
+    fn out_of_range(&self, expected: &'static str) -> SlotError {
+        SlotError::OutOfRange {
+            expected,
+            got: self.kind(),
         }
     }
 
-    #[inline]
-    pub fn as_str(&self) -> Result<&'a str, String> {
-        match &self {
-            SlotValue::Str(x) => Ok(x),
-            _ => Err("str".into()),
+    /// Any integer slot, widened.
+    fn int(&self) -> Option<i128> {
+        Some(match *self {
+            SlotValue::U64(x) => x.into(),
+            SlotValue::U32(x) => x.into(),
+            SlotValue::U16(x) => x.into(),
+            SlotValue::U8(x) => x.into(),
+            SlotValue::I64(x) => x.into(),
+            SlotValue::I32(x) => x.into(),
+            SlotValue::I16(x) => x.into(),
+            SlotValue::I8(x) => x.into(),
+            _ => return None,
+        })
+    }
+
+    /// Integer value under [`Coercion::Lenient`]: integral floats and
+    /// decimals, bools and integer strings.
+    fn lenient_int(&self) -> Option<i128> {
+        match *self {
+            SlotValue::F64(x) if x.fract() == 0.0 && x.abs() < 2f64.powi(127) => Some(x as i128),
+            SlotValue::F32(x) if x.fract() == 0.0 && x.abs() < 2f32.powi(127) => Some(x as i128),
+            SlotValue::Decimal { value, scale } => {
+                let div = 10i128.checked_pow(scale)?;
+                (value % div == 0).then_some(value / div)
+            }
+            SlotValue::Bool(b) => Some(b.into()),
+            SlotValue::Str(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn coerce_int(&self, mode: Coercion, expected: &'static str) -> Result<i128, SlotError> {
+        match (self.int(), mode) {
+            (Some(v), _) => Ok(v),
+            (None, Coercion::Lenient) => self.lenient_int().ok_or_else(|| self.mismatch(expected)),
+            (None, Coercion::Strict) => Err(self.mismatch(expected)),
         }
     }
 
+    pub fn coerce_u64(&self, mode: Coercion) -> Result<u64, SlotError> {
+        let v = self.coerce_int(mode, "u64")?;
+        u64::try_from(v).map_err(|_| self.out_of_range("u64"))
+    }
+
+    pub fn coerce_i64(&self, mode: Coercion) -> Result<i64, SlotError> {
+        let v = self.coerce_int(mode, "i64")?;
+        i64::try_from(v).map_err(|_| self.out_of_range("i64"))
+    }
+
+    pub fn coerce_f64(&self, mode: Coercion) -> Result<f64, SlotError> {
+        match *self {
+            SlotValue::F64(x) => return Ok(x),
+            SlotValue::F32(x) => return Ok(x.into()),
+            _ => {}
+        }
+        if let Some(v) = self.int() {
+            return match mode {
+                _ if (-F64_EXACT..=F64_EXACT).contains(&v) => Ok(v as f64),
+                Coercion::Lenient => Ok(v as f64),
+                Coercion::Strict => Err(self.out_of_range("f64")),
+            };
+        }
+        match (*self, mode) {
+            (SlotValue::Decimal { value, scale }, Coercion::Lenient) => {
+                Ok(value as f64 / 10f64.powi(scale as i32))
+            }
+            (SlotValue::Bool(b), Coercion::Lenient) => Ok(u8::from(b).into()),
+            (SlotValue::Str(s), Coercion::Lenient) => {
+                s.trim().parse().map_err(|_| self.mismatch("f64"))
+            }
+            _ => Err(self.mismatch("f64")),
+        }
+    }
+
+    pub fn coerce_bool(&self, mode: Coercion) -> Result<bool, SlotError> {
+        match (*self, mode) {
+            (SlotValue::Bool(b), _) => Ok(b),
+            (SlotValue::Str(s), Coercion::Lenient) => match s.trim() {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(self.mismatch("bool")),
+            },
+            (_, Coercion::Lenient) => match self.int() {
+                Some(0) => Ok(false),
+                Some(1) => Ok(true),
+                Some(_) => Err(self.out_of_range("bool")),
+                None => Err(self.mismatch("bool")),
+            },
+            _ => Err(self.mismatch("bool")),
+        }
+    }
+
+    /// Lenient mode also reads UTF-8 `Bytes`.
+    pub fn coerce_str(&self, mode: Coercion) -> Result<&'a str, SlotError> {
+        match (*self, mode) {
+            (SlotValue::Str(s), _) => Ok(s),
+            (SlotValue::Bytes(b), Coercion::Lenient) => {
+                std::str::from_utf8(b).map_err(|_| self.mismatch("str"))
+            }
+            _ => Err(self.mismatch("str")),
+        }
+    }
+
+    /// Nanoseconds since the epoch from `DateTime64`, `Date` or `Date32`.
+    /// Sub-nanosecond ticks only truncate in lenient mode.
+    pub fn coerce_epoch_nanos(&self, mode: Coercion) -> Result<i64, SlotError> {
+        const DAY_NANOS: i64 = 86_400 * 1_000_000_000;
+        let nanos = match *self {
+            SlotValue::DateTime64 { epoch, scale } if scale <= 9 => {
+                epoch.checked_mul(10i64.pow(9 - scale))
+            }
+            SlotValue::DateTime64 { epoch, scale } => {
+                let div = 10i64.checked_pow(scale - 9);
+                match (div, mode) {
+                    (Some(div), _) if epoch % div == 0 => Some(epoch / div),
+                    (Some(div), Coercion::Lenient) => Some(epoch / div),
+                    _ => None,
+                }
+            }
+            SlotValue::Date(days) => i64::from(days).checked_mul(DAY_NANOS),
+            SlotValue::Date32(days) => i64::from(days).checked_mul(DAY_NANOS),
+            _ => return Err(self.mismatch("epoch nanos")),
+        };
+        nanos.ok_or_else(|| self.out_of_range("epoch nanos"))
+    }
+
+    /// IPv4 widens to its IPv4-mapped IPv6 address in either mode.
+    pub fn coerce_ipv6(&self, _mode: Coercion) -> Result<u128, SlotError> {
+        match *self {
+            SlotValue::IPv6(ip) => Ok(ip),
+            SlotValue::IPv4(ip) => Ok(std::net::Ipv4Addr::from(ip).to_ipv6_mapped().into()),
+            _ => Err(self.mismatch("ipv6")),
+        }
+    }
+
+    #[inline]
+    pub fn as_u64(&self) -> Result<u64, SlotError> {
+        self.coerce_u64(Coercion::Strict)
+    }
+
+    #[inline]
+    pub fn as_i64(&self) -> Result<i64, SlotError> {
+        self.coerce_i64(Coercion::Strict)
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> Result<f64, SlotError> {
+        self.coerce_f64(Coercion::Strict)
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Result<bool, SlotError> {
+        self.coerce_bool(Coercion::Strict)
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Result<&'a str, SlotError> {
+        self.coerce_str(Coercion::Strict)
+    }
+
+    /// Like [`SlotValue::as_str`], but also resolves interned strings.
     #[inline]
-    pub fn as_bool(&self) -> Result<&bool, String> {
-        match &self {
-            SlotValue::Bool(x) => Ok(&x),
-            _ => Err("bool".into()),
+    pub fn as_str_in<'b>(&self, dict: &'b StrDict) -> Result<&'b str, SlotError>
+    where
+        'a: 'b,
+    {
+        match *self {
+            SlotValue::StrId(id) => dict.get(id).ok_or(SlotError::UnknownStrId(id)),
+            _ => self.as_str(),
         }
     }
+
+    #[inline]
+    pub fn as_epoch_nanos(&self) -> Result<i64, SlotError> {
+        self.coerce_epoch_nanos(Coercion::Strict)
+    }
+
+    #[inline]
+    pub fn as_ipv6(&self) -> Result<u128, SlotError> {
+        self.coerce_ipv6(Coercion::Strict)
+    }
 }
@@ -1,58 +1,223 @@
 use crate::error::ViewError;
 use crate::schema::Schema;
-use crate::slot::SlotValue;
+use crate::slot::{Coercion, SlotValue};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::{Ipv4Addr, Ipv6Addr},
+};
 
 pub trait BenView: Sized {
     const EVT_HASH: [u8; 32];
 
+    /// Event name. When set, rows from any schema version of this event are
+    /// accepted, not only the one hashing to `EVT_HASH`.
+    const EVENT: Option<&'static str> = None;
+
     const FIELD_NAMES: &'static [&'static str];
 
     const FIELD_COUNT: usize;
 
+    /// How fields convert from slots; see [`field_with`].
+    const COERCION: Coercion = Coercion::Strict;
+
     fn from_row(schema: &Schema, row: &[SlotValue]) -> Result<Self, ViewError>;
 }
 
+/// Checks that `row` belongs to an event `T` understands, then builds `T`.
+/// Fields are resolved by name, so the row's schema may have more columns
+/// than `T` or lay them out in a different order.
 pub fn view<T>(schema: &Schema, row: &[SlotValue]) -> Result<T, ViewError>
 where
     T: BenView,
 {
-    if schema.evt_hash != T::EVT_HASH {
+    if schema.evt_hash != T::EVT_HASH && T::EVENT != Some(schema.event.as_str()) {
         return Err(ViewError::EvtHashMismatch {
             expected: T::EVT_HASH,
             got: schema.evt_hash,
         });
     }
 
-    if schema.fields_len() != T::FIELD_COUNT {
+    if row.len() != schema.fields_len() {
         return Err(ViewError::FieldCountMismatch {
-            expected: T::FIELD_COUNT,
-            got: schema.fields_len(),
+            expected: schema.fields_len(),
+            got: row.len(),
         });
     }
 
-    if row.len() != T::FIELD_COUNT {
-        return Err(ViewError::FieldCountMismatch {
-            expected: T::FIELD_COUNT,
-            got: row.len(),
-        });
+    T::from_row(schema, row)
+}
+
+/// Looks `name` up in `schema` and converts its slot. A column that is absent
+/// from the schema or `Missing` in the row is only accepted when `T` can
+/// stand in for it (`Option<_>`).
+pub fn field<T: FromSlot>(
+    schema: &Schema,
+    row: &[SlotValue],
+    name: &'static str,
+) -> Result<T, ViewError> {
+    field_with(schema, row, name, Coercion::Strict)
+}
+
+/// [`field`] with an explicit [`Coercion`] mode.
+pub fn field_with<T: FromSlot>(
+    schema: &Schema,
+    row: &[SlotValue],
+    name: &'static str,
+    mode: Coercion,
+) -> Result<T, ViewError> {
+    let slot = schema.index_of(name).and_then(|idx| row.get(idx));
+
+    match slot {
+        None | Some(SlotValue::Missing) => {
+            T::missing().ok_or(ViewError::MissingNotAllowed { field: name })
+        }
+        Some(slot) => T::from_slot(slot, mode).ok_or(ViewError::TypeMismatch { field: name }),
+    }
+}
+
+/// Conversion from a slot into a view field. Under [`Coercion::Strict`] only
+/// lossless conversions are accepted, which lets a column widen
+/// (`U64` -> `F64`) without breaking readers.
+pub trait FromSlot: Sized {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self>;
+
+    /// Value for an absent or `Missing` column, if this type has one.
+    fn missing() -> Option<Self> {
+        None
     }
+}
 
-    #[cfg(debug_assertions)]
-    {
-        for (index, (field, &expected_name)) in
-            schema.fields.iter().zip(T::FIELD_NAMES.iter()).enumerate()
-        {
-            if field.name != expected_name {
-                return Err(ViewError::FieldNameMismatch {
-                    index,
-                    expected: expected_name,
-                    got: field.name.clone(),
-                });
+impl<T: FromSlot> FromSlot for Option<T> {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        match slot {
+            SlotValue::Missing => Some(None),
+            slot => T::from_slot(slot, mode).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl FromSlot for u64 {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        slot.coerce_u64(mode).ok()
+    }
+}
+
+/// `DateTime64` reads as nanoseconds since the epoch whatever its scale, so
+/// a column's precision can change without shifting readers' values.
+impl FromSlot for i64 {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        match *slot {
+            SlotValue::DateTime64 { .. } => slot.coerce_epoch_nanos(mode).ok(),
+            _ => slot.coerce_i64(mode).ok(),
+        }
+    }
+}
+
+macro_rules! narrow_from_slot {
+    ($via:ty => $($ty:ty),*) => {
+        $(
+            impl FromSlot for $ty {
+                fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+                    <$via>::from_slot(slot, mode).and_then(|v| <$ty>::try_from(v).ok())
+                }
             }
+        )*
+    };
+}
+
+narrow_from_slot!(u64 => u32, u16, u8);
+narrow_from_slot!(i64 => i32, i16, i8);
+
+impl FromSlot for f64 {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        slot.coerce_f64(mode).ok()
+    }
+}
+
+impl FromSlot for f32 {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        if let SlotValue::F32(v) = *slot {
+            return Some(v);
+        }
+        let v = slot.coerce_f64(mode).ok()?;
+        match mode {
+            Coercion::Strict => ((v as f32) as f64 == v).then_some(v as f32),
+            Coercion::Lenient => Some(v as f32),
         }
     }
+}
 
-    T::from_row(schema, row)
+impl FromSlot for bool {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        slot.coerce_bool(mode).ok()
+    }
+}
+
+impl FromSlot for String {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        slot.coerce_str(mode).ok().map(str::to_owned)
+    }
+}
+
+impl FromSlot for Ipv4Addr {
+    fn from_slot(slot: &SlotValue, _mode: Coercion) -> Option<Self> {
+        match *slot {
+            SlotValue::IPv4(v) => Some(v.into()),
+            _ => None,
+        }
+    }
+}
+
+impl FromSlot for Ipv6Addr {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        slot.coerce_ipv6(mode).ok().map(Ipv6Addr::from)
+    }
+}
+
+/// Arrays element by element; raw bytes also read as `Vec<u8>`.
+impl<T: FromSlot> FromSlot for Vec<T> {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        match *slot {
+            SlotValue::Array(seq) => seq
+                .iter()
+                .map(|item| item.ok().and_then(|item| T::from_slot(&item, mode)))
+                .collect(),
+            SlotValue::Bytes(b) => b
+                .iter()
+                .map(|&x| T::from_slot(&SlotValue::U8(x), mode))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<K: FromSlot + Eq + Hash, V: FromSlot> FromSlot for HashMap<K, V> {
+    fn from_slot(slot: &SlotValue, mode: Coercion) -> Option<Self> {
+        match *slot {
+            SlotValue::Map(map) => map
+                .iter()
+                .map(|entry| {
+                    let (k, v) = entry.ok()?;
+                    Some((K::from_slot(&k, mode)?, V::from_slot(&v, mode)?))
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl FromSlot for [u8; 16] {
+    fn from_slot(slot: &SlotValue, _mode: Coercion) -> Option<Self> {
+        match *slot {
+            SlotValue::Uuid(v) => Some(v),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -72,24 +237,9 @@ mod tests {
         const FIELD_NAMES: &'static [&'static str] = &["id", "name"];
         const FIELD_COUNT: usize = 2;
 
-        fn from_row(_schema: &Schema, row: &[SlotValue]) -> Result<Self, ViewError> {
-            if row.len() != Self::FIELD_COUNT {
-                return Err(ViewError::FieldCountMismatch {
-                    expected: Self::FIELD_COUNT,
-                    got: row.len(),
-                });
-            }
-
-            let id = match row[0] {
-                SlotValue::I64(v) => v,
-                _ => return Err(ViewError::TypeMismatch { field: "id" }),
-            };
-
-            let name = match &row[1] {
-                SlotValue::Str(s) => s.clone().to_string(),
-                _ => return Err(ViewError::TypeMismatch { field: "name" }),
-            };
-
+        fn from_row(schema: &Schema, row: &[SlotValue]) -> Result<Self, ViewError> {
+            let id = field(schema, row, "id")?;
+            let name = field(schema, row, "name")?;
             Ok(TestEvent { id, name })
         }
     }
@@ -102,12 +252,12 @@ mod tests {
             fields: vec![
                 Field {
                     name: "id".to_string(),
-                    ty: FieldType::UInt64,
+                    ty: FieldType::Int64,
                     nullable: false,
                 },
                 Field {
-                    name: "id".to_string(),
-                    ty: FieldType::UInt64,
+                    name: "name".to_string(),
+                    ty: FieldType::String,
                     nullable: false,
                 },
             ],
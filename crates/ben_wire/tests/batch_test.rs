@@ -0,0 +1,190 @@
+use ben_wire::{
+    batch::{Batch, BatchEncoder, BatchHeader, Push},
+    ch_binary,
+    rowbinary::{
+        EncodeQuic, RowBinCursor, RowBinaryDecode, RowBinaryDecodeRef, RowBinaryEncode,
+        RowBinaryResult,
+    },
+};
+
+const HASH: [u8; 32] = [0x5A; 32];
+
+#[derive(Debug, PartialEq)]
+struct Hit {
+    id: u64,
+    route: String,
+}
+
+impl RowBinaryEncode for Hit {
+    fn encode_rowbinary(&self, out: &mut Vec<u8>) -> RowBinaryResult {
+        out.extend_from_slice(&self.id.to_le_bytes());
+        ch_binary::write_str(&self.route, out);
+        Ok(())
+    }
+}
+
+impl RowBinaryDecode for Hit {
+    const EVT_HASH: [u8; 32] = HASH;
+    const FIELD_COUNT: u16 = 2;
+
+    fn from_rowbinary(cur: &mut RowBinCursor<'_>) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: cur.read_u64()?,
+            route: cur.read_string()?,
+        })
+    }
+}
+
+impl EncodeQuic for Hit {
+    const EVT_HASH: [u8; 32] = HASH;
+    const FIELD_COUNT: u16 = 2;
+}
+
+struct HitRef<'a> {
+    id: u64,
+    route: &'a str,
+}
+
+impl<'a> RowBinaryDecodeRef<'a> for HitRef<'a> {
+    const EVT_HASH: [u8; 32] = HASH;
+    const FIELD_COUNT: u16 = 2;
+
+    fn from_rowbinary_ref(cur: &mut RowBinCursor<'a>) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: cur.read_u64()?,
+            route: cur.read_str()?,
+        })
+    }
+}
+
+fn hit(id: u64) -> Hit {
+    Hit {
+        id,
+        route: format!("/r/{id}"),
+    }
+}
+
+/// Pushes `n` hits, finishing a frame whenever the encoder is full.
+fn encode_all(enc: &mut BatchEncoder<Hit>, n: u64) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    for id in 0..n {
+        let row = hit(id);
+        if enc.push(&row).unwrap() == Push::Full {
+            frames.push(enc.finish());
+            assert_eq!(enc.push(&row).unwrap(), Push::Added);
+        }
+    }
+    if !enc.is_empty() {
+        frames.push(enc.finish());
+    }
+    frames
+}
+
+#[test]
+fn batch_roundtrip() {
+    let mut enc = BatchEncoder::<Hit>::new(usize::MAX, 100);
+    let frames = encode_all(&mut enc, 3);
+    assert_eq!(frames.len(), 1);
+
+    let batch = Batch::parse(&frames[0]).unwrap();
+    assert_eq!(batch.header.evt_hash, HASH);
+    assert_eq!(batch.header.field_count, 2);
+    assert_eq!(batch.header.row_count, 3);
+    assert_eq!(batch.header.frame_len(), frames[0].len());
+
+    let rows: Vec<Hit> = batch.rows().unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(rows, vec![hit(0), hit(1), hit(2)]);
+}
+
+#[test]
+fn row_budget_splits_batches() {
+    let mut enc = BatchEncoder::<Hit>::new(usize::MAX, 4);
+    let frames = encode_all(&mut enc, 10);
+
+    let counts: Vec<u32> = frames
+        .iter()
+        .map(|f| BatchHeader::parse(f).unwrap().row_count)
+        .collect();
+    assert_eq!(counts, vec![4, 4, 2]);
+}
+
+#[test]
+fn byte_budget_splits_batches() {
+    let row_len = 8 + 1 + "/r/0".len();
+    let budget = BatchHeader::LEN + 3 * row_len;
+    let mut enc = BatchEncoder::<Hit>::new(budget, u32::MAX);
+    let frames = encode_all(&mut enc, 7);
+
+    for frame in &frames {
+        assert!(frame.len() <= budget);
+    }
+    let total: u32 = frames
+        .iter()
+        .map(|f| BatchHeader::parse(f).unwrap().row_count)
+        .sum();
+    assert_eq!(total, 7);
+    assert_eq!(frames.len(), 3);
+}
+
+#[test]
+fn oversized_row_goes_out_alone() {
+    let mut enc = BatchEncoder::<Hit>::new(BatchHeader::LEN + 4, u32::MAX);
+    assert_eq!(enc.push(&hit(1)).unwrap(), Push::Added);
+    assert_eq!(enc.push(&hit(2)).unwrap(), Push::Full);
+    assert_eq!(enc.rows(), 1);
+}
+
+#[test]
+fn frames_split_from_a_stream() {
+    let mut enc = BatchEncoder::<Hit>::new(usize::MAX, 2);
+    let stream: Vec<u8> = encode_all(&mut enc, 5).concat();
+
+    let mut ids = Vec::new();
+    let mut rest = &stream[..];
+    while !rest.is_empty() {
+        let batch = Batch::parse(rest).unwrap();
+        for row in batch.rows_ref::<HitRef>().unwrap() {
+            let row = row.unwrap();
+            assert_eq!(row.route, format!("/r/{}", row.id));
+            ids.push(row.id);
+        }
+        rest = &rest[batch.header.frame_len()..];
+    }
+    assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn rows_decode_lazily_and_stop_at_errors() {
+    let mut enc = BatchEncoder::<Hit>::new(usize::MAX, 10);
+    let mut frame = encode_all(&mut enc, 3).remove(0);
+
+    // claim one more row than was written
+    frame[34..38].copy_from_slice(&4u32.to_le_bytes());
+    let batch = Batch::parse(&frame).unwrap();
+    let mut rows = batch.rows::<Hit>().unwrap();
+    assert_eq!(rows.next().unwrap().unwrap(), hit(0));
+    assert!(rows.next().unwrap().is_ok());
+    assert!(rows.next().unwrap().is_ok());
+    assert!(rows.next().unwrap().is_err());
+    assert!(rows.next().is_none());
+
+    // claim one fewer row: leftover bytes are an error
+    frame[34..38].copy_from_slice(&2u32.to_le_bytes());
+    let batch = Batch::parse(&frame).unwrap();
+    let results: Vec<_> = batch.rows::<Hit>().unwrap().collect();
+    assert_eq!(results.len(), 3);
+    assert!(results[2].is_err());
+}
+
+#[test]
+fn rejects_short_and_foreign_batches() {
+    let mut enc = BatchEncoder::<Hit>::new(usize::MAX, 10);
+    let frame = encode_all(&mut enc, 2).remove(0);
+
+    assert!(Batch::parse(&frame[..BatchHeader::LEN - 1]).is_err());
+    assert!(Batch::parse(&frame[..frame.len() - 1]).is_err());
+
+    let mut foreign = frame.clone();
+    foreign[0] ^= 0xFF;
+    assert!(Batch::parse(&foreign).unwrap().rows::<Hit>().is_err());
+}
@@ -0,0 +1,38 @@
+use ben_wire::bits::BitSet;
+
+#[test]
+fn bits_land_in_their_word() {
+    let set = BitSet::<3>::from_bits([0, 63, 64, 191]).unwrap();
+    assert_eq!(set.words(), &[1 | 1 << 63, 1, 1 << 63]);
+    assert_eq!(set.count_ones(), 4);
+    assert_eq!(set.ones().collect::<Vec<_>>(), [0, 63, 64, 191]);
+    assert!(set.contains(64) && !set.contains(65));
+    assert!(!set.contains(500));
+    assert_eq!(format!("{set:?}"), "{0, 63, 64, 191}");
+}
+
+#[test]
+fn set_algebra() {
+    let a = BitSet::<2>::from_bits([1, 70, 100]).unwrap();
+    let b = BitSet::<2>::from_bits([70, 127]).unwrap();
+
+    assert_eq!((a & b).ones().collect::<Vec<_>>(), [70]);
+    assert_eq!((a | b).count_ones(), 4);
+    assert!(a.intersects(&b));
+    assert!((a | b).contains_all(&a));
+    assert!(!a.contains_all(&b));
+    assert!(BitSet::<1>::EMPTY.is_empty());
+    assert_eq!(BitSet::<2>::default(), BitSet::EMPTY);
+}
+
+#[test]
+fn bits_past_the_width_are_refused() {
+    let mut set = BitSet::<1>::EMPTY;
+    assert!(!set.set(64));
+    assert!(set.is_empty());
+    assert!(set.set(63));
+
+    assert_eq!(BitSet::<1>::bit(64), None);
+    assert_eq!(BitSet::<1>::EMPTY.with(63).and_then(|s| s.with(64)), None);
+    assert_eq!(BitSet::<2>::from_bits([1, 128]), None);
+}
@@ -0,0 +1,242 @@
+//! Golden bytes for ClickHouse `FORMAT RowBinary`. Each expected buffer is
+//! what ClickHouse emits for the query in the comment above it.
+
+use ben_wire::{
+    bits::BitSet,
+    ch_binary,
+    rowbinary::{InfraValues, NamesAndTypes, RowBinCursor, RowBinaryDecode, RowBinaryEncode},
+    schema::{Field, FieldType, Schema},
+};
+
+fn hex(s: &str) -> Vec<u8> {
+    s.split_whitespace()
+        .map(|b| u8::from_str_radix(b, 16).unwrap())
+        .collect()
+}
+
+// SELECT 'héllo'
+#[test]
+fn string_has_leb128_length() {
+    let mut out = Vec::new();
+    ch_binary::write_str("héllo", &mut out);
+    assert_eq!(out, hex("06 68 c3 a9 6c 6c 6f"));
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(cur.read_str().unwrap(), "héllo");
+}
+
+// SELECT repeat('a', 300)
+#[test]
+fn long_string_length_spans_two_bytes() {
+    let s = "a".repeat(300);
+    let mut out = Vec::new();
+    ch_binary::write_str(&s, &mut out);
+    assert_eq!(&out[..2], &hex("ac 02")[..]);
+    assert_eq!(out.len(), 302);
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(cur.read_string().unwrap(), s);
+}
+
+// SELECT NULL::Nullable(UInt64), 7::Nullable(UInt64)
+#[test]
+fn nullable_marker_is_one_for_null() {
+    let mut out = Vec::new();
+    None::<u64>.encode_rowbinary(&mut out).unwrap();
+    Some(7u64).encode_rowbinary(&mut out).unwrap();
+    assert_eq!(out, hex("01 00 07 00 00 00 00 00 00 00"));
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(Option::<u64>::from_rowbinary(&mut cur).unwrap(), None);
+    assert_eq!(Option::<u64>::from_rowbinary(&mut cur).unwrap(), Some(7));
+}
+
+// SELECT [1, 2]::Array(UInt64)
+#[test]
+fn array_has_leb128_length() {
+    let mut out = Vec::new();
+    vec![1u64, 2].encode_rowbinary(&mut out).unwrap();
+    assert_eq!(
+        out,
+        hex("02 01 00 00 00 00 00 00 00 02 00 00 00 00 00 00 00")
+    );
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(Vec::<u64>::from_rowbinary(&mut cur).unwrap(), vec![1, 2]);
+}
+
+// SELECT toIPv4('1.2.3.4'), toIPv6('2001:db8::1')
+#[test]
+fn ip_addresses() {
+    let v4 = u32::from(std::net::Ipv4Addr::new(1, 2, 3, 4));
+    let v6 = u128::from("2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap());
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&v4.to_le_bytes());
+    ch_binary::write_ipv6(v6, &mut out);
+    assert_eq!(
+        out,
+        hex("04 03 02 01 20 01 0d b8 00 00 00 00 00 00 00 00 00 00 00 01")
+    );
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(cur.read_ipv4().unwrap(), v4);
+    assert_eq!(cur.read_ipv6().unwrap(), v6);
+}
+
+// SELECT toUUID('61f0c404-5cb3-11e7-907b-a6006ad3dba0')
+#[test]
+fn uuid_is_two_little_endian_halves() {
+    let canonical = hex("61 f0 c4 04 5c b3 11 e7 90 7b a6 00 6a d3 db a0");
+    let uuid: [u8; 16] = canonical.try_into().unwrap();
+
+    let mut out = Vec::new();
+    ch_binary::write_uuid(&uuid, &mut out);
+    assert_eq!(out, hex("e7 11 b3 5c 04 c4 f0 61 a0 db d3 6a 00 a6 7b 90"));
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(cur.read_uuid().unwrap(), uuid);
+}
+
+// SELECT toDateTime64('2024-01-01 00:00:00', 3, 'UTC')
+#[test]
+fn datetime64_is_ticks_only() {
+    let mut out = Vec::new();
+    ch_binary::write_datetime64(1_704_067_200_000, &mut out);
+    assert_eq!(out, hex("00 f4 51 c2 8c 01 00 00"));
+
+    let mut cur = RowBinCursor::new(&out);
+    assert_eq!(cur.read_datetime64().unwrap(), 1_704_067_200_000);
+}
+
+// SELECT 1::UInt8 AS x, 'a' AS yy FORMAT RowBinaryWithNamesAndTypes
+#[test]
+fn names_and_types_header() {
+    let bytes = hex("02 01 78 02 79 79 05 55 49 6e 74 38 06 53 74 72 69 6e 67 01 01 61");
+
+    let mut cur = RowBinCursor::new(&bytes);
+    let header = NamesAndTypes::read(&mut cur).unwrap();
+    assert_eq!(
+        header.columns,
+        vec![
+            ("x".to_string(), "UInt8".to_string()),
+            ("yy".to_string(), "String".to_string()),
+        ]
+    );
+    assert_eq!(cur.read_u8().unwrap(), 1);
+    assert_eq!(cur.read_str().unwrap(), "a");
+
+    let mut out = Vec::new();
+    header.write(&mut out);
+    assert_eq!(out, bytes[..cur.pos - 3]);
+}
+
+#[test]
+fn names_and_types_from_schema() {
+    let schema = Schema {
+        event: "login".to_string(),
+        version: 1,
+        evt_hash: [0; 32],
+        fields: vec![
+            Field {
+                name: "user".to_string(),
+                ty: FieldType::String,
+                nullable: false,
+            },
+            Field {
+                name: "at".to_string(),
+                ty: FieldType::DateTime64 { scale: 3 },
+                nullable: true,
+            },
+        ],
+    };
+
+    let header = NamesAndTypes::from_schema(&schema);
+    assert_eq!(header.names().collect::<Vec<_>>(), ["user", "at"]);
+    assert_eq!(header.columns[1].1, "Nullable(DateTime64(3))");
+
+    // a count the buffer cannot hold is rejected before allocating
+    let mut cur = RowBinCursor::new(&[0xff, 0xff, 0x03, 0x00]);
+    assert!(NamesAndTypes::read(&mut cur).is_err());
+}
+
+// SELECT toUUID('61f0c404-5cb3-11e7-907b-a6006ad3dba0'), 1700000000123::DateTime64(3),
+//        5::UInt128
+// FORMAT RowBinary
+#[test]
+fn infra_values_follow_the_row() {
+    let infra = InfraValues {
+        tenant_id: Some(
+            hex("61 f0 c4 04 5c b3 11 e7 90 7b a6 00 6a d3 db a0")
+                .try_into()
+                .unwrap(),
+        ),
+        ingest_ts: Some(1_700_000_000_123),
+        field_mask: Some(BitSet::from_words([5, 0])),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        infra.columns(),
+        [
+            ("tenant_id".to_string(), "UUID".to_string()),
+            ("ingest_ts".to_string(), "DateTime64(3)".to_string()),
+            ("field_mask".to_string(), "UInt128".to_string()),
+        ]
+    );
+
+    let mut out = Vec::new();
+    infra.write(&mut out);
+    assert_eq!(
+        out,
+        hex(concat!(
+            "e7 11 b3 5c 04 c4 f0 61 a0 db d3 6a 00 a6 7b 90 ",
+            "7b 68 e5 cf 8b 01 00 00 ",
+            "05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+        ))
+    );
+
+    assert!(InfraValues::default().columns().is_empty());
+}
+
+#[test]
+fn masks_keep_bits_past_the_first_word() {
+    let mask = BitSet::from_bits([3, 64, 127]).unwrap();
+    let infra = InfraValues {
+        system_mask: Some(mask),
+        struct_mask: BitSet::bit(100),
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    infra.write(&mut out);
+
+    let mut cur = RowBinCursor::new(&out);
+    let system = cur.read_u128().unwrap();
+    assert_eq!(system, 1 << 3 | 1 << 64 | 1 << 127);
+    assert_eq!(cur.read_u128().unwrap(), 1 << 100);
+    assert_eq!(cur.remaining(), 0);
+
+    let words = [system as u64, (system >> 64) as u64];
+    assert_eq!(BitSet::from_words(words), mask);
+}
+
+#[test]
+fn malformed_input_is_rejected() {
+    // 11-byte varint
+    let mut cur = RowBinCursor::new(&[0xFF; 11]);
+    assert!(cur.read_uvarint().is_err());
+
+    // u64::MAX is the largest valid 10-byte varint
+    let max = hex("ff ff ff ff ff ff ff ff ff 01");
+    assert_eq!(RowBinCursor::new(&max).read_uvarint().unwrap(), u64::MAX);
+    let over = hex("ff ff ff ff ff ff ff ff ff 02");
+    assert!(RowBinCursor::new(&over).read_uvarint().is_err());
+
+    // array length larger than the buffer
+    let mut cur = RowBinCursor::new(&[0x05, 0x00]);
+    assert!(Vec::<u64>::from_rowbinary(&mut cur).is_err());
+
+    // Nullable marker other than 0/1
+    let mut cur = RowBinCursor::new(&[0x02]);
+    assert!(Option::<u64>::from_rowbinary(&mut cur).is_err());
+}
@@ -1,5 +1,5 @@
 use ben_wire::{
-    Encoding,
+    Encoding, ch_binary,
     envelope::Envelope,
     rowbinary::{EncodeQuic, RowBinCursor, RowBinaryDecode, RowBinaryEncode, RowBinaryResult},
 };
@@ -14,7 +14,6 @@ struct TestEvent {
     ipv4: u32,
     ipv6: u128,
     dt_epoch: i64,
-    dt_scale: u32,
     uuid: [u8; 16],
 }
 
@@ -23,18 +22,14 @@ impl RowBinaryEncode for TestEvent {
         out.extend_from_slice(&self.a.to_le_bytes());
         out.push(self.b as u8);
 
-        // string: len + data
-        let bytes = self.c.as_bytes();
-        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
-        out.extend_from_slice(bytes);
+        ch_binary::write_str(&self.c, out);
 
         out.extend_from_slice(&self.d.to_le_bytes());
         out.extend_from_slice(&self.e.to_bits().to_le_bytes());
         out.extend_from_slice(&self.ipv4.to_le_bytes());
-        out.extend_from_slice(&self.ipv6.to_le_bytes());
-        out.extend_from_slice(&self.dt_epoch.to_le_bytes());
-        out.extend_from_slice(&self.dt_scale.to_le_bytes());
-        out.extend_from_slice(&self.uuid);
+        ch_binary::write_ipv6(self.ipv6, out);
+        ch_binary::write_datetime64(self.dt_epoch, out);
+        ch_binary::write_uuid(&self.uuid, out);
 
         Ok(())
     }
@@ -53,7 +48,7 @@ impl RowBinaryDecode for TestEvent {
         let ipv4 = cur.read_ipv4()?;
         let ipv6 = cur.read_ipv6()?;
 
-        let (dt_epoch, dt_scale) = cur.read_datetime64()?;
+        let dt_epoch = cur.read_datetime64()?;
         let uuid = cur.read_uuid()?;
 
         Ok(Self {
@@ -65,7 +60,6 @@ impl RowBinaryDecode for TestEvent {
             ipv4,
             ipv6,
             dt_epoch,
-            dt_scale,
             uuid,
         })
     }
@@ -77,11 +71,10 @@ fn make_event() -> TestEvent {
         b: true,
         c: "hello-world".into(),
         d: -1234567,
-        e: 3.14159,
+        e: 2.5,
         ipv4: 0x0A0B0C0D,
         ipv6: 0x11223344556677889900AABBCCDDEEFF_u128,
         dt_epoch: 1700000000,
-        dt_scale: 3,
         uuid: [
             0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
             0x0F, 0x10,
@@ -94,7 +87,7 @@ fn test_rowbinary_roundtrip() {
     let evt = make_event();
 
     let mut buf = Vec::new();
-    evt.encode_rowbinary(&mut buf);
+    evt.encode_rowbinary(&mut buf).unwrap();
 
     let mut cur = RowBinCursor { buf: &buf, pos: 0 };
     let decoded = TestEvent::from_rowbinary(&mut cur).unwrap();
@@ -107,7 +100,7 @@ fn test_truncated_decode_fails() {
     let evt = make_event();
 
     let mut buf = Vec::new();
-    evt.encode_rowbinary(&mut buf);
+    evt.encode_rowbinary(&mut buf).unwrap();
 
     let truncated = &buf[..5];
 
@@ -128,7 +121,7 @@ fn test_string_encoding() {
     };
 
     let mut buf = Vec::new();
-    evt.encode_rowbinary(&mut buf);
+    evt.encode_rowbinary(&mut buf).unwrap();
 
     let mut cur = RowBinCursor { buf: &buf, pos: 0 };
     let decoded = TestEvent::from_rowbinary(&mut cur).unwrap();
@@ -141,7 +134,7 @@ fn test_envelope_roundtrip() {
     let evt = make_event();
 
     let mut payload = Vec::new();
-    evt.encode_rowbinary(&mut payload);
+    evt.encode_rowbinary(&mut payload).unwrap();
 
     let epoch = 123456;
 
@@ -151,7 +144,8 @@ fn test_envelope_roundtrip() {
         TestEvent::EVT_HASH,
         epoch,
         TestEvent::FIELD_COUNT,
-    );
+    )
+    .unwrap();
     outer.extend_from_slice(&payload);
 
     // parse envelope
@@ -180,10 +174,10 @@ fn test_quic_encoding() {
     let evt = Q(make_event());
 
     let mut regular = Vec::new();
-    evt.0.encode_rowbinary(&mut regular);
+    evt.0.encode_rowbinary(&mut regular).unwrap();
 
     let mut quic = Vec::new();
-    evt.encode_quic(&mut quic);
+    evt.encode_quic(&mut quic).unwrap();
 
     assert_eq!(
         quic,
@@ -195,3 +189,19 @@ fn test_quic_encoding() {
         .concat()
     );
 }
+
+#[test]
+fn test_borrowed_reads_do_not_copy() {
+    let mut buf = Vec::new();
+    ch_binary::write_str("abc", &mut buf);
+    ch_binary::write_bytes(&[9, 8], &mut buf);
+
+    let mut cur = RowBinCursor::new(&buf);
+    let s = cur.read_str().unwrap();
+    let bytes = cur.read_byte_array().unwrap();
+
+    assert_eq!(s, "abc");
+    assert_eq!(bytes, &[9, 8]);
+    assert_eq!(s.as_ptr(), buf[1..].as_ptr());
+    assert!(cur.read_str().is_err());
+}
@@ -1,7 +1,12 @@
 use ben_wire::{
-    envelope::Envelope,
-    rowpack::{decode_rowpack, BenEncodeRowpack, Tag},
-    slot::SlotValue,
+    envelope::{Envelope, EnvelopeFlags},
+    error::RowpackError,
+    intern::{StrDict, StrInterner},
+    rowpack::{
+        BenEncodeRowpack, MAX_NESTING, RowpackCursor, Tag, decode_rowpack, decode_rowpack_interned,
+        encode_rowpack, encode_rowpack_interned,
+    },
+    slot::{SlotMap, SlotSeq, SlotValue},
 };
 
 struct Login {
@@ -38,7 +43,7 @@ fn rowpack_encode_decode_roundtrip() {
     };
 
     let epoch = 1234_u64;
-    let bytes = login.to_envelope_rowpack(epoch);
+    let bytes = login.to_envelope_rowpack(epoch).unwrap();
 
     // parse envelope
     let env = Envelope::parse(&bytes).expect("parse envelope");
@@ -53,3 +58,389 @@ fn rowpack_encode_decode_roundtrip() {
     assert!(matches!(row[1], SlotValue::U64(7)));
     assert!(matches!(row[2], SlotValue::Bool(true)));
 }
+
+#[test]
+fn rowpack_v2_frame_roundtrip() {
+    let login = Login {
+        heat: 9,
+        user_id: 11,
+        enabled: false,
+    };
+
+    let bytes = login
+        .to_frame_rowpack(77, EnvelopeFlags::SAMPLED)
+        .expect("frame");
+
+    let env = Envelope::parse(&bytes).expect("parse envelope");
+    assert_eq!(env.version, 2);
+    assert_eq!(env.field_count, Login::FIELD_COUNT);
+
+    let row = decode_rowpack(env.payload, env.field_count as usize).unwrap();
+    assert!(matches!(row[0], SlotValue::U64(9)));
+    assert!(matches!(row[1], SlotValue::U64(11)));
+    assert!(matches!(row[2], SlotValue::Bool(false)));
+}
+
+const ARRAY: &[u8] = &[Tag::U8 as u8, 1, Tag::U8 as u8, 2];
+const MAP: &[u8] = &[Tag::Str as u8, 1, 0, 0, 0, b'k', Tag::I8 as u8, 0xFF];
+
+fn sample_row() -> Vec<SlotValue<'static>> {
+    vec![
+        SlotValue::U64(u64::MAX),
+        SlotValue::I64(-42),
+        SlotValue::F64(2.5),
+        SlotValue::Bool(true),
+        SlotValue::Str("hostname-01"),
+        SlotValue::Missing,
+        SlotValue::IPv4(0x0A00_0001),
+        SlotValue::IPv6(0x2001_0db8_0000_0000_0000_0000_0000_0001),
+        SlotValue::DateTime64 {
+            epoch: 1_700_000_000_123,
+            scale: 3,
+        },
+        SlotValue::Uuid([7; 16]),
+        SlotValue::U32(u32::MAX),
+        SlotValue::U16(443),
+        SlotValue::U8(7),
+        SlotValue::I32(-70_000),
+        SlotValue::I16(-300),
+        SlotValue::I8(-1),
+        SlotValue::F32(0.5),
+        SlotValue::Decimal {
+            value: -123_456,
+            scale: 4,
+        },
+        SlotValue::Date(19_723),
+        SlotValue::Date32(-1),
+        SlotValue::Bytes(&[0, 0xFF, 0x80]),
+        SlotValue::Array(SlotSeq::new(2, ARRAY)),
+        SlotValue::Map(SlotMap::new(1, MAP)),
+    ]
+}
+
+#[test]
+fn slot_rows_roundtrip() {
+    let row = sample_row();
+    let mut buf = Vec::new();
+    encode_rowpack(&row, &mut buf).unwrap();
+
+    let decoded = decode_rowpack(&buf, row.len()).expect("decode");
+    assert_eq!(format!("{decoded:?}"), format!("{row:?}"));
+}
+
+#[test]
+fn every_truncation_is_an_error_not_a_panic() {
+    let row = sample_row();
+    let mut buf = Vec::new();
+    encode_rowpack(&row, &mut buf).unwrap();
+
+    for cut in 0..buf.len() {
+        let err = decode_rowpack(&buf[..cut], row.len()).unwrap_err();
+        assert!(
+            matches!(err, RowpackError::Truncated { offset, .. } if offset <= cut),
+            "cut at {cut}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn unknown_tag_is_reported() {
+    let mut buf = vec![Tag::U64 as u8];
+    buf.extend_from_slice(&1u64.to_le_bytes());
+    buf.push(0xEE);
+
+    let err = decode_rowpack(&buf, 2).unwrap_err();
+    assert_eq!(
+        err,
+        RowpackError::UnknownTag {
+            field: 1,
+            offset: 9,
+            tag: 0xEE
+        }
+    );
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+    let mut buf = Vec::new();
+    encode_rowpack(&[SlotValue::Bool(false)], &mut buf).unwrap();
+    buf.extend_from_slice(&[0, 0]);
+
+    let err = decode_rowpack(&buf, 1).unwrap_err();
+    assert_eq!(
+        err,
+        RowpackError::TrailingBytes {
+            offset: 2,
+            count: 2
+        }
+    );
+}
+
+#[test]
+fn oversized_string_length_is_truncation() {
+    let mut buf = vec![Tag::Str as u8];
+    buf.extend_from_slice(&u32::MAX.to_le_bytes());
+    buf.extend_from_slice(b"abc");
+
+    let err = decode_rowpack(&buf, 1).unwrap_err();
+    assert!(matches!(err, RowpackError::Truncated { field: 0, .. }));
+}
+
+#[test]
+fn invalid_utf8_is_rejected() {
+    let mut buf = vec![Tag::Str as u8];
+    buf.extend_from_slice(&2u32.to_le_bytes());
+    buf.extend_from_slice(&[0xC3, 0x28]);
+
+    let err = decode_rowpack(&buf, 1).unwrap_err();
+    assert_eq!(
+        err,
+        RowpackError::InvalidUtf8 {
+            field: 0,
+            offset: 0
+        }
+    );
+}
+
+#[test]
+fn cursor_reads_slot_by_slot() {
+    let mut buf = Vec::new();
+    encode_rowpack(&[SlotValue::I64(3), SlotValue::Str("x")], &mut buf).unwrap();
+
+    let mut cur = RowpackCursor::new(&buf);
+    assert!(matches!(cur.read_slot(0), Ok(SlotValue::I64(3))));
+    assert!(cur.finish().is_err());
+    assert!(matches!(cur.read_slot(1), Ok(SlotValue::Str("x"))));
+    assert_eq!(cur.remaining(), 0);
+    cur.finish().unwrap();
+}
+
+#[test]
+fn interned_strings_define_once_then_reference() {
+    let mut interner = StrInterner::new();
+    let mut dict = StrDict::new();
+
+    let rows = [
+        [SlotValue::Str("edge-01"), SlotValue::Str("/login")],
+        [SlotValue::Str("edge-01"), SlotValue::Str("/logout")],
+        [SlotValue::Str("edge-01"), SlotValue::Str("/login")],
+    ];
+
+    let mut sizes = Vec::new();
+    for row in &rows {
+        let mut buf = Vec::new();
+        encode_rowpack_interned(row, &mut interner, &mut buf).unwrap();
+        sizes.push(buf.len());
+
+        let decoded = decode_rowpack_interned(&buf, row.len(), &mut dict).unwrap();
+        for (got, want) in decoded.iter().zip(row) {
+            assert!(matches!(got, SlotValue::StrId(_)));
+            assert_eq!(got.as_str_in(&dict), want.as_str());
+        }
+    }
+
+    // once defined, each string is a tag and a u32 id
+    assert_eq!(sizes[2], 2 * 5);
+    assert!(sizes[0] > sizes[2]);
+    assert_eq!(interner.len(), 3);
+    assert_eq!(dict.len(), 3);
+}
+
+#[test]
+fn str_id_needs_a_dictionary() {
+    let mut interner = StrInterner::new();
+    let mut buf = Vec::new();
+    encode_rowpack_interned(&[SlotValue::Str("tenant-a")], &mut interner, &mut buf).unwrap();
+
+    assert_eq!(
+        decode_rowpack(&buf, 1).unwrap_err(),
+        RowpackError::NoDictionary {
+            field: 0,
+            offset: 0
+        }
+    );
+}
+
+#[test]
+fn undefined_str_id_is_rejected() {
+    let mut buf = Vec::new();
+    encode_rowpack(&[SlotValue::StrId(3)], &mut buf).unwrap();
+
+    let mut dict = StrDict::new();
+    assert_eq!(
+        decode_rowpack_interned(&buf, 1, &mut dict).unwrap_err(),
+        RowpackError::UnknownStrId {
+            field: 0,
+            offset: 0,
+            id: 3,
+            defined: 0
+        }
+    );
+}
+
+#[test]
+fn rejected_row_leaves_the_dictionary_untouched() {
+    let mut interner = StrInterner::new();
+    let mut buf = Vec::new();
+    encode_rowpack_interned(&[SlotValue::Str("tenant-a")], &mut interner, &mut buf).unwrap();
+
+    let mut dict = StrDict::new();
+    let mut trailing = buf.clone();
+    trailing.push(0);
+    assert!(matches!(
+        decode_rowpack_interned(&trailing, 1, &mut dict),
+        Err(RowpackError::TrailingBytes { .. })
+    ));
+    assert!(dict.is_empty());
+
+    // The sender's next try still defines id 0.
+    let row = decode_rowpack_interned(&buf, 1, &mut dict).unwrap();
+    assert!(matches!(row[0], SlotValue::StrId(0)));
+    assert_eq!(dict.get(0), Some("tenant-a"));
+}
+
+#[test]
+fn full_interner_falls_back_to_inline() {
+    let mut interner = StrInterner::with_limit(1);
+    let mut dict = StrDict::with_limit(1);
+
+    let row = [SlotValue::Str("a"), SlotValue::Str("b")];
+    let mut buf = Vec::new();
+    encode_rowpack_interned(&row, &mut interner, &mut buf).unwrap();
+
+    let decoded = decode_rowpack_interned(&buf, 2, &mut dict).unwrap();
+    assert!(matches!(decoded[0], SlotValue::StrId(0)));
+    assert!(matches!(decoded[1], SlotValue::Str("b")));
+}
+
+#[test]
+fn full_dictionary_rejects_definitions() {
+    let mut interner = StrInterner::new();
+    let mut buf = Vec::new();
+    encode_rowpack_interned(
+        &[SlotValue::Str("a"), SlotValue::Str("b")],
+        &mut interner,
+        &mut buf,
+    )
+    .unwrap();
+
+    let mut dict = StrDict::with_limit(1);
+    assert!(matches!(
+        decode_rowpack_interned(&buf, 2, &mut dict).unwrap_err(),
+        RowpackError::DictionaryFull { field: 1, .. }
+    ));
+}
+
+#[test]
+fn arrays_and_maps_nest() {
+    let mut inner = Vec::new();
+    encode_rowpack(&[SlotValue::I32(1), SlotValue::Missing], &mut inner).unwrap();
+    let mut outer = Vec::new();
+    encode_rowpack(
+        &[
+            SlotValue::Str("a"),
+            SlotValue::Array(SlotSeq::new(2, &inner)),
+        ],
+        &mut outer,
+    )
+    .unwrap();
+
+    let mut buf = Vec::new();
+    encode_rowpack(&[SlotValue::Map(SlotMap::new(1, &outer))], &mut buf).unwrap();
+
+    let row = decode_rowpack(&buf, 1).unwrap();
+    let SlotValue::Map(map) = row[0] else {
+        panic!("expected map, got {:?}", row[0]);
+    };
+    let entries: Vec<_> = map.iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(matches!(entries[0].0, SlotValue::Str("a")));
+
+    let SlotValue::Array(seq) = entries[0].1 else {
+        panic!("expected array");
+    };
+    let items: Vec<_> = seq.iter().collect::<Result<_, _>>().unwrap();
+    assert!(matches!(items[..], [SlotValue::I32(1), SlotValue::Missing]));
+}
+
+#[test]
+fn nested_errors_report_absolute_offsets() {
+    // [Bool(true), <bad tag>] inside an array that starts at byte 0
+    let body = [Tag::Bool as u8, 1, 0xEE];
+    let mut buf = vec![Tag::Array as u8];
+    buf.extend_from_slice(&2u32.to_le_bytes());
+    buf.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&body);
+
+    assert_eq!(
+        decode_rowpack(&buf, 1).unwrap_err(),
+        RowpackError::UnknownTag {
+            field: 0,
+            offset: 11,
+            tag: 0xEE
+        }
+    );
+}
+
+#[test]
+fn array_count_must_match_body() {
+    let body = [Tag::U8 as u8, 1, Tag::U8 as u8, 2];
+    for count in [1u32, 3, u32::MAX] {
+        let mut buf = vec![Tag::Array as u8];
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&body);
+
+        let err = decode_rowpack(&buf, 1).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RowpackError::BadNesting { .. } | RowpackError::Truncated { .. }
+            ),
+            "count {count}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn nesting_is_bounded() {
+    let mut buf = Vec::new();
+    encode_rowpack(&[SlotValue::Bool(true)], &mut buf).unwrap();
+    for _ in 0..=MAX_NESTING {
+        let inner = std::mem::take(&mut buf);
+        encode_rowpack(&[SlotValue::Array(SlotSeq::new(1, &inner))], &mut buf).unwrap();
+    }
+
+    assert!(matches!(
+        decode_rowpack(&buf, 1).unwrap_err(),
+        RowpackError::TooDeep { field: 0, .. }
+    ));
+}
+
+#[test]
+fn oversized_lengths_are_refused() {
+    // Zeroed pages are mapped lazily, so this never touches 4 GiB.
+    let huge = vec![0u8; u32::MAX as usize + 1];
+    let mut buf = vec![0xAA];
+    let err = encode_rowpack(&[SlotValue::Bytes(&huge)], &mut buf).unwrap_err();
+    assert_eq!(err, RowpackError::TooLong { len: huge.len() });
+    assert_eq!(buf, [0xAA]);
+}
+
+#[test]
+fn failed_row_does_not_keep_its_string_ids() {
+    let huge = vec![0u8; u32::MAX as usize + 1];
+    let mut interner = StrInterner::new();
+    let mut dropped = Vec::new();
+    let failed = [SlotValue::Str("tenant-a"), SlotValue::Bytes(&huge)];
+    assert!(encode_rowpack_interned(&failed, &mut interner, &mut dropped).is_err());
+    assert!(interner.is_empty());
+
+    // The next row defines the string again instead of sending a bare id.
+    let mut buf = Vec::new();
+    encode_rowpack_interned(&[SlotValue::Str("tenant-a")], &mut interner, &mut buf).unwrap();
+    let mut dict = StrDict::new();
+    let row = decode_rowpack_interned(&buf, 1, &mut dict).unwrap();
+    assert!(matches!(row[0], SlotValue::StrId(0)));
+    assert_eq!(dict.get(0), Some("tenant-a"));
+}
@@ -0,0 +1,93 @@
+use ben_wire::{
+    error::SchemaError,
+    schema::{Field, FieldType},
+};
+
+#[test]
+fn ch_type_names_round_trip() {
+    let types = [
+        FieldType::UInt8,
+        FieldType::UInt128,
+        FieldType::Int64,
+        FieldType::Float32,
+        FieldType::Decimal {
+            precision: 18,
+            scale: 4,
+        },
+        FieldType::Bool,
+        FieldType::IPv6,
+        FieldType::Date32,
+        FieldType::DateTime64 { scale: 9 },
+        FieldType::Uuid,
+        FieldType::LowCardinality(Box::new(FieldType::String)),
+        FieldType::Array(Box::new(FieldType::Nullable(Box::new(FieldType::Int32)))),
+        FieldType::Map(
+            Box::new(FieldType::String),
+            Box::new(FieldType::Map(
+                Box::new(FieldType::UInt16),
+                Box::new(FieldType::Array(Box::new(FieldType::Float64))),
+            )),
+        ),
+        FieldType::Tuple(vec![
+            ("host".into(), FieldType::String),
+            (
+                "geo".into(),
+                FieldType::Tuple(vec![
+                    (String::new(), FieldType::Float64),
+                    (String::new(), FieldType::Float64),
+                ]),
+            ),
+        ]),
+        FieldType::Array(Box::new(FieldType::Tuple(vec![(
+            "ts".into(),
+            FieldType::DateTime64 { scale: 3 },
+        )]))),
+        FieldType::Enum8(vec![("low".into(), -1), ("it's, (high)".into(), 2)]),
+        FieldType::Map(
+            Box::new(FieldType::String),
+            Box::new(FieldType::Enum16(vec![(r"a\b".into(), 1000)])),
+        ),
+    ];
+    for ty in types {
+        assert_eq!(ty.ch_type().parse::<FieldType>(), Ok(ty));
+    }
+}
+
+#[test]
+fn field_from_ch_type_lifts_nullable_and_ignores_timezone() {
+    let f = Field::from_ch_type("note", "Nullable(String)").unwrap();
+    assert!(f.nullable);
+    assert_eq!(f.ty, FieldType::String);
+    assert_eq!(f.ch_type(), "Nullable(String)");
+
+    let f = Field::from_ch_type("ts", " DateTime64(3, 'Europe/Oslo') ").unwrap();
+    assert!(!f.nullable);
+    assert_eq!(f.ty, FieldType::DateTime64 { scale: 3 });
+}
+
+#[test]
+fn enum_labels_are_quoted() {
+    let ty = FieldType::Enum8(vec![("low".into(), 1), ("it's".into(), 2)]);
+    assert_eq!(ty.ch_type(), r"Enum8('low' = 1, 'it\'s' = 2)");
+}
+
+#[test]
+fn unsupported_ch_types_are_rejected() {
+    for bad in [
+        "DateTime",
+        "Array(UInt64",
+        "Decimal(x, 2)",
+        "Map(String)",
+        "Tuple()",
+        "Tuple(a Blob)",
+        "Enum8()",
+        "Enum8(low = 1)",
+        "Enum8('low' = 300)",
+        "Enum16('low')",
+    ] {
+        assert!(matches!(
+            bad.parse::<FieldType>(),
+            Err(SchemaError::UnknownType(_))
+        ));
+    }
+}
@@ -1,80 +1,470 @@
-// use ben_wire::{serde_bridge::RowpackStructDe, slot::SlotValue};
-// use serde::Deserialize;
-
-// #[derive(Debug, Deserialize, PartialEq)]
-// struct LoginView {
-//     user: String,
-//     heat: u32,
-//     ip: Option<String>, // keep it simple for now
-// }
-
-// #[test]
-// fn serde_bridge_maps_row_to_struct() {
-//     // Pretend schema.fields = ["user","heat","ip"]
-//     let field_names = vec!["user", "heat", "ip"];
-
-//     let row = vec![
-//         SlotValue::Str("alice"),    // user
-//         SlotValue::U64(200),        // heat
-//         SlotValue::Str("10.1.2.3"), // ip
-//     ];
-
-//     let de = RowpackStructDe {
-//         row: &row,
-//         field_names: &field_names,
-//     };
-
-//     let view: LoginView = serde::Deserialize::deserialize(de).expect("deserialize LoginView");
-
-//     assert_eq!(
-//         view,
-//         LoginView {
-//             user: "alice".to_string(),
-//             heat: 200,
-//             ip: Some("10.1.2.3".to_string()),
-//         }
-//     );
-// }
-
-// #[test]
-// fn serde_bridge_handles_missing_as_option_none() {
-//     let field_names = vec!["user", "heat", "ip"];
-
-//     let row = vec![
-//         SlotValue::Str("bob"),
-//         SlotValue::U64(50),
-//         SlotValue::Missing, // no ip
-//     ];
-
-//     let de = RowpackStructDe {
-//         row: &row,
-//         field_names: &field_names,
-//     };
-
-//     let view: LoginView = serde::Deserialize::deserialize(de).expect("deserialize LoginView");
-
-//     assert_eq!(
-//         view,
-//         LoginView {
-//             user: "bob".to_string(),
-//             heat: 50,
-//             ip: None,
-//         }
-//     );
-// }
-
-// use ben_wire::serde_bridge::SlotValueDeserializer;
-// use serde::de::DeserializeSeed;
-
-// #[test]
-// fn slotvalue_deserializer_numeric_coercions() {
-//     // u64 -> u64
-//     let d = SlotValueDeserializer(SlotValue::U64(42));
-//     let v: u64 = serde::Deserialize::deserialize(d).expect("u64 from U64");
-//     assert_eq!(v, 42);
-
-//     // U64  u32 via Serde
-//     let d = SlotValueDeserializer(SlotValue::U64(7));
-//     let v: u32 = serde::Deserialize::deserialize(d).expect("u32 from U64");
-//     assert_eq!(v, 7);
-// }
+use std::net::Ipv4Addr;
+
+use ben_wire::{
+    intern::{StrDict, StrInterner},
+    rowpack::{decode_rowpack_interned, encode_rowpack_interned},
+    schema::{Field, FieldType, Schema},
+    serde_bridge::{RowpackStructDe, SlotValueDeserializer, from_row, to_row, to_rowpack},
+    slot::SlotValue,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct LoginView {
+    user: String,
+    heat: u32,
+    ip: Option<String>, // keep it simple for now
+}
+
+fn field(name: &str, ty: FieldType, nullable: bool) -> Field {
+    Field {
+        name: name.to_string(),
+        ty,
+        nullable,
+    }
+}
+
+fn login_schema() -> Schema {
+    Schema {
+        event: "login".to_string(),
+        version: 1,
+        evt_hash: [0x42; 32],
+        fields: vec![
+            field("user", FieldType::String, false),
+            field("heat", FieldType::UInt64, false),
+            field("ip", FieldType::String, true),
+        ],
+    }
+}
+
+#[test]
+fn serde_bridge_maps_row_to_struct() {
+    let schema = login_schema();
+
+    let row = vec![
+        SlotValue::Str("alice"),    // user
+        SlotValue::U64(200),        // heat
+        SlotValue::Str("10.1.2.3"), // ip
+    ];
+
+    let de = RowpackStructDe::new(&schema, &row);
+    let view: LoginView = serde::Deserialize::deserialize(de).expect("deserialize LoginView");
+
+    assert_eq!(
+        view,
+        LoginView {
+            user: "alice".to_string(),
+            heat: 200,
+            ip: Some("10.1.2.3".to_string()),
+        }
+    );
+}
+
+#[test]
+fn serde_bridge_handles_missing_as_option_none() {
+    let schema = login_schema();
+
+    let row = vec![
+        SlotValue::Str("bob"),
+        SlotValue::U64(50),
+        SlotValue::Missing, // no ip
+    ];
+
+    let view: LoginView = from_row(&schema, &row).expect("deserialize LoginView");
+
+    assert_eq!(
+        view,
+        LoginView {
+            user: "bob".to_string(),
+            heat: 50,
+            ip: None,
+        }
+    );
+}
+
+#[test]
+fn slotvalue_deserializer_numeric_coercions() {
+    // u64 -> u64
+    let d = SlotValueDeserializer(SlotValue::U64(42));
+    let v: u64 = serde::Deserialize::deserialize(d).expect("u64 from U64");
+    assert_eq!(v, 42);
+
+    // U64 -> u32 via serde
+    let d = SlotValueDeserializer(SlotValue::U64(7));
+    let v: u32 = serde::Deserialize::deserialize(d).expect("u32 from U64");
+    assert_eq!(v, 7);
+
+    // out of range is an error, not a truncation
+    let d = SlotValueDeserializer(SlotValue::U64(1 << 40));
+    assert!(u32::deserialize(d).is_err());
+
+    let d = SlotValueDeserializer(SlotValue::U64(3));
+    let v: f64 = serde::Deserialize::deserialize(d).expect("f64 from U64");
+    assert_eq!(v, 3.0);
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Borrowed<'a> {
+    user: &'a str,
+    heat: u64,
+}
+
+#[test]
+fn borrows_strings_and_ignores_unknown_columns() {
+    let schema = login_schema();
+    let row = vec![
+        SlotValue::Str("carol"),
+        SlotValue::U64(1),
+        SlotValue::Str("10.0.0.1"),
+    ];
+
+    let view: Borrowed = from_row(&schema, &row).unwrap();
+    assert_eq!(
+        view,
+        Borrowed {
+            user: "carol",
+            heat: 1
+        }
+    );
+}
+
+#[test]
+fn row_length_must_match_schema() {
+    let schema = login_schema();
+    let row = vec![SlotValue::Str("dave")];
+
+    assert!(from_row::<LoginView>(&schema, &row).is_err());
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Outcome {
+    Ok,
+    Denied,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Access {
+    outcome: Outcome,
+    src: Ipv4Addr,
+    bytes: u64,
+    ratio: f64,
+    tag: Option<String>,
+}
+
+fn access_schema() -> Schema {
+    Schema {
+        event: "access".to_string(),
+        version: 1,
+        evt_hash: [0x24; 32],
+        fields: vec![
+            field("bytes", FieldType::UInt64, false),
+            field("outcome", FieldType::String, false),
+            field("ratio", FieldType::Float64, false),
+            field("src", FieldType::IPv4, false),
+            field("tag", FieldType::String, true),
+        ],
+    }
+}
+
+#[test]
+fn serializer_produces_rows_in_schema_order() {
+    let schema = access_schema();
+    let access = Access {
+        outcome: Outcome::Denied,
+        src: Ipv4Addr::new(10, 1, 2, 3),
+        bytes: 512,
+        ratio: 0.25,
+        tag: None,
+    };
+
+    let mut buf = Vec::new();
+    let row = to_row(&access, &schema, &mut buf).unwrap();
+
+    assert!(matches!(row[0], SlotValue::U64(512)));
+    assert!(matches!(row[1], SlotValue::Str("Denied")));
+    assert!(matches!(row[2], SlotValue::F64(r) if r == 0.25));
+    assert!(matches!(row[3], SlotValue::IPv4(ip) if ip == u32::from(access.src)));
+    assert!(matches!(row[4], SlotValue::Missing));
+
+    let back: Access = from_row(&schema, &row).unwrap();
+    assert_eq!(back, access);
+}
+
+#[test]
+fn serializer_rejects_bad_rows() {
+    let schema = access_schema();
+
+    #[derive(Serialize)]
+    struct Partial {
+        bytes: u64,
+    }
+    let mut out = Vec::new();
+    let err = to_rowpack(&Partial { bytes: 1 }, &schema, &mut out).unwrap_err();
+    assert!(err.to_string().contains("not nullable"));
+    assert!(out.is_empty());
+
+    #[derive(Serialize)]
+    struct Extra {
+        bogus: u64,
+    }
+    let err = to_rowpack(&Extra { bogus: 1 }, &schema, &mut out).unwrap_err();
+    assert!(err.to_string().contains("not in the schema"));
+
+    assert!(to_rowpack(&7u64, &schema, &mut out).is_err());
+}
+
+#[test]
+fn interned_strings_resolve_through_the_dictionary() {
+    let schema = login_schema();
+    let mut interner = StrInterner::new();
+    let mut dict = StrDict::new();
+
+    let mut buf = Vec::new();
+    let row = [
+        SlotValue::Str("erin"),
+        SlotValue::U64(9),
+        SlotValue::Missing,
+    ];
+    encode_rowpack_interned(&row, &mut interner, &mut buf).unwrap();
+    let decoded = decode_rowpack_interned(&buf, 3, &mut dict).unwrap();
+    assert!(matches!(decoded[0], SlotValue::StrId(0)));
+
+    assert!(from_row::<LoginView>(&schema, &decoded).is_err());
+
+    let de = RowpackStructDe::new(&schema, &decoded).with_dict(&dict);
+    let view = LoginView::deserialize(de).unwrap();
+    assert_eq!(view.user, "erin");
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Flow {
+    port: u16,
+    ttl: u8,
+    delta: i32,
+    loss: f32,
+    price: String,
+    payload: Vec<u8>,
+    hops: Vec<u32>,
+    labels: std::collections::BTreeMap<String, Option<i64>>,
+    region: String,
+    day: u16,
+}
+
+fn flow_schema() -> Schema {
+    Schema {
+        event: "flow".to_string(),
+        version: 1,
+        evt_hash: [0x31; 32],
+        fields: vec![
+            field("port", FieldType::UInt16, false),
+            field("ttl", FieldType::UInt8, false),
+            field("delta", FieldType::Int32, false),
+            field("loss", FieldType::Float32, false),
+            field(
+                "price",
+                FieldType::Decimal {
+                    precision: 9,
+                    scale: 2,
+                },
+                false,
+            ),
+            field("payload", FieldType::Bytes, false),
+            field("hops", FieldType::Array(Box::new(FieldType::UInt32)), false),
+            field(
+                "labels",
+                FieldType::Map(
+                    Box::new(FieldType::String),
+                    Box::new(FieldType::Nullable(Box::new(FieldType::Int64))),
+                ),
+                false,
+            ),
+            field(
+                "region",
+                FieldType::LowCardinality(Box::new(FieldType::String)),
+                false,
+            ),
+            field("day", FieldType::Date, false),
+        ],
+    }
+}
+
+#[test]
+fn narrow_decimal_and_nested_columns_roundtrip() {
+    let schema = flow_schema();
+    let flow = Flow {
+        port: 443,
+        ttl: 64,
+        delta: -7,
+        loss: 0.25,
+        price: "-12.50".to_string(),
+        payload: vec![0, 0xFF],
+        hops: vec![1, 2, 3],
+        labels: [("a".to_string(), Some(1)), ("b".to_string(), None)].into(),
+        region: "eu".to_string(),
+        day: 19_723,
+    };
+
+    let mut buf = Vec::new();
+    let row = to_row(&flow, &schema, &mut buf).unwrap();
+
+    assert!(matches!(row[0], SlotValue::U16(443)));
+    assert!(matches!(row[1], SlotValue::U8(64)));
+    assert!(matches!(row[2], SlotValue::I32(-7)));
+    assert!(matches!(row[3], SlotValue::F32(v) if v == 0.25));
+    assert!(matches!(
+        row[4],
+        SlotValue::Decimal {
+            value: -1250,
+            scale: 2
+        }
+    ));
+    assert!(matches!(row[5], SlotValue::Bytes(&[0, 0xFF])));
+    assert!(matches!(row[6], SlotValue::Array(seq) if seq.len() == 3));
+    assert!(matches!(row[7], SlotValue::Map(map) if map.len() == 2));
+    assert!(matches!(row[8], SlotValue::Str("eu")));
+    assert!(matches!(row[9], SlotValue::Date(19_723)));
+
+    let back: Flow = from_row(&schema, &row).unwrap();
+    assert_eq!(
+        back,
+        Flow {
+            price: "-12.50".to_string(),
+            ..flow
+        }
+    );
+}
+
+#[test]
+fn narrow_columns_reject_out_of_range_values() {
+    let schema = Schema {
+        event: "n".to_string(),
+        version: 1,
+        evt_hash: [0; 32],
+        fields: vec![
+            field("small", FieldType::UInt8, true),
+            field(
+                "money",
+                FieldType::Decimal {
+                    precision: 4,
+                    scale: 2,
+                },
+                true,
+            ),
+        ],
+    };
+
+    #[derive(Serialize)]
+    struct Small {
+        small: u64,
+    }
+    #[derive(Serialize)]
+    struct Money<'a> {
+        money: &'a str,
+    }
+
+    let mut out = Vec::new();
+    assert!(to_rowpack(&Small { small: 256 }, &schema, &mut out).is_err());
+    assert!(to_rowpack(&Small { small: 255 }, &schema, &mut out).is_ok());
+
+    for bad in ["100.00", "1.234", "1e3", "", "-"] {
+        assert!(
+            to_rowpack(&Money { money: bad }, &schema, &mut out).is_err(),
+            "{bad:?}"
+        );
+    }
+    assert!(to_rowpack(&Money { money: "99.99" }, &schema, &mut out).is_ok());
+}
+
+#[test]
+fn enum_columns_take_labels_or_values() {
+    let schema = Schema {
+        event: "e".to_string(),
+        version: 1,
+        evt_hash: [0; 32],
+        fields: vec![
+            field(
+                "outcome",
+                FieldType::Enum8(vec![("Allowed".into(), 1), ("Denied".into(), 2)]),
+                false,
+            ),
+            field(
+                "code",
+                FieldType::Enum16(vec![("ok".into(), 200), ("gone".into(), 410)]),
+                true,
+            ),
+        ],
+    };
+
+    #[derive(Serialize)]
+    struct Row<'a> {
+        outcome: Outcome,
+        code: Option<&'a str>,
+    }
+    #[derive(Serialize)]
+    struct Raw {
+        outcome: i64,
+        code: Option<u16>,
+    }
+
+    let mut buf = Vec::new();
+    let row = to_row(
+        &Row {
+            outcome: Outcome::Denied,
+            code: Some("gone"),
+        },
+        &schema,
+        &mut buf,
+    )
+    .unwrap();
+    assert!(matches!(row[0], SlotValue::I8(2)));
+    assert!(matches!(row[1], SlotValue::I16(410)));
+
+    let mut out = Vec::new();
+    let raw = |outcome, code| Raw { outcome, code };
+    assert!(to_rowpack(&raw(1, None), &schema, &mut out).is_ok());
+    assert!(to_rowpack(&raw(3, None), &schema, &mut out).is_err());
+    assert!(to_rowpack(&raw(1, Some(404)), &schema, &mut out).is_err());
+    let unknown = Row {
+        outcome: Outcome::Denied,
+        code: Some("teapot"),
+    };
+    assert!(to_rowpack(&unknown, &schema, &mut out).is_err());
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Stamped {
+    ts: i64,
+}
+
+#[test]
+fn datetime64_reads_as_epoch_nanos() {
+    let schema = Schema {
+        event: "stamped".to_string(),
+        version: 1,
+        evt_hash: [0x11; 32],
+        fields: vec![field("ts", FieldType::DateTime64 { scale: 3 }, false)],
+    };
+    let row = [SlotValue::DateTime64 {
+        epoch: 1_700_000_000_123,
+        scale: 3,
+    }];
+    let nanos = 1_700_000_000_123_000_000;
+
+    let stamped: Stamped = from_row(&schema, &row).unwrap();
+    assert_eq!(stamped.ts, nanos);
+    assert_eq!(
+        ben_wire::view::field::<i64>(&schema, &row, "ts").unwrap(),
+        nanos
+    );
+
+    // Written back, nanoseconds truncate to the column's milliseconds.
+    let mut buf = Vec::new();
+    let back = to_row(&Stamped { ts: nanos + 999 }, &schema, &mut buf).unwrap();
+    assert!(matches!(
+        back[0],
+        SlotValue::DateTime64 {
+            epoch: 1_700_000_000_123,
+            scale: 3
+        }
+    ));
+}
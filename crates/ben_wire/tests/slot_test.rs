@@ -0,0 +1,104 @@
+use ben_wire::{
+    error::SlotError,
+    slot::{Coercion, SlotValue},
+};
+
+const STRICT: Coercion = Coercion::Strict;
+const LENIENT: Coercion = Coercion::Lenient;
+
+#[test]
+fn integers_widen_losslessly() {
+    assert_eq!(SlotValue::U8(7).as_u64(), Ok(7));
+    assert_eq!(SlotValue::I16(-3).as_i64(), Ok(-3));
+    assert_eq!(SlotValue::U32(u32::MAX).as_i64(), Ok(u32::MAX as i64));
+    assert_eq!(SlotValue::U64(42).as_f64(), Ok(42.0));
+    assert_eq!(SlotValue::I64(-(1 << 53)).as_f64(), Ok(-(2f64.powi(53))));
+    assert_eq!(SlotValue::F32(0.5).as_f64(), Ok(0.5));
+}
+
+#[test]
+fn strict_refuses_lossy_conversions() {
+    assert_eq!(
+        SlotValue::U64((1 << 53) + 1).as_f64(),
+        Err(SlotError::OutOfRange {
+            expected: "f64",
+            got: "u64"
+        })
+    );
+    assert_eq!(
+        SlotValue::I64(-1).as_u64(),
+        Err(SlotError::OutOfRange {
+            expected: "u64",
+            got: "i64"
+        })
+    );
+    assert_eq!(
+        SlotValue::F64(3.0).as_u64(),
+        Err(SlotError::TypeMismatch {
+            expected: "u64",
+            got: "f64"
+        })
+    );
+    assert!(SlotValue::Str("1").as_f64().is_err());
+    assert!(SlotValue::U8(1).as_bool().is_err());
+    assert!(SlotValue::Missing.as_str().is_err());
+}
+
+#[test]
+fn lenient_rounds_and_parses() {
+    assert_eq!(
+        SlotValue::U64((1 << 53) + 1).coerce_f64(LENIENT),
+        Ok(2f64.powi(53))
+    );
+    assert_eq!(SlotValue::F64(3.0).coerce_u64(LENIENT), Ok(3));
+    assert!(SlotValue::F64(3.5).coerce_u64(LENIENT).is_err());
+    assert_eq!(
+        SlotValue::Decimal {
+            value: 1250,
+            scale: 2
+        }
+        .coerce_f64(LENIENT),
+        Ok(12.5)
+    );
+    assert_eq!(SlotValue::Str(" 42 ").coerce_i64(LENIENT), Ok(42));
+    assert_eq!(SlotValue::Str("2.5").coerce_f64(LENIENT), Ok(2.5));
+    assert_eq!(SlotValue::Str("true").coerce_bool(LENIENT), Ok(true));
+    assert_eq!(SlotValue::U8(0).coerce_bool(LENIENT), Ok(false));
+    assert!(SlotValue::U8(2).coerce_bool(LENIENT).is_err());
+    assert_eq!(SlotValue::Bytes(b"abc").coerce_str(LENIENT), Ok("abc"));
+    assert!(SlotValue::Bytes(b"abc").coerce_str(STRICT).is_err());
+}
+
+#[test]
+fn timestamps_become_epoch_nanos() {
+    let ms = SlotValue::DateTime64 {
+        epoch: 1_704_067_200_123,
+        scale: 3,
+    };
+    assert_eq!(ms.as_epoch_nanos(), Ok(1_704_067_200_123_000_000));
+    assert_eq!(SlotValue::Date(1).as_epoch_nanos(), Ok(86_400_000_000_000));
+
+    let ps = SlotValue::DateTime64 {
+        epoch: 1_001,
+        scale: 12,
+    };
+    assert!(ps.as_epoch_nanos().is_err());
+    assert_eq!(ps.coerce_epoch_nanos(LENIENT), Ok(1));
+
+    let far = SlotValue::DateTime64 {
+        epoch: i64::MAX,
+        scale: 0,
+    };
+    assert!(matches!(
+        far.as_epoch_nanos(),
+        Err(SlotError::OutOfRange { .. })
+    ));
+}
+
+#[test]
+fn ipv4_widens_to_mapped_ipv6() {
+    let v4 = SlotValue::IPv4(u32::from(std::net::Ipv4Addr::new(10, 0, 0, 1)));
+    let mapped: std::net::Ipv6Addr = "::ffff:10.0.0.1".parse().unwrap();
+    assert_eq!(v4.as_ipv6(), Ok(u128::from(mapped)));
+    assert!(SlotValue::U32(1).as_ipv6().is_err());
+}
@@ -9,6 +9,7 @@ fn main() -> Result<()> {
         Commands::Init { path } => cmd_init(path),
         Commands::Build { path } => cmd_build(path),
         Commands::Inspect { path, kind } => cmd_inspect(path, kind),
+        Commands::Compat { old, new } => cmd_compat(old, new),
     }
 }
 
@@ -42,6 +43,10 @@ enum Commands {
         #[arg(default_value = "summary")]
         kind: InspectKind,
     },
+
+    /// Classify the changes between two BenSchema manifest JSON files.
+    /// Exits non-zero when any change is breaking.
+    Compat { old: PathBuf, new: PathBuf },
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -82,3 +87,24 @@ fn cmd_inspect(path: PathBuf, kind: InspectKind) -> Result<()> {
 
     Ok(())
 }
+
+fn cmd_compat(old: PathBuf, new: PathBuf) -> Result<()> {
+    let report = ben_contracts::check_manifests(
+        &std::fs::read_to_string(&old)?,
+        &std::fs::read_to_string(&new)?,
+    )?;
+
+    println!(
+        "{}: v{} -> v{}",
+        report.table, report.from_version, report.to_version
+    );
+    for c in &report.changes {
+        println!("  [{}] {}", c.compat, c.change);
+    }
+    println!("compatibility: {}", report.compat());
+
+    if report.is_breaking() {
+        anyhow::bail!("breaking schema change for {}", report.table);
+    }
+    Ok(())
+}
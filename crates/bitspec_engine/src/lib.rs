@@ -1,4 +1,5 @@
 pub mod pack;
+pub mod plan;
 pub mod policy;
 pub mod predicate;
 pub mod threshold;
@@ -6,11 +7,12 @@ pub mod threshold;
 use std::collections::HashMap;
 
 use crate::predicate::PredOp;
-use ben_wire::slot::SlotValue;
+use ben_wire::slot::{Coercion, SlotValue};
 
-pub type Bit = u16;
+pub use ben_wire::bits::{Bit, DEFAULT_WORDS};
 
-pub type BitMask = u64;
+/// `WORDS * 64` bits wide; a derived pack picks it with `#[bspec(width = N)]`.
+pub type BitMask<const WORDS: usize = DEFAULT_WORDS> = ben_wire::bits::BitSet<WORDS>;
 
 pub type FactMap = HashMap<&'static str, FactValue>;
 
@@ -21,43 +23,53 @@ pub enum FactValue {
     Str(String),
 }
 
+/// A predicate bound to its field's index in a schema; see [`plan::RowPlan`].
 #[derive(Debug)]
 pub struct Rule<'a> {
+    /// The field's name.
     pub name: &'static str,
     pub pred: PredOp<'a>,
     pub field_idx: usize,
+    pub bit: Bit,
 }
 
 pub trait RowAccess {
     fn get_slot(&self, field_id: &str) -> Option<&SlotValue>;
 
+    /// How slots convert for the typed getters and predicates. Strict still
+    /// widens losslessly, so `gt(10)` fires on a `U64` field.
+    #[inline]
+    fn coercion(&self) -> Coercion {
+        Coercion::Strict
+    }
+
     #[inline]
     fn get_f64(&self, field_id: &str) -> Option<f64> {
         let s = self.get_slot(field_id)?;
-        s.as_f64().ok().copied()
+        s.coerce_f64(self.coercion()).ok()
     }
 
     #[inline]
     fn get_u64(&self, field_id: &str) -> Option<u64> {
         let s = self.get_slot(field_id)?;
-        s.as_u64().ok().copied()
+        s.coerce_u64(self.coercion()).ok()
     }
 
     #[inline]
     fn get_i64(&self, field_id: &str) -> Option<i64> {
         let s = self.get_slot(field_id)?;
-        s.as_i64().ok().copied()
+        s.coerce_i64(self.coercion()).ok()
     }
 
     #[inline]
     fn get_str(&self, field_id: &str) -> Option<&str> {
         let s = self.get_slot(field_id)?;
-        s.as_str().ok()
+        s.coerce_str(self.coercion()).ok()
     }
 
     #[inline]
     fn get_bool(&self, field_id: &str) -> Option<bool> {
         let s = self.get_slot(field_id)?;
-        s.as_bool().ok().copied()
+        s.coerce_bool(self.coercion()).ok()
     }
 }
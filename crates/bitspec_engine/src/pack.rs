@@ -1,15 +1,52 @@
-use crate::{BitMask, FactMap, RowAccess, predicate::PredicateSpec, threshold::ThresholdSpec};
+use std::fmt;
 
+use ben_wire::{Schema, slot::Coercion};
+
+use crate::{
+    Bit, BitMask, DEFAULT_WORDS, FactMap, RowAccess, Rule,
+    plan::{BindError, BoundOp, RowPlan},
+    predicate::PredicateSpec,
+    threshold::ThresholdSpec,
+};
+
+/// Every bit index should be below `WORDS * 64`; the derive rejects structs
+/// that allocate more, and [`BitspecPack::bind`] rejects hand-built packs.
+/// [`BitspecPack::eval`] skips bits that do not fit.
 #[derive(Debug)]
-pub struct BitspecPack {
+pub struct BitspecPack<const WORDS: usize = DEFAULT_WORDS> {
     pub predicates: &'static [PredicateSpec<'static>],
     pub thresholds: &'static [ThresholdSpec],
     pub bit_count: u16,
+    /// One entry per bit, in bit order.
+    pub bits: &'static [BitInfo],
+}
+
+/// What one bit of a pack's mask means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitInfo {
+    pub bit: Bit,
+    pub rule: &'static str,
+    /// Set for threshold bits.
+    pub level: Option<&'static str>,
+    pub field: &'static str,
+    /// The op as written in `#[bspec]`, e.g. `not(eq("tls"))` or `gte(100)`.
+    pub op: &'static str,
+}
+
+/// `LATENCY.SLOW: latency_ms gte(100)`
+impl fmt::Display for BitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.rule)?;
+        if let Some(level) = self.level {
+            write!(f, ".{level}")?;
+        }
+        write!(f, ": {} {}", self.field, self.op)
+    }
 }
 
-impl BitspecPack {
-    pub fn eval<R: RowAccess + ?Sized>(&self, row: &R) -> (BitMask, FactMap) {
-        let mut mask: BitMask = 0;
+impl<const WORDS: usize> BitspecPack<WORDS> {
+    pub fn eval<R: RowAccess + ?Sized>(&self, row: &R) -> (BitMask<WORDS>, FactMap) {
+        let mut mask = BitMask::EMPTY;
         let mut facts = FactMap::default();
 
         self.eval_preds(row, &mut mask);
@@ -18,17 +55,119 @@ impl BitspecPack {
         (mask, facts)
     }
 
-    fn eval_preds<R: RowAccess + ?Sized>(&self, row: &R, mask: &mut BitMask) {
+    pub fn info(&self, bit: Bit) -> Option<&'static BitInfo> {
+        self.bits.get(usize::from(bit))
+    }
+
+    /// The entries for the bits set in `mask`, lowest first.
+    pub fn matched(&self, mask: &BitMask<WORDS>) -> impl Iterator<Item = &'static BitInfo> {
+        let bits = self.bits;
+        mask.ones().filter_map(move |b| bits.get(usize::from(b)))
+    }
+
+    /// One line per matched bit; see [`BitInfo`]'s `Display`.
+    pub fn explain(&self, mask: &BitMask<WORDS>) -> Vec<String> {
+        self.matched(mask).map(ToString::to_string).collect()
+    }
+
+    /// Resolves every predicate and threshold field in `schema`, reporting
+    /// all the missing ones at once.
+    pub fn bind(&self, schema: &Schema) -> Result<RowPlan<WORDS>, BindError> {
+        self.check_bits()?;
+        self.check_patterns()?;
+
+        let mut unknown = Vec::new();
+        let mut index = |field: &'static str| {
+            let idx = schema.index_of(field);
+            if idx.is_none() && !unknown.contains(&field) {
+                unknown.push(field);
+            }
+            idx
+        };
+
+        let rules: Vec<_> = self
+            .predicates
+            .iter()
+            .filter_map(|p| {
+                let field_idx = index(p.field_id);
+                let op = BoundOp::bind(&p.op, &mut index);
+                let rule = Rule {
+                    name: p.field_id,
+                    pred: p.op.clone(),
+                    field_idx: field_idx?,
+                    bit: p.bit,
+                };
+                Some((rule, op?))
+            })
+            .collect();
+        let thresholds: Vec<_> = self
+            .thresholds
+            .iter()
+            .filter_map(|th| Some((index(th.field_id)?, th)))
+            .collect();
+
+        if !unknown.is_empty() {
+            return Err(BindError::UnknownFields {
+                event: schema.event.clone(),
+                fields: unknown,
+            });
+        }
+        Ok(RowPlan {
+            rules,
+            thresholds,
+            coercion: Coercion::Strict,
+            field_count: schema.fields.len(),
+        })
+    }
+
+    fn check_bits(&self) -> Result<(), BindError> {
+        let preds = self.predicates.iter().map(|p| (p.field_id, p.bit));
+        let levels = self
+            .thresholds
+            .iter()
+            .flat_map(|th| th.levels.iter().map(|l| (th.field_id, l.bit)));
+        match preds
+            .chain(levels)
+            .find(|&(_, bit)| usize::from(bit) >= BitMask::<WORDS>::WIDTH)
+        {
+            Some((rule, bit)) => Err(BindError::BitOutOfRange {
+                rule,
+                bit,
+                width: BitMask::<WORDS>::WIDTH,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_patterns(&self) -> Result<(), BindError> {
+        match self
+            .predicates
+            .iter()
+            .find_map(|p| Some((p.field_id, p.op.bad_pattern()?)))
+        {
+            Some((rule, (pattern, error))) => Err(BindError::BadPattern {
+                rule,
+                pattern,
+                error: error.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn eval_preds<R: RowAccess + ?Sized>(&self, row: &R, mask: &mut BitMask<WORDS>) {
         for pred in self.predicates {
-            if let Some(slot) = row.get_slot(pred.field_id) {
-                if pred.op.eval(slot) {
-                    *mask |= pred.bit;
-                }
+            if pred.op.eval_row(row.get_slot(pred.field_id), row) {
+                mask.set(pred.bit);
             }
         }
     }
 
-    fn eval_thresh<R: RowAccess + ?Sized>(&self, row: &R, mask: &mut BitMask, facts: &mut FactMap) {
+    fn eval_thresh<R: RowAccess + ?Sized>(
+        &self,
+        row: &R,
+        mask: &mut BitMask<WORDS>,
+        facts: &mut FactMap,
+    ) {
         for th in self.thresholds {
             th.eval(row, mask, facts);
         }
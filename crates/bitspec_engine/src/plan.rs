@@ -0,0 +1,192 @@
+//! A [`BitspecPack`](crate::pack::BitspecPack) bound to one
+//! [`Schema`](ben_wire::Schema). [`BitspecPack::bind`](crate::pack::BitspecPack::bind)
+//! resolves the field names once; rows are then read by index.
+
+use ben_wire::slot::{Coercion, SlotValue};
+
+use crate::{
+    Bit, BitMask, DEFAULT_WORDS, FactMap, Rule,
+    predicate::{ArithOp, CmpOp, Detached, NumExpr, PredOp},
+    threshold::ThresholdSpec,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BindError {
+    #[error("bitspec fields not in schema `{event}`: {}", fields.join(", "))]
+    UnknownFields {
+        event: String,
+        fields: Vec<&'static str>,
+    },
+
+    #[error("bitspec rule `{rule}` uses bit {bit}, past the {width}-bit mask")]
+    BitOutOfRange {
+        rule: &'static str,
+        bit: Bit,
+        width: usize,
+    },
+
+    #[error("bitspec rule `{rule}` has a regex that does not parse: /{pattern}/: {error}")]
+    BadPattern {
+        rule: &'static str,
+        pattern: &'static str,
+        error: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct RowPlan<const WORDS: usize = DEFAULT_WORDS> {
+    pub(crate) rules: Vec<(Rule<'static>, BoundOp<'static>)>,
+    /// Field index and spec.
+    pub(crate) thresholds: Vec<(usize, &'static ThresholdSpec)>,
+    pub(crate) coercion: Coercion,
+    pub(crate) field_count: usize,
+}
+
+impl<const WORDS: usize> RowPlan<WORDS> {
+    /// Strict unless set; see [`RowAccess::coercion`](crate::RowAccess::coercion).
+    pub fn with_coercion(mut self, coercion: Coercion) -> Self {
+        self.coercion = coercion;
+        self
+    }
+
+    /// Fields of the bound schema, so of every row.
+    pub fn field_count(&self) -> usize {
+        self.field_count
+    }
+
+    /// Same result as [`BitspecPack::eval`](crate::pack::BitspecPack::eval)
+    /// on the row. A `Missing` slot, or one past the end, fires nothing.
+    pub fn eval(&self, row: &[SlotValue]) -> (BitMask<WORDS>, FactMap) {
+        debug_assert_eq!(
+            row.len(),
+            self.field_count,
+            "row does not match the bound schema"
+        );
+        let slot = |idx: usize| row.get(idx).filter(|s| !matches!(s, SlotValue::Missing));
+
+        let mut mask = BitMask::EMPTY;
+        let mut facts = FactMap::default();
+
+        for (rule, op) in &self.rules {
+            if op.eval(row.get(rule.field_idx), row, self.coercion) {
+                mask.set(rule.bit);
+            }
+        }
+
+        for &(idx, th) in &self.thresholds {
+            if let Some(val) = slot(idx).and_then(|s| s.coerce_f64(self.coercion).ok()) {
+                th.apply(val, &mut mask, &mut facts);
+            }
+        }
+
+        (mask, facts)
+    }
+}
+
+/// A [`PredOp`] whose cross-field reads are resolved to column indices.
+/// Ops that only read their own slot are kept as they are.
+#[derive(Debug)]
+pub(crate) enum BoundOp<'a> {
+    Slot(&'a PredOp<'a>),
+    Compare {
+        lhs: BoundExpr,
+        op: CmpOp,
+        rhs: BoundExpr,
+    },
+    All(Vec<BoundOp<'a>>),
+    Any(Vec<BoundOp<'a>>),
+    Not(Box<BoundOp<'a>>),
+}
+
+/// A [`NumExpr`] reading columns by index.
+#[derive(Debug)]
+pub(crate) enum BoundExpr {
+    Column(usize),
+    Const(f64),
+    Abs(Box<BoundExpr>),
+    Arith {
+        op: ArithOp,
+        lhs: Box<BoundExpr>,
+        rhs: Box<BoundExpr>,
+    },
+}
+
+impl<'a> BoundOp<'a> {
+    /// `None` if a field has no index. Every field is still passed to
+    /// `index`, so all the unknown ones get reported.
+    pub(crate) fn bind(
+        op: &'a PredOp<'a>,
+        index: &mut impl FnMut(&'static str) -> Option<usize>,
+    ) -> Option<Self> {
+        let mut list = |ops: &'a [PredOp<'a>]| {
+            let bound: Vec<_> = ops.iter().map(|p| Self::bind(p, index)).collect();
+            bound.into_iter().collect::<Option<Vec<_>>>()
+        };
+        Some(match op {
+            PredOp::Compare { lhs, op, rhs } => {
+                let (lhs, rhs) = (BoundExpr::bind(lhs, index), BoundExpr::bind(rhs, index));
+                BoundOp::Compare {
+                    lhs: lhs?,
+                    op: *op,
+                    rhs: rhs?,
+                }
+            }
+            PredOp::All(ops) => BoundOp::All(list(ops)?),
+            PredOp::Any(ops) => BoundOp::Any(list(ops)?),
+            PredOp::Not(inner) => BoundOp::Not(Box::new(Self::bind(inner, index)?)),
+            op => BoundOp::Slot(op),
+        })
+    }
+
+    /// Same result as [`PredOp::eval_row`] over the named row.
+    fn eval(&self, slot: Option<&SlotValue>, row: &[SlotValue], mode: Coercion) -> bool {
+        let present = slot.is_some_and(|s| !matches!(s, SlotValue::Missing));
+        match self {
+            BoundOp::Slot(op) => op.eval_row(slot, &Detached(mode)),
+            BoundOp::Compare { lhs, op, rhs } => {
+                present
+                    && lhs
+                        .eval(row, mode)
+                        .zip(rhs.eval(row, mode))
+                        .is_some_and(|(a, b)| op.apply(a, b))
+            }
+            BoundOp::All(ops) => ops.iter().all(|p| p.eval(slot, row, mode)),
+            BoundOp::Any(ops) => ops.iter().any(|p| p.eval(slot, row, mode)),
+            BoundOp::Not(inner) => present && !inner.eval(slot, row, mode),
+        }
+    }
+}
+
+impl BoundExpr {
+    fn bind(
+        expr: &NumExpr<'_>,
+        index: &mut impl FnMut(&'static str) -> Option<usize>,
+    ) -> Option<Self> {
+        Some(match expr {
+            NumExpr::Field(f) => BoundExpr::Column(index(f)?),
+            NumExpr::Const(v) => BoundExpr::Const(*v),
+            NumExpr::Abs(x) => BoundExpr::Abs(Box::new(Self::bind(x, index)?)),
+            NumExpr::Arith { op, lhs, rhs } => {
+                let (lhs, rhs) = (Self::bind(lhs, index), Self::bind(rhs, index));
+                BoundExpr::Arith {
+                    op: *op,
+                    lhs: Box::new(lhs?),
+                    rhs: Box::new(rhs?),
+                }
+            }
+        })
+    }
+
+    /// Same result as [`NumExpr::eval`].
+    fn eval(&self, row: &[SlotValue], mode: Coercion) -> Option<f64> {
+        match self {
+            BoundExpr::Column(idx) => row.get(*idx)?.coerce_f64(mode).ok(),
+            BoundExpr::Const(v) => Some(*v),
+            BoundExpr::Abs(x) => x.eval(row, mode).map(f64::abs),
+            BoundExpr::Arith { op, lhs, rhs } => {
+                let (a, b) = (lhs.eval(row, mode)?, rhs.eval(row, mode)?);
+                op.apply(a, b)
+            }
+        }
+    }
+}
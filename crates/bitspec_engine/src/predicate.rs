@@ -1,50 +1,374 @@
-use ben_wire::slot::SlotValue;
+use std::{fmt, sync::OnceLock};
+
+use ben_wire::slot::{Coercion, SlotValue};
+use regex::Regex;
+
+use crate::{Bit, RowAccess};
 
 #[derive(Debug, Clone)]
 pub enum PredOp<'a> {
     EqF64(f64),
     GtF64(f64),
     LtF64(f64),
-    BetweenF64 { lo: f64, hi: f64 },
+    BetweenF64 {
+        lo: f64,
+        hi: f64,
+    },
 
     EqBool(bool),
 
     EqStr(&'static str),
     NeStr(&'static str),
     StartsWith(&'static str),
+    EndsWith(&'static str),
     Contains(&'static str),
+    Matches(&'a Pattern),
+    /// `*` matches any run of characters, `?` exactly one.
+    Glob(&'static str),
+    /// Sorted and deduplicated.
+    InStrSet(&'a [&'static str]),
+    /// Sorted and deduplicated.
+    InIntSet(&'a [i64]),
+    /// IPv4 slots and networks are compared as IPv4-mapped IPv6.
+    InCidr(&'a [Cidr]),
+
+    ModEq {
+        m: u64,
+        r: u64,
+    },
+    ModNe {
+        m: u64,
+        r: u64,
+    },
 
-    ModEq { m: u64, r: u64 },
-    ModNe { m: u64, r: u64 },
+    /// Numeric comparison that may read other fields of the row.
+    Compare {
+        lhs: &'a NumExpr<'a>,
+        op: CmpOp,
+        rhs: &'a NumExpr<'a>,
+    },
+
+    /// The field is in the row but holds no value (`SlotValue::Missing`).
+    IsNull,
+    /// The field is null or not in the row at all.
+    IsMissing,
 
     All(&'a [PredOp<'a>]),
     Any(&'a [PredOp<'a>]),
     Not(&'a PredOp<'a>),
 }
 
+/// A regex compiled on first use. The derive checks that it parses; a
+/// hand-built one that doesn't never matches and fails
+/// [`BitspecPack::bind`](crate::pack::BitspecPack::bind). [`Pattern::try_new`]
+/// reports it up front.
+pub struct Pattern {
+    src: &'static str,
+    re: OnceLock<Option<Regex>>,
+}
+
+impl Pattern {
+    pub const fn new(src: &'static str) -> Self {
+        Self {
+            src,
+            re: OnceLock::new(),
+        }
+    }
+
+    /// Compiles now, reporting a regex that does not parse.
+    pub fn try_new(src: &'static str) -> Result<Self, regex::Error> {
+        let re = Regex::new(src)?;
+        Ok(Self {
+            src,
+            re: OnceLock::from(Some(re)),
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.src
+    }
+
+    /// Why the pattern never matches, if it does not parse.
+    pub fn check(&self) -> Result<(), regex::Error> {
+        match self.compiled() {
+            Some(_) => Ok(()),
+            None => Regex::new(self.src).map(drop),
+        }
+    }
+
+    pub fn is_match(&self, s: &str) -> bool {
+        self.compiled().is_some_and(|re| re.is_match(s))
+    }
+
+    fn compiled(&self) -> Option<&Regex> {
+        self.re.get_or_init(|| Regex::new(self.src).ok()).as_ref()
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}/", self.src)
+    }
+}
+
+/// An IPv6 network; IPv4 ones are stored IPv4-mapped (`::ffff:0:0/96`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    /// Host bits are zero.
+    pub net: u128,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: u128) -> bool {
+        let mask = u128::MAX
+            .checked_shl(128u32.saturating_sub(self.prefix.into()))
+            .unwrap_or(0);
+        ip & mask == self.net
+    }
+}
+
+/// `*` and `?` over chars, backtracking only to the last `*`. All-ASCII
+/// input is compared byte by byte.
+fn glob_match(pat: &str, s: &str) -> bool {
+    if pat.is_ascii() && s.is_ascii() {
+        glob_walk(pat.as_bytes(), s.as_bytes(), |t, i| {
+            t.get(i).map(|&b| (char::from(b), 1))
+        })
+    } else {
+        glob_walk(pat, s, |t, i| {
+            t[i..].chars().next().map(|c| (c, c.len_utf8()))
+        })
+    }
+}
+
+/// Walks byte offsets into `pat` and `s`; `next` reads the char at an offset
+/// and its width.
+fn glob_walk<T: ?Sized>(pat: &T, s: &T, next: impl Fn(&T, usize) -> Option<(char, usize)>) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Pattern offset after the last `*`, and where in `s` it started matching.
+    let mut star: Option<(usize, usize)> = None;
+    while let Some((c, width)) = next(s, i) {
+        match next(pat, p) {
+            Some(('*', w)) => {
+                p += w;
+                star = Some((p, i));
+            }
+            Some((pc, w)) if pc == '?' || pc == c => {
+                p += w;
+                i += width;
+            }
+            _ => match star {
+                Some((sp, si)) => {
+                    p = sp;
+                    i = si + next(s, si).map_or(1, |(_, w)| w);
+                    star = Some((sp, i));
+                }
+                None => return false,
+            },
+        }
+    }
+    while let Some(('*', w)) = next(pat, p) {
+        p += w;
+    }
+    next(pat, p).is_none()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    Ne,
+}
+
+impl CmpOp {
+    pub fn apply(self, a: f64, b: f64) -> bool {
+        match self {
+            CmpOp::Gt => a > b,
+            CmpOp::Gte => a >= b,
+            CmpOp::Lt => a < b,
+            CmpOp::Lte => a <= b,
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    /// `None` when dividing by zero, so a ratio over an empty field fires
+    /// nothing.
+    Div,
+}
+
+impl ArithOp {
+    pub fn apply(self, a: f64, b: f64) -> Option<f64> {
+        match self {
+            ArithOp::Add => Some(a + b),
+            ArithOp::Sub => Some(a - b),
+            ArithOp::Mul => Some(a * b),
+            ArithOp::Div => (b != 0.0).then(|| a / b),
+        }
+    }
+}
+
+/// A number computed from the row's fields.
+#[derive(Debug, Clone)]
+pub enum NumExpr<'a> {
+    Field(&'static str),
+    Const(f64),
+    Abs(&'a NumExpr<'a>),
+    Arith {
+        op: ArithOp,
+        lhs: &'a NumExpr<'a>,
+        rhs: &'a NumExpr<'a>,
+    },
+}
+
+impl NumExpr<'_> {
+    /// `None` when a field is missing or not numeric under the row's
+    /// coercion.
+    pub fn eval<R: RowAccess + ?Sized>(&self, row: &R) -> Option<f64> {
+        match self {
+            NumExpr::Field(f) => row.get_f64(f),
+            NumExpr::Const(v) => Some(*v),
+            NumExpr::Abs(x) => x.eval(row).map(f64::abs),
+            NumExpr::Arith { op, lhs, rhs } => {
+                let (a, b) = (lhs.eval(row)?, rhs.eval(row)?);
+                op.apply(a, b)
+            }
+        }
+    }
+
+    fn fields(&self, out: &mut Vec<&'static str>) {
+        match self {
+            NumExpr::Field(f) => {
+                if !out.contains(f) {
+                    out.push(f);
+                }
+            }
+            NumExpr::Const(_) => {}
+            NumExpr::Abs(x) => x.fields(out),
+            NumExpr::Arith { lhs, rhs, .. } => {
+                lhs.fields(out);
+                rhs.fields(out);
+            }
+        }
+    }
+}
+
+/// No other fields; cross-field ops see nothing.
+pub(crate) struct Detached(pub(crate) Coercion);
+
+impl RowAccess for Detached {
+    fn get_slot(&self, _field_id: &str) -> Option<&SlotValue<'_>> {
+        None
+    }
+
+    fn coercion(&self) -> Coercion {
+        self.0
+    }
+}
+
 impl<'a> PredOp<'a> {
     pub fn eval(&self, slot: &SlotValue) -> bool {
+        self.eval_with(slot, Coercion::Strict)
+    }
+
+    /// Evaluates against the one slot; cross-field ops don't fire.
+    pub fn eval_with(&self, slot: &SlotValue, mode: Coercion) -> bool {
+        self.eval_row(Some(slot), &Detached(mode))
+    }
+
+    /// The first regex in this op, nested ones included, that does not parse.
+    pub(crate) fn bad_pattern(&self) -> Option<(&'static str, regex::Error)> {
         match self {
-            PredOp::EqF64(v) => slot.as_f64().map(|x| x == v).unwrap_or(false),
-            PredOp::GtF64(v) => slot.as_f64().map(|x| x > v).unwrap_or(false),
-            PredOp::LtF64(v) => slot.as_f64().map(|x| x < v).unwrap_or(false),
-            PredOp::BetweenF64 { lo, hi } => {
-                slot.as_f64().map(|x| x >= lo && x <= hi).unwrap_or(false)
-            }
+            PredOp::Matches(p) => p.check().err().map(|e| (p.as_str(), e)),
+            PredOp::All(ops) | PredOp::Any(ops) => ops.iter().find_map(Self::bad_pattern),
+            PredOp::Not(inner) => inner.bad_pattern(),
+            _ => None,
+        }
+    }
+
+    /// `slot` is the predicate's own field, `None` when it isn't in the row.
+    /// A null or absent field fires only `is_null`/`is_missing` (and `all`/
+    /// `any` over them); every other op, `not(...)` included, is false.
+    pub fn eval_row<R: RowAccess + ?Sized>(&self, slot: Option<&SlotValue>, row: &R) -> bool {
+        let slot = match slot {
+            Some(SlotValue::Missing) | None => return self.on_missing(slot.is_some()),
+            Some(s) => s,
+        };
+        let mode = row.coercion();
+        let f64_ = || slot.coerce_f64(mode);
+        let str_ = || slot.coerce_str(mode);
+        let u64_ = || slot.coerce_u64(mode);
+
+        match self {
+            PredOp::EqF64(v) => f64_().map(|x| x == *v).unwrap_or(false),
+            PredOp::GtF64(v) => f64_().map(|x| x > *v).unwrap_or(false),
+            PredOp::LtF64(v) => f64_().map(|x| x < *v).unwrap_or(false),
+            PredOp::BetweenF64 { lo, hi } => f64_().map(|x| x >= *lo && x <= *hi).unwrap_or(false),
 
-            PredOp::EqBool(v) => slot.as_bool().map(|x| *x == *v).unwrap_or(false),
+            PredOp::EqBool(v) => slot.coerce_bool(mode).map(|x| x == *v).unwrap_or(false),
 
-            PredOp::EqStr(s) => slot.as_str().map(|x| x == *s).unwrap_or(false),
-            PredOp::NeStr(s) => slot.as_str().map(|x| x != *s).unwrap_or(false),
-            PredOp::StartsWith(s) => slot.as_str().map(|x| x.starts_with(s)).unwrap_or(false),
-            PredOp::Contains(s) => slot.as_str().map(|x| x.contains(s)).unwrap_or(false),
+            PredOp::EqStr(s) => str_().map(|x| x == *s).unwrap_or(false),
+            PredOp::NeStr(s) => str_().map(|x| x != *s).unwrap_or(false),
+            PredOp::StartsWith(s) => str_().map(|x| x.starts_with(s)).unwrap_or(false),
+            PredOp::EndsWith(s) => str_().map(|x| x.ends_with(s)).unwrap_or(false),
+            PredOp::Contains(s) => str_().map(|x| x.contains(s)).unwrap_or(false),
+            PredOp::Matches(re) => str_().is_ok_and(|x| re.is_match(x)),
+            PredOp::Glob(g) => str_().is_ok_and(|x| glob_match(g, x)),
+            PredOp::InStrSet(set) => str_().is_ok_and(|x| set.binary_search(&x).is_ok()),
+            PredOp::InIntSet(set) => slot
+                .coerce_i64(mode)
+                .is_ok_and(|x| set.binary_search(&x).is_ok()),
+            PredOp::InCidr(nets) => slot
+                .coerce_ipv6(mode)
+                .is_ok_and(|ip| nets.iter().any(|n| n.contains(ip))),
 
-            PredOp::ModEq { m, r } => slot.as_u64().map(|x| x % *m == *r).unwrap_or(false),
-            PredOp::ModNe { m, r } => slot.as_u64().map(|x| x % *m != *r).unwrap_or(false),
+            PredOp::ModEq { m, r } => u64_().map(|x| x % *m == *r).unwrap_or(false),
+            PredOp::ModNe { m, r } => u64_().map(|x| x % *m != *r).unwrap_or(false),
 
-            PredOp::All(list) => list.iter().all(|p| p.eval(slot)),
-            PredOp::Any(list) => list.iter().any(|p| p.eval(slot)),
-            PredOp::Not(inner) => !inner.eval(slot),
+            PredOp::Compare { lhs, op, rhs } => lhs
+                .eval(row)
+                .zip(rhs.eval(row))
+                .is_some_and(|(a, b)| op.apply(a, b)),
+
+            PredOp::IsNull | PredOp::IsMissing => false,
+
+            PredOp::All(list) => list.iter().all(|p| p.eval_row(Some(slot), row)),
+            PredOp::Any(list) => list.iter().any(|p| p.eval_row(Some(slot), row)),
+            PredOp::Not(inner) => !inner.eval_row(Some(slot), row),
+        }
+    }
+
+    fn on_missing(&self, null: bool) -> bool {
+        match self {
+            PredOp::IsNull => null,
+            PredOp::IsMissing => true,
+            PredOp::All(list) => list.iter().all(|p| p.on_missing(null)),
+            PredOp::Any(list) => list.iter().any(|p| p.on_missing(null)),
+            _ => false,
+        }
+    }
+
+    /// Fields the op reads through the row (its own too, when named), each
+    /// once.
+    pub fn fields(&self, out: &mut Vec<&'static str>) {
+        match self {
+            PredOp::Compare { lhs, rhs, .. } => {
+                lhs.fields(out);
+                rhs.fields(out);
+            }
+            PredOp::All(list) | PredOp::Any(list) => list.iter().for_each(|p| p.fields(out)),
+            PredOp::Not(inner) => inner.fields(out),
+            _ => {}
         }
     }
 }
@@ -52,6 +376,6 @@ impl<'a> PredOp<'a> {
 #[derive(Debug, Clone)]
 pub struct PredicateSpec<'a> {
     pub field_id: &'static str,
-    pub bit: u64,
+    pub bit: Bit,
     pub op: PredOp<'a>,
 }
@@ -1,4 +1,4 @@
-use crate::{BitMask, FactMap, FactValue, RowAccess};
+use crate::{Bit, BitMask, FactMap, FactValue, RowAccess};
 
 #[derive(Debug, Clone)]
 pub enum ThresholdOp {
@@ -13,7 +13,7 @@ pub enum ThresholdOp {
 pub struct ThresholdLevel {
     pub name: &'static str,
     pub value: f64,
-    pub bit: u16, // bit index
+    pub bit: Bit,
 }
 
 #[derive(Debug, Clone)]
@@ -31,16 +31,24 @@ pub struct ThresholdSpec {
 }
 
 impl ThresholdSpec {
-    pub fn eval<R: RowAccess + ?Sized>(
+    pub fn eval<R: RowAccess + ?Sized, const WORDS: usize>(
         &self,
         row: &R,
-        out_mask: &mut BitMask,
+        out_mask: &mut BitMask<WORDS>,
         out_facts: &mut FactMap,
     ) {
-        let Some(val) = row.get_f64(self.field_id) else {
-            return;
-        };
+        if let Some(val) = row.get_f64(self.field_id) {
+            self.apply(val, out_mask, out_facts);
+        }
+    }
 
+    /// Sets the bits and facts for the field's value.
+    pub fn apply<const WORDS: usize>(
+        &self,
+        val: f64,
+        out_mask: &mut BitMask<WORDS>,
+        out_facts: &mut FactMap,
+    ) {
         let mut triggered: Vec<&'static str> = Vec::new();
 
         for lvl in self.levels {
@@ -53,7 +61,7 @@ impl ThresholdSpec {
             };
 
             if pass {
-                *out_mask |= (1u64 << lvl.bit);
+                out_mask.set(lvl.bit);
                 triggered.push(lvl.name);
             }
         }
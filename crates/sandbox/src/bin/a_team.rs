@@ -1,39 +1,41 @@
-// use anyhow::Result;
-// use ben_sandbox::SandboxEvent;
-// use ben_wire::rowbinary::EncodeQuic;
-// use tokio::io::AsyncWriteExt;
-// use tokio::net::TcpStream;
-
-// #[tokio::main]
-// async fn main() -> Result<()> {
-//     let addr = "127.0.0.1:41000";
-
-//     let mut stream = TcpStream::connect(addr).await?;
-
-//     let evt = SandboxEvent {
-//         id: 42,
-//         kind: "sandbox.demo".into(),
-//         ts: 1_700_000_000,
-//         value: 123.45,
-//         flag: true,
-//     };
-
-//     // Encode RowBinary payload
-//     let mut payload = Vec::new();
-//     evt.encode_quic(&mut payload);
-
-//     // Prepend u32 length prefix
-//     let len = payload.len() as u32;
-//     let mut frame = Vec::new();
-//     frame.extend_from_slice(&len.to_le_bytes());
-//     frame.extend_from_slice(&payload);
-
-//     stream.write_all(&frame).await?;
-//     stream.flush().await?;
-
-//     Ok(())
-// }
-
-fn main() {
-    println!("Hello, world!");
+use anyhow::Result;
+use ben_sandbox::{ADDR, SandboxEvent};
+use ben_wire::batch::{BatchEncoder, Push};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+const EVENTS: u64 = 1_000;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut stream = TcpStream::connect(ADDR).await?;
+
+    // Batches cap out at 16 KiB or 256 rows, whichever comes first.
+    let mut batch = BatchEncoder::<SandboxEvent>::new(16 * 1024, 256);
+    let mut frames = 0;
+
+    for id in 0..EVENTS {
+        let evt = SandboxEvent {
+            id,
+            kind: "sandbox.demo".into(),
+            ts: 1_700_000_000 + id,
+            value: id as f64 * 0.5,
+            flag: id % 2 == 0,
+        };
+
+        if batch.push(&evt)? == Push::Full {
+            stream.write_all(&batch.finish()).await?;
+            frames += 1;
+            batch.push(&evt)?;
+        }
+    }
+
+    if !batch.is_empty() {
+        stream.write_all(&batch.finish()).await?;
+        frames += 1;
+    }
+    stream.flush().await?;
+
+    println!("a_team: sent {EVENTS} events in {frames} batches");
+    Ok(())
 }
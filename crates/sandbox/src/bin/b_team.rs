@@ -1,33 +1,47 @@
 use anyhow::Result;
-use ben_wire::Envelope;
+use ben_sandbox::{ADDR, SandboxEvent};
+use ben_wire::batch::{Batch, BatchHeader};
 use tokio::io::AsyncReadExt;
 use tokio::net::TcpListener;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let addr = "127.0.0.1:54321";
-    let listener = TcpListener::bind(addr).await?;
-    println!("b_team: listening on {}", addr);
+    let listener = TcpListener::bind(ADDR).await?;
+    println!("b_team: listening on {}", ADDR);
 
     let (mut socket, peer) = listener.accept().await?;
     println!("b_team: accepted connection from {}", peer);
 
-    let mut len_buf = [0u8; 4];
-    socket.read_exact(&mut len_buf).await?;
-    let payload_len = u32::from_le_bytes(len_buf) as usize;
-
-    println!("b_team: expecting payload of {} bytes", payload_len);
-
-    let mut payload = vec![0u8; payload_len];
-    socket.read_exact(&mut payload).await?;
-
-    println!("b_team: received payload, decoding...");
-
-    let evt = Envelope::parse(&payload)?;
-    println!("b_team: got event hash {:x?}", evt.evt_hash);
-    println!("b_team: got event hash {:x?}", evt.epoch);
-    println!("b_team: got event hash {:x?}", evt.encoding);
-    println!("b_team: got event hash {:x?}", evt.payload);
-
+    let mut frame = vec![0u8; BatchHeader::LEN];
+    let mut total = 0usize;
+
+    loop {
+        frame.resize(BatchHeader::LEN, 0);
+        match socket.read_exact(&mut frame).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        let header = BatchHeader::parse(&frame)?;
+        frame.resize(header.frame_len(), 0);
+        socket.read_exact(&mut frame[BatchHeader::LEN..]).await?;
+
+        let batch = Batch::parse(&frame)?;
+        let mut last = None;
+        for row in batch.rows::<SandboxEvent>()? {
+            last = Some(row?);
+            total += 1;
+        }
+
+        println!(
+            "b_team: batch of {} rows ({} bytes), last {:?}",
+            header.row_count,
+            header.frame_len(),
+            last
+        );
+    }
+
+    println!("b_team: received {} events", total);
     Ok(())
 }
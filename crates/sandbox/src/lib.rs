@@ -1,2 +1,5 @@
-// pub mod schema;
-// pub use schema::SandboxEvent;
+pub mod schema;
+pub use schema::SandboxEvent;
+
+/// Address b_team listens on and a_team connects to.
+pub const ADDR: &str = "127.0.0.1:41000";